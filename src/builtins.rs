@@ -1,20 +1,54 @@
-use std::sync::Arc;
+use std::{path::PathBuf, sync::Arc};
 
 use async_trait::async_trait;
 
 use crate::{
     error::Result,
     registry,
-    shell::{PtyShell, ShellSpec},
+    repl::{ShareMode, panes::ViewRequest, viewers::Viewer},
+    runtime::ReplSettings,
+    shell::{Shell, ShellSpec, expect::Script},
 };
 
 pub mod admin;
+pub mod alias;
+pub mod args;
+pub mod expect;
 pub mod format;
+pub mod help;
 pub mod local;
+pub mod pane;
 pub mod quit;
 pub mod remote;
+pub mod secret;
+pub mod set;
+pub mod var;
 
+pub use args::BuiltinUsage;
 pub use format::format_shell_line;
+pub use set::BuiltinSet;
+
+#[async_trait]
+pub trait Builtin: Send + Sync {
+    fn name(&self) -> &str;
+
+    fn aliases(&self) -> &[&str] {
+        &[]
+    }
+
+    fn usage(&self) -> &BuiltinUsage;
+
+    fn completions(&self) -> Vec<String> {
+        self.usage()
+            .subcommands
+            .iter()
+            .filter(|u| !u.name.starts_with('<'))
+            .map(|u| u.name.to_string())
+            .collect()
+    }
+
+    async fn run(&self, ctx: &mut dyn BuiltinContext, args: &str) -> Result<()>;
+}
 
 #[async_trait]
 pub trait BuiltinContext: Send {
@@ -27,9 +61,36 @@ pub trait BuiltinContext: Send {
     async fn ensure_shell_session_by_name(
         &mut self,
         name: &str,
-    ) -> Result<Arc<PtyShell>>;
+    ) -> Result<Arc<dyn Shell>>;
     async fn list_entries_with_status(&self) -> Vec<(String, registry::Entry, bool)>;
     async fn list_running_entries(&self) -> Vec<String>;
+    async fn start_recording(
+        &mut self,
+        name: &str,
+        path: Option<PathBuf>,
+    ) -> Result<PathBuf>;
+    async fn stop_recording(&mut self, name: &str) -> Result<PathBuf>;
+    async fn list_recordings(&self) -> Vec<(String, PathBuf)>;
+
+    fn lock_session(&mut self, name: &str) -> Result<()>;
+    fn unlock_session(&mut self, name: &str) -> Result<bool>;
+    fn is_locked(&self, name: &str) -> bool;
+    fn session_cwd(&self, name: &str) -> Option<String>;
+    async fn add_pane(&mut self, name: &str) -> Result<bool>;
+    fn remove_pane(&mut self, name: &str) -> bool;
+    fn focus_pane(&mut self, name: &str) -> Result<()>;
+    fn list_panes(&self) -> (Vec<String>, Option<String>);
+    fn request_view(&mut self, request: ViewRequest);
+    async fn render_screen(&self, name: &str) -> Result<String>;
+    fn share_session(&mut self, name: &str, mode: ShareMode) -> Result<()>;
+    fn unshare_session(&mut self, name: &str) -> usize;
+    fn share_mode(&self, name: &str) -> Option<ShareMode>;
+    fn list_viewers(&self, name: &str) -> Vec<Viewer>;
+    async fn run_expect(&mut self, name: &str, script: &Script) -> Result<()>;
+    async fn send_secret(&mut self, name: &str, ask: bool) -> Result<()>;
+    fn daemon_socket(&self) -> Option<PathBuf>;
+    async fn install_prompt_hook(&mut self, name: &str) -> Result<()>;
+    async fn cd_sync(&mut self, from: &str, to: &str) -> Result<String>;
 
     fn list_entries(&self) -> Vec<(String, registry::Entry)>;
     fn register_entry(&mut self, name: String, entry: registry::Entry);
@@ -40,4 +101,20 @@ pub trait BuiltinContext: Send {
 
     fn get_default_mode(&self) -> Option<String>;
     fn set_default_mode(&mut self, name: &str) -> bool;
+
+    fn list_aliases(&self) -> Vec<(String, Vec<String>)>;
+    fn get_alias(&self, name: &str) -> Option<Vec<String>>;
+    fn set_alias(&mut self, name: String, lines: Vec<String>) -> Result<()>;
+    fn remove_alias(&mut self, name: &str) -> bool;
+
+    fn list_vars(&self) -> Vec<(String, String)>;
+    fn get_var(&self, name: &str) -> Option<String>;
+    fn set_var(&mut self, name: String, value: String) -> Result<()>;
+    fn remove_var(&mut self, name: &str) -> bool;
+    async fn substitute(&mut self, input: &str) -> Result<String>;
+
+    fn list_builtins(&self) -> Vec<Arc<dyn Builtin>>;
+    fn get_builtin(&self, name: &str) -> Option<Arc<dyn Builtin>>;
+
+    fn repl_settings(&self) -> Option<ReplSettings>;
 }
@@ -1,18 +1,126 @@
+use std::path::PathBuf;
+
+use async_trait::async_trait;
 use tracing::{debug, info, warn};
 
 use crate::{
-    builtins::BuiltinContext,
-    error::{BuiltinError, Result},
-    ui::ui_println,
+    builtins::{
+        Builtin, BuiltinContext,
+        args::{Args, BuiltinUsage, Usage},
+    },
+    daemon,
+    error::{ReplRouterError, Result},
+    repl::ShareMode,
+    shell::record::{self, ReplayOptions},
+    ui::{ui_flush, ui_print, ui_println},
 };
 
+const DEFAULT_GET: &str = "get";
+const DEFAULT_SET: &str = "set";
+const RECORD_START: &str = "start";
+const RECORD_STOP: &str = "stop";
+const RECORD_STATUS: &str = "status";
+const CURSOR_TO_BOTTOM: &str = "\x1b[999;1H";
+
+pub static USAGE: BuiltinUsage = BuiltinUsage {
+    name: "admin",
+    about: "inspect and configure the running psh",
+    subcommands: &[
+        Usage {
+            name: "sessions",
+            synopsis: "sessions",
+            about: "list running sessions",
+        },
+        Usage {
+            name: "default",
+            synopsis: "default <get|set> [name]",
+            about: "show or change the default shell for unprefixed lines",
+        },
+        Usage {
+            name: "lock",
+            synopsis: "lock <name>",
+            about: "make a session read-only; its output keeps streaming",
+        },
+        Usage {
+            name: "unlock",
+            synopsis: "unlock <name>",
+            about: "allow sending to a locked session again",
+        },
+        Usage {
+            name: "share",
+            synopsis: "share <name> [read|write]",
+            about: "let `psh join <name>` clients watch (or type into) a session",
+        },
+        Usage {
+            name: "unshare",
+            synopsis: "unshare <name>",
+            about: "stop sharing a session and disconnect its viewers",
+        },
+        Usage {
+            name: "cd-sync",
+            synopsis: "cd-sync <from> <to>",
+            about: "cd session <to> into the working directory of <from>",
+        },
+        Usage {
+            name: "cwd-hook",
+            synopsis: "cwd-hook <name>",
+            about: "install the bash/zsh prompt hook that reports the directory; sent on connect to bash/zsh and ssh shells, telnet shells need this by hand",
+        },
+        Usage {
+            name: "screen",
+            synopsis: "screen <name>",
+            about: "repaint the current screen of a running session",
+        },
+        Usage {
+            name: "detach",
+            synopsis: "detach",
+            about: "leave this psh running in the background; `psh attach` returns",
+        },
+        Usage {
+            name: "record",
+            synopsis: "record <start|stop|status> [name] [--file <path>]",
+            about: "tee a session's input and output to an asciicast v2 file",
+        },
+        Usage {
+            name: "replay",
+            synopsis: "replay <file> [--speed <x>] [--idle-limit <secs>]",
+            about: "play back a recorded .cast file in this terminal",
+        },
+        Usage {
+            name: "help",
+            synopsis: "help [subcommand]",
+            about: "show this help or the usage of one subcommand",
+        },
+    ],
+};
+
+pub struct AdminBuiltin;
+
+#[async_trait]
+impl Builtin for AdminBuiltin {
+    fn name(&self) -> &str {
+        USAGE.name
+    }
+
+    fn usage(&self) -> &BuiltinUsage {
+        &USAGE
+    }
+
+    async fn run(&self, ctx: &mut dyn BuiltinContext, args: &str) -> Result<()> {
+        handle(ctx, args).await
+    }
+}
+
 pub async fn handle(ctx: &mut dyn BuiltinContext, args: &str) -> Result<()> {
     debug!(args = args, "builtin_admin_handle start");
 
-    let parts: Vec<&str> = args.split_whitespace().collect();
+    let Some(mut args) = Args::parse(&USAGE, args, "help")? else {
+        return Ok(());
+    };
 
-    match parts.as_slice() {
-        ["sessions"] => {
+    match args.subcommand() {
+        "sessions" => {
+            args.finish()?;
             ui_println("Running sessions list:")?;
             let names = ctx.list_running_entries().await;
             if names.is_empty() {
@@ -21,24 +129,173 @@ pub async fn handle(ctx: &mut dyn BuiltinContext, args: &str) -> Result<()> {
             } else {
                 info!(count = names.len(), "sessions listed");
                 for n in names {
-                    ui_println(&format!("  {n}"))?;
+                    let mut line = format!("  {n}");
+                    if ctx.is_locked(&n) {
+                        line.push_str(" [locked]");
+                    }
+                    if let Some(mode) = ctx.share_mode(&n) {
+                        line.push_str(&format!(" [shared {mode}]"));
+                    }
+                    ui_println(&line)?;
+                    for v in ctx.list_viewers(&n) {
+                        ui_println(&format!("    viewer #{} ({})", v.id, v.mode))?;
+                    }
                 }
             }
         }
-        ["default", "set", name] => match ctx.set_default_mode(name) {
-            true => info!(name = *name, "set_default_shell ok"),
-            false => warn!(name = *name, "set_default_shell unknown"),
-        },
-        ["default", "get"] => match ctx.get_default_mode() {
-            Some(n) => info!(name = %n, "shell_default"),
-            None => warn!("shell_default unset"),
+        "default" => match args
+            .required_one_of("action", &[DEFAULT_GET, DEFAULT_SET])?
+            .as_str()
+        {
+            DEFAULT_SET => {
+                let name = args.required("name")?;
+                args.finish()?;
+                match ctx.set_default_mode(&name) {
+                    true => info!(name = %name, "set_default_shell ok"),
+                    false => warn!(name = %name, "set_default_shell unknown"),
+                }
+            }
+            _ => {
+                args.finish()?;
+                match ctx.get_default_mode() {
+                    Some(n) => info!(name = %n, "shell_default"),
+                    None => warn!("shell_default unset"),
+                }
+            }
         },
-        _ => {
-            warn!(args = args, "admin_unrecognized");
-            return Err(BuiltinError::AdminUnrecognized {
-                args: args.to_string(),
+        "lock" => {
+            let name = args.required("name")?;
+            args.finish()?;
+            ctx.lock_session(&name)?;
+            ui_println(&format!("{name} is locked read-only"))?;
+            info!(name = %name, "admin_lock ok");
+        }
+        "unlock" => {
+            let name = args.required("name")?;
+            args.finish()?;
+            match ctx.unlock_session(&name)? {
+                true => ui_println(&format!("{name} is unlocked"))?,
+                false => ui_println(&format!("{name} was not locked"))?,
+            }
+            info!(name = %name, "admin_unlock ok");
+        }
+        "share" => {
+            let name = args.required("name")?;
+            let mode = args
+                .optional_parsed::<ShareMode>()
+                .unwrap_or(ShareMode::Read);
+            args.finish()?;
+            ctx.share_session(&name, mode)?;
+            ui_println(&format!(
+                "{name} is shared {mode}; `psh join {name}` joins it"
+            ))?;
+            info!(name = %name, %mode, "admin_share ok");
+        }
+        "unshare" => {
+            let name = args.required("name")?;
+            args.finish()?;
+            let kicked = ctx.unshare_session(&name);
+            ui_println(&format!(
+                "{name} is no longer shared ({kicked} viewers left)"
+            ))?;
+            info!(name = %name, kicked = kicked, "admin_unshare ok");
+        }
+        "cd-sync" => {
+            let from = args.required("from")?;
+            let to = args.required("to")?;
+            args.finish()?;
+            let path = ctx.cd_sync(&from, &to).await?;
+            ui_println(&format!("{to} -> {path}"))?;
+            info!(from = %from, to = %to, "admin_cd_sync ok");
+        }
+        "cwd-hook" => {
+            let name = args.required("name")?;
+            args.finish()?;
+            ctx.install_prompt_hook(&name).await?;
+            ui_println(&format!("prompt hook sent to {name}"))?;
+            info!(name = %name, "admin_cwd_hook ok");
+        }
+        "screen" => {
+            let name = args.required("name")?;
+            args.finish()?;
+            let screen = ctx.render_screen(&name).await?;
+            ui_print(&screen)?;
+            ui_println(CURSOR_TO_BOTTOM)?;
+            ui_flush()?;
+            info!(name = %name, "admin_screen ok");
+        }
+        "detach" => {
+            args.finish()?;
+            let Some(path) = ctx.daemon_socket() else {
+                warn!("admin_detach not_detachable");
+                return Err(ReplRouterError::NotDetachable.into());
+            };
+            daemon::request_detach(&path).await?;
+            info!(path = %path.display(), "admin_detach ok");
+        }
+        "record" => {
+            let file = args.option("file")?.map(PathBuf::from);
+            let action = args.required_one_of(
+                "action",
+                &[RECORD_START, RECORD_STOP, RECORD_STATUS],
+            )?;
+            let name = match action.as_str() {
+                RECORD_STATUS => None,
+                _ => Some(args.required("name")?),
+            };
+            args.finish()?;
+            match (action.as_str(), name) {
+                (RECORD_START, Some(name)) => {
+                    let path = ctx.start_recording(&name, file).await?;
+                    ui_println(&format!("recording {name} to {}", path.display()))?;
+                }
+                (RECORD_STOP, Some(name)) => {
+                    let path = ctx.stop_recording(&name).await?;
+                    ui_println(&format!(
+                        "stopped recording {name}: {}",
+                        path.display()
+                    ))?;
+                }
+                _ => {
+                    let recordings = ctx.list_recordings().await;
+                    if recordings.is_empty() {
+                        ui_println("no sessions are being recorded")?;
+                    }
+                    for (name, path) in recordings {
+                        ui_println(&format!("  {name:<14}{}", path.display()))?;
+                    }
+                }
             }
-            .into());
+            info!(action = %action, "admin_record ok");
+        }
+        "replay" => {
+            let speed = args.option_parsed::<f64>("speed")?.unwrap_or(1.0);
+            let idle_limit = args.option_parsed::<f64>("idle-limit")?;
+            let file = PathBuf::from(args.required("file")?);
+            let valid_speed = speed.is_finite() && speed > 0.0;
+            if !valid_speed || idle_limit.is_some_and(|l| l.is_nan() || l < 0.0) {
+                return Err(args
+                    .error("--speed must be positive and --idle-limit not negative"));
+            }
+            args.finish()?;
+            let options = ReplayOptions { speed, idle_limit };
+            let header = record::replay(&file, &options, |data| {
+                ui_print(data)?;
+                ui_flush()
+            })
+            .await?;
+            ui_println("")?;
+            ui_println(&format!(
+                "replayed {} ({}x{})",
+                file.display(),
+                header.width,
+                header.height
+            ))?;
+            info!(file = %file.display(), "admin_replay ok");
+        }
+        other => {
+            warn!(sub = other, "admin_unsupported");
+            return Err(args.unsupported());
         }
     }
 
@@ -0,0 +1,137 @@
+use async_trait::async_trait;
+use tracing::{debug, info, warn};
+
+use crate::{
+    builtins::{
+        Builtin, BuiltinContext,
+        args::{Args, BuiltinUsage, Usage},
+    },
+    error::{BuiltinError, PshError, Result},
+    ui::ui_println,
+};
+
+pub static USAGE: BuiltinUsage = BuiltinUsage {
+    name: "alias",
+    about: "define shortcuts that expand to one or more prefixed commands",
+    subcommands: &[
+        Usage {
+            name: "list",
+            synopsis: "list",
+            about: "list defined aliases and what they expand to",
+        },
+        Usage {
+            name: "show",
+            synopsis: "show <name>",
+            about: "show the commands an alias expands to",
+        },
+        Usage {
+            name: "set",
+            synopsis: "set <name> <line> [line...]",
+            about: "define an alias; each quoted line runs in order, $1..$N and $@ take arguments",
+        },
+        Usage {
+            name: "remove",
+            synopsis: "remove <name>",
+            about: "delete an alias",
+        },
+        Usage {
+            name: "help",
+            synopsis: "help [subcommand]",
+            about: "show this help or the usage of one subcommand",
+        },
+    ],
+};
+
+pub struct AliasBuiltin;
+
+#[async_trait]
+impl Builtin for AliasBuiltin {
+    fn name(&self) -> &str {
+        USAGE.name
+    }
+
+    fn usage(&self) -> &BuiltinUsage {
+        &USAGE
+    }
+
+    async fn run(&self, ctx: &mut dyn BuiltinContext, args: &str) -> Result<()> {
+        handle(ctx, args).await
+    }
+}
+
+fn print_alias(name: &str, lines: &[String]) -> Result<()> {
+    match lines {
+        [single] => ui_println(&format!("  {name} = {single}"))?,
+        many => {
+            ui_println(&format!("  {name} ="))?;
+            for line in many {
+                ui_println(&format!("    {line}"))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn unknown_alias(name: &str) -> PshError {
+    BuiltinError::InvalidArgs {
+        detail: format!("no alias named `{name}`\ntry `alias: list`"),
+    }
+    .into()
+}
+
+pub async fn handle(ctx: &mut dyn BuiltinContext, args: &str) -> Result<()> {
+    debug!(args = args, "builtin_alias_handle start");
+    let Some(mut args) = Args::parse(&USAGE, args, "list")? else {
+        return Ok(());
+    };
+    match args.subcommand() {
+        "list" => {
+            args.finish()?;
+            let aliases = ctx.list_aliases();
+            if aliases.is_empty() {
+                ui_println("No aliases defined")?;
+            } else {
+                ui_println("Alias list:")?;
+                for (name, lines) in aliases {
+                    print_alias(&name, &lines)?;
+                }
+            }
+            info!("alias_list ok");
+        }
+        "show" => {
+            let name = args.required("name")?;
+            args.finish()?;
+            match ctx.get_alias(&name) {
+                Some(lines) => print_alias(&name, &lines)?,
+                None => {
+                    warn!(name = %name, "alias_show unknown");
+                    return Err(unknown_alias(&name));
+                }
+            }
+            info!(name = %name, "alias_show ok");
+        }
+        "set" => {
+            let name = args.required("name")?;
+            let first = args.required("line")?;
+            let mut lines = vec![first];
+            lines.extend(args.rest()?);
+            ctx.set_alias(name.clone(), lines)?;
+            info!(name = %name, "alias_set ok");
+        }
+        "remove" => {
+            let name = args.required("name")?;
+            args.finish()?;
+            if !ctx.remove_alias(&name) {
+                warn!(name = %name, "alias_remove unknown");
+                return Err(unknown_alias(&name));
+            }
+            info!(name = %name, "alias_remove ok");
+        }
+        other => {
+            warn!(sub = other, "alias_unsupported");
+            return Err(args.unsupported());
+        }
+    }
+    info!("builtin_alias_handle ok");
+    Ok(())
+}
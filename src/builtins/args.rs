@@ -0,0 +1,628 @@
+use std::str::FromStr;
+
+use tracing::{debug, info, warn};
+
+use crate::{
+    error::{BuiltinError, PshError, Result},
+    ui::ui_println,
+};
+
+const HELP_SUBCOMMAND: &str = "help";
+const HELP_FLAGS: [&str; 2] = ["--help", "-h"];
+const END_OF_FLAGS: &str = "--";
+const OPTION_PREFIX: &str = "--";
+const LIST_SEPARATOR: char = ',';
+const DQUOTE_ESCAPES: [char; 4] = ['"', '\\', '$', '`'];
+const USAGE_COLUMN_PAD: usize = 2;
+const MAX_SYNOPSIS_COLUMN: usize = 32;
+
+#[derive(Debug)]
+pub struct Usage {
+    pub name: &'static str,
+    pub synopsis: &'static str,
+    pub about: &'static str,
+}
+
+#[derive(Debug)]
+pub struct BuiltinUsage {
+    pub name: &'static str,
+    pub about: &'static str,
+    pub subcommands: &'static [Usage],
+}
+
+impl BuiltinUsage {
+    pub fn find(&self, name: &str) -> Option<&'static Usage> {
+        self.subcommands.iter().find(|u| u.name == name)
+    }
+
+    pub fn help_lines(&self) -> Vec<String> {
+        let width = self
+            .subcommands
+            .iter()
+            .map(|u| u.synopsis.chars().count())
+            .filter(|len| *len <= MAX_SYNOPSIS_COLUMN)
+            .max()
+            .unwrap_or(0)
+            + USAGE_COLUMN_PAD;
+        let lead = self.name.chars().count() + USAGE_COLUMN_PAD;
+        let mut lines = vec![format!("{}: {}", self.name, self.about)];
+        for u in self.subcommands {
+            if u.synopsis.chars().count() > MAX_SYNOPSIS_COLUMN {
+                lines.push(format!("  {}: {}", self.name, u.synopsis));
+                lines.push(format!("  {}{}", " ".repeat(lead + width), u.about));
+            } else {
+                lines.push(format!(
+                    "  {}: {:<width$}{}",
+                    self.name,
+                    u.synopsis,
+                    u.about,
+                    width = width
+                ));
+            }
+        }
+        lines
+    }
+
+    pub fn topic_lines(&self, topic: &str) -> Option<Vec<String>> {
+        self.find(topic).map(|u| {
+            vec![
+                format!("usage: {}: {}", self.name, u.synopsis),
+                format!("  {}", u.about),
+            ]
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    text: String,
+    col: usize,
+    width: usize,
+    quoted: bool,
+}
+
+fn tokenize(input: &str) -> std::result::Result<Vec<Token>, (String, usize, usize)> {
+    let mut tokens = Vec::new();
+    let mut current: Option<Token> = None;
+    let mut quote: Option<(char, usize)> = None;
+    let mut escaped = false;
+
+    for (col, ch) in input.chars().enumerate() {
+        if escaped {
+            let tok = current.get_or_insert_with(|| Token {
+                text: String::new(),
+                col: col - 1,
+                width: 0,
+                quoted: false,
+            });
+            // Like sh, double quotes keep the backslash unless it escapes one
+            // of the characters that are special inside them.
+            if matches!(quote, Some(('"', _))) && !DQUOTE_ESCAPES.contains(&ch) {
+                tok.text.push('\\');
+            }
+            tok.text.push(ch);
+            escaped = false;
+            continue;
+        }
+        match (quote, ch) {
+            (Some(('\'', _)), '\'') | (Some(('"', _)), '"') => quote = None,
+            (Some(('"', _)), '\\') | (None, '\\') => escaped = true,
+            (Some(_), c) => {
+                if let Some(tok) = current.as_mut() {
+                    tok.text.push(c);
+                }
+            }
+            (None, '\'' | '"') => {
+                let tok = current.get_or_insert_with(|| Token {
+                    text: String::new(),
+                    col,
+                    width: 0,
+                    quoted: true,
+                });
+                tok.quoted = true;
+                quote = Some((ch, col));
+            }
+            (None, c) if c.is_whitespace() => {
+                if let Some(mut tok) = current.take() {
+                    tok.width = col - tok.col;
+                    tokens.push(tok);
+                }
+            }
+            (None, c) => {
+                current
+                    .get_or_insert_with(|| Token {
+                        text: String::new(),
+                        col,
+                        width: 0,
+                        quoted: false,
+                    })
+                    .text
+                    .push(c);
+            }
+        }
+    }
+
+    let len = input.chars().count();
+    if let Some((q, col)) = quote {
+        return Err((format!("unterminated {q} quote"), col, len - col));
+    }
+    if escaped {
+        return Err(("trailing backslash".to_string(), len - 1, 1));
+    }
+    if let Some(mut tok) = current.take() {
+        tok.width = len - tok.col;
+        tokens.push(tok);
+    }
+    Ok(tokens)
+}
+
+pub fn split(usage: &BuiltinUsage, input: &str) -> Result<Vec<String>> {
+    match tokenize(input) {
+        Ok(tokens) => Ok(tokens.into_iter().map(|t| t.text).collect()),
+        Err((reason, col, width)) => {
+            warn!(builtin = usage.name, %reason, "builtin_args_split failed");
+            Err(invalid(usage, None, input, Some((col, width)), &reason))
+        }
+    }
+}
+
+pub struct Args {
+    usage: &'static BuiltinUsage,
+    input: String,
+    tokens: Vec<Token>,
+    sub: &'static Usage,
+}
+
+impl Args {
+    pub fn parse(
+        usage: &'static BuiltinUsage,
+        input: &str,
+        default: &str,
+    ) -> Result<Option<Self>> {
+        debug!(
+            builtin = usage.name,
+            input = input,
+            "builtin_args_parse start"
+        );
+        let mut tokens = match tokenize(input) {
+            Ok(t) => t,
+            Err((reason, col, width)) => {
+                warn!(builtin = usage.name, %reason, "builtin_args_parse tokenize failed");
+                return Err(invalid(usage, None, input, Some((col, width)), &reason));
+            }
+        };
+
+        let help_flag = take_help_flags(&mut tokens);
+
+        let sub = match tokens.first() {
+            None if default == HELP_SUBCOMMAND => {
+                print_help(usage, None)?;
+                info!(builtin = usage.name, "builtin_args_parse help");
+                return Ok(None);
+            }
+            None => usage.find(default),
+            Some(t) if t.text == HELP_SUBCOMMAND && !t.quoted => {
+                let topic = tokens.get(1).map(|t| t.text.clone());
+                print_help(usage, topic.as_deref())?;
+                info!(builtin = usage.name, "builtin_args_parse help");
+                return Ok(None);
+            }
+            Some(t) => match usage.find(&t.text) {
+                Some(u) => {
+                    tokens.remove(0);
+                    Some(u)
+                }
+                None => {
+                    warn!(builtin = usage.name, sub = %t.text, "builtin_args_parse unknown");
+                    return Err(invalid(
+                        usage,
+                        None,
+                        input,
+                        Some((t.col, t.width)),
+                        &format!("unknown subcommand `{}`", t.text),
+                    ));
+                }
+            },
+        };
+        let Some(sub) = sub else {
+            warn!(builtin = usage.name, "builtin_args_parse no_default");
+            return Err(invalid(usage, None, input, None, "missing subcommand"));
+        };
+
+        if help_flag {
+            print_help(usage, Some(sub.name))?;
+            info!(
+                builtin = usage.name,
+                sub = sub.name,
+                "builtin_args_parse help"
+            );
+            return Ok(None);
+        }
+
+        info!(
+            builtin = usage.name,
+            sub = sub.name,
+            "builtin_args_parse ok"
+        );
+        Ok(Some(Self {
+            usage,
+            input: input.to_string(),
+            tokens,
+            sub,
+        }))
+    }
+
+    pub fn subcommand(&self) -> &'static str {
+        self.sub.name
+    }
+
+    pub fn required(&mut self, what: &str) -> Result<String> {
+        match self.next_positional()? {
+            Some(tok) => Ok(tok.text),
+            None => Err(self.error_at_end(&format!("missing <{what}>"))),
+        }
+    }
+
+    pub fn required_one_of(&mut self, what: &str, choices: &[&str]) -> Result<String> {
+        let Some(tok) = self.next_positional()? else {
+            return Err(self.error_at_end(&format!(
+                "missing <{what}>, expected one of: {}",
+                choices.join(", ")
+            )));
+        };
+        if !choices.contains(&tok.text.as_str()) {
+            return Err(self.error_at(
+                &tok,
+                &format!(
+                    "invalid <{what}> `{}`, expected one of: {}",
+                    tok.text,
+                    choices.join(", ")
+                ),
+            ));
+        }
+        Ok(tok.text)
+    }
+
+    pub fn optional_parsed<T: FromStr>(&mut self) -> Option<T> {
+        if self.end_of_flags() == 0 {
+            return None;
+        }
+        let value = self.tokens.first()?.text.parse::<T>().ok()?;
+        self.tokens.remove(0);
+        Some(value)
+    }
+
+    pub fn option(&mut self, long: &str) -> Result<Option<String>> {
+        Ok(self.take_option(long)?.map(|t| t.text))
+    }
+
+    pub fn option_parsed<T: FromStr>(&mut self, long: &str) -> Result<Option<T>> {
+        let Some(tok) = self.take_option(long)? else {
+            return Ok(None);
+        };
+        match tok.text.parse::<T>() {
+            Ok(v) => Ok(Some(v)),
+            Err(_) => Err(self.error_at(
+                &tok,
+                &format!("invalid value `{}` for `{OPTION_PREFIX}{long}`", tok.text),
+            )),
+        }
+    }
+
+    pub fn option_list(&mut self, long: &str) -> Result<Vec<String>> {
+        Ok(self
+            .option(long)?
+            .map(|v| {
+                v.split(LIST_SEPARATOR)
+                    .map(str::trim)
+                    .filter(|s| !s.is_empty())
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    pub fn rest(&mut self) -> Result<Vec<String>> {
+        let end = self.end_of_flags();
+        if let Some(tok) = self.tokens[..end]
+            .iter()
+            .find(|t| !t.quoted && t.text.starts_with(OPTION_PREFIX))
+        {
+            return Err(self.error_at(tok, &format!("unknown option `{}`", tok.text)));
+        }
+        let mut out: Vec<String> = Vec::new();
+        for (idx, tok) in self.tokens.drain(..).enumerate() {
+            if idx != end {
+                out.push(tok.text);
+            }
+        }
+        Ok(out)
+    }
+
+    pub fn finish(self) -> Result<()> {
+        let end = self.end_of_flags();
+        match self.tokens.iter().enumerate().find(|(i, _)| *i != end) {
+            Some((_, tok)) => {
+                Err(self.error_at(tok, &format!("unexpected argument `{}`", tok.text)))
+            }
+            None => Ok(()),
+        }
+    }
+
+    pub fn error(&self, reason: &str) -> PshError {
+        invalid(self.usage, Some(self.sub), &self.input, None, reason)
+    }
+
+    pub fn unsupported(self) -> PshError {
+        invalid(
+            self.usage,
+            None,
+            &self.input,
+            None,
+            &format!("subcommand `{}` is not supported here", self.sub.name),
+        )
+    }
+
+    fn end_of_flags(&self) -> usize {
+        self.tokens
+            .iter()
+            .position(|t| !t.quoted && t.text == END_OF_FLAGS)
+            .unwrap_or(self.tokens.len())
+    }
+
+    fn next_positional(&mut self) -> Result<Option<Token>> {
+        let end = self.end_of_flags();
+        let Some(idx) = (0..self.tokens.len()).find(|&i| i != end) else {
+            return Ok(None);
+        };
+        let tok = &self.tokens[idx];
+        if idx < end && !tok.quoted && tok.text.starts_with(OPTION_PREFIX) {
+            return Err(self.error_at(tok, &format!("unknown option `{}`", tok.text)));
+        }
+        Ok(Some(self.tokens.remove(idx)))
+    }
+
+    fn take_option(&mut self, long: &str) -> Result<Option<Token>> {
+        let flag = format!("{OPTION_PREFIX}{long}");
+        let inline = format!("{flag}=");
+        let end = self.end_of_flags();
+        let Some(idx) = self.tokens[..end]
+            .iter()
+            .position(|t| !t.quoted && (t.text == flag || t.text.starts_with(&inline)))
+        else {
+            return Ok(None);
+        };
+        let tok = self.tokens.remove(idx);
+        if let Some(value) = tok.text.strip_prefix(&inline) {
+            return Ok(Some(Token {
+                text: value.to_string(),
+                ..tok.clone()
+            }));
+        }
+        if idx < self.end_of_flags() {
+            return Ok(Some(self.tokens.remove(idx)));
+        }
+        Err(self.error_at(&tok, &format!("option `{flag}` needs a value")))
+    }
+
+    fn error_at(&self, tok: &Token, reason: &str) -> PshError {
+        invalid(
+            self.usage,
+            Some(self.sub),
+            &self.input,
+            Some((tok.col, tok.width)),
+            reason,
+        )
+    }
+
+    fn error_at_end(&self, reason: &str) -> PshError {
+        let len = self.input.chars().count();
+        invalid(
+            self.usage,
+            Some(self.sub),
+            &self.input,
+            Some((len, 1)),
+            reason,
+        )
+    }
+}
+
+fn take_help_flags(tokens: &mut Vec<Token>) -> bool {
+    let end = tokens
+        .iter()
+        .position(|t| !t.quoted && t.text == END_OF_FLAGS)
+        .unwrap_or(tokens.len());
+    let before = tokens.len();
+    let mut idx = 0;
+    tokens.retain(|t| {
+        let keep = idx >= end || t.quoted || !HELP_FLAGS.contains(&t.text.as_str());
+        idx += 1;
+        keep
+    });
+    tokens.len() != before
+}
+
+fn invalid(
+    usage: &BuiltinUsage,
+    sub: Option<&Usage>,
+    input: &str,
+    span: Option<(usize, usize)>,
+    reason: &str,
+) -> PshError {
+    let mut detail = reason.to_string();
+    if let Some((col, width)) = span {
+        let lead = usage.name.chars().count() + USAGE_COLUMN_PAD + col;
+        detail.push_str(&format!("\n  {}: {}", usage.name, input));
+        detail.push_str(&format!(
+            "\n  {}{}",
+            " ".repeat(lead),
+            "^".repeat(width.max(1))
+        ));
+    }
+    match sub {
+        Some(u) => detail.push_str(&format!("\nusage: {}: {}", usage.name, u.synopsis)),
+        None => detail.push_str(&format!("\ntry `{}: {HELP_SUBCOMMAND}`", usage.name)),
+    }
+    BuiltinError::InvalidArgs { detail }.into()
+}
+
+pub fn print_help(usage: &BuiltinUsage, topic: Option<&str>) -> Result<()> {
+    debug!(builtin = usage.name, topic = ?topic, "builtin_print_help start");
+    let lines = match topic {
+        None => usage.help_lines(),
+        Some(t) => match usage.topic_lines(t) {
+            Some(lines) => lines,
+            None => {
+                warn!(
+                    builtin = usage.name,
+                    topic = t,
+                    "builtin_print_help unknown"
+                );
+                return Err(BuiltinError::InvalidArgs {
+                    detail: format!(
+                        "no help for `{}: {t}`\ntry `{}: {HELP_SUBCOMMAND}`",
+                        usage.name, usage.name
+                    ),
+                }
+                .into());
+            }
+        },
+    };
+    for line in lines {
+        ui_println(&line)?;
+    }
+    info!(builtin = usage.name, "builtin_print_help ok");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static USAGE: BuiltinUsage = BuiltinUsage {
+        name: "test",
+        about: "args fixture",
+        subcommands: &[Usage {
+            name: "run",
+            synopsis: "run [port] [--opt <v>] [args...] [-- extra...]",
+            about: "fixture subcommand",
+        }],
+    };
+
+    fn args(input: &str) -> Args {
+        match Args::parse(&USAGE, input, "run") {
+            Ok(Some(args)) => args,
+            Ok(None) => panic!("no args for {input:?}"),
+            Err(e) => panic!("parse {input:?}: {e}"),
+        }
+    }
+
+    fn words(input: &str) -> Vec<String> {
+        split(&USAGE, input).expect("split")
+    }
+
+    #[test]
+    fn tokenizer_handles_quotes_and_escapes() {
+        assert_eq!(words(r#"a 'b c' "d e""#), ["a", "b c", "d e"]);
+        assert_eq!(words(r#"'a "b"' "it's""#), [r#"a "b""#, "it's"]);
+        assert_eq!(words(r"a\ b c\\d"), ["a b", r"c\d"]);
+        assert_eq!(words(r#""a \"b\" \\c""#), [r#"a "b" \c"#]);
+        assert_eq!(words(r"'a\nb'"), [r"a\nb"]);
+        assert_eq!(words(r#""a\nb \$x \`y\`""#), [r"a\nb $x `y`"]);
+        assert_eq!(words(r#""C:\dir\file""#), [r"C:\dir\file"]);
+        assert_eq!(words(r#"x"y z"w"#), ["xy zw"]);
+        assert_eq!(words(r#"'' """#), ["", ""]);
+    }
+
+    #[test]
+    fn tokenizer_reports_unterminated_input() {
+        let err = split(&USAGE, "a 'b c").expect_err("quote");
+        assert!(err.to_string().contains("unterminated ' quote"), "{err}");
+        let err = split(&USAGE, r#"a "b"#).expect_err("quote");
+        assert!(err.to_string().contains(r#"unterminated " quote"#), "{err}");
+        let err = split(&USAGE, r"a b\").expect_err("backslash");
+        assert!(err.to_string().contains("trailing backslash"), "{err}");
+    }
+
+    #[test]
+    fn options_take_separate_or_inline_values() {
+        let mut a = args("run --opt one x --list=a,b");
+        assert_eq!(a.option("opt").expect("opt"), Some("one".to_string()));
+        assert_eq!(a.option_list("list").expect("list"), ["a", "b"]);
+        assert_eq!(a.option("missing").expect("missing"), None);
+        assert_eq!(a.required("x").expect("x"), "x");
+        a.finish().expect("finish");
+
+        let mut a = args("run --opt");
+        let err = a.option("opt").expect_err("no value");
+        assert!(err.to_string().contains("needs a value"), "{err}");
+
+        let mut a = args("run --opt=8080");
+        assert_eq!(a.option_parsed::<u16>("opt").expect("parsed"), Some(8080));
+        let mut a = args("run --opt=many");
+        assert!(a.option_parsed::<u16>("opt").is_err());
+    }
+
+    #[test]
+    fn unknown_options_are_reported() {
+        let mut a = args("run --bogus x");
+        let err = a.required("x").expect_err("unknown");
+        assert!(
+            err.to_string().contains("unknown option `--bogus`"),
+            "{err}"
+        );
+
+        let mut a = args("run '--bogus'");
+        assert_eq!(a.required("x").expect("quoted"), "--bogus");
+
+        let a = args("run x y");
+        let err = a.finish().expect_err("extra");
+        assert!(err.to_string().contains("unexpected argument `x`"), "{err}");
+    }
+
+    #[test]
+    fn end_of_flags_stops_option_parsing() {
+        let mut a = args("run -- --opt v");
+        assert_eq!(a.option("opt").expect("opt"), None);
+        assert_eq!(a.required("x").expect("x"), "--opt");
+        assert_eq!(a.required("v").expect("v"), "v");
+        a.finish().expect("finish");
+    }
+
+    #[test]
+    fn optional_parsed_stops_at_end_of_flags() {
+        let mut a = args("run 22 -- 23");
+        assert_eq!(a.optional_parsed::<u16>(), Some(22));
+        assert_eq!(a.optional_parsed::<u16>(), None);
+        assert_eq!(a.rest().expect("rest"), ["23"]);
+
+        let mut a = args("run host 22");
+        assert_eq!(a.optional_parsed::<u16>(), None);
+        assert_eq!(a.rest().expect("rest"), ["host", "22"]);
+    }
+
+    #[test]
+    fn rest_rejects_unknown_options_before_end_of_flags() {
+        let mut a = args("run a --bogus b");
+        let err = a.rest().expect_err("unknown");
+        assert!(
+            err.to_string().contains("unknown option `--bogus`"),
+            "{err}"
+        );
+
+        let mut a = args("run a '--quoted' -v -- --raw b");
+        assert_eq!(
+            a.rest().expect("rest"),
+            ["a", "--quoted", "-v", "--raw", "b"]
+        );
+    }
+
+    #[test]
+    fn help_flags_are_stripped_before_end_of_flags_only() {
+        assert!(
+            Args::parse(&USAGE, "run -- --help", "run")
+                .expect("parse")
+                .is_some()
+        );
+    }
+}
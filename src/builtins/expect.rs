@@ -0,0 +1,82 @@
+use async_trait::async_trait;
+use tracing::{debug, info, warn};
+
+use crate::{
+    builtins::{
+        Builtin, BuiltinContext,
+        args::{Args, BuiltinUsage, Usage},
+    },
+    error::Result,
+    runtime::config,
+    shell::expect::Script,
+    ui::ui_println,
+};
+
+pub static USAGE: BuiltinUsage = BuiltinUsage {
+    name: "expect",
+    about: "drive sessions with expect scripts",
+    subcommands: &[
+        Usage {
+            name: "run",
+            synopsis: "run <name> <script>",
+            about: "run an expect script (send, expect /re/ [secs], if expect/else/end, sleep, fail) against a session in the background; lines sent to it meanwhile wait for the script",
+        },
+        Usage {
+            name: "check",
+            synopsis: "check <script>",
+            about: "parse an expect script without running it",
+        },
+        Usage {
+            name: "help",
+            synopsis: "help [subcommand]",
+            about: "show this help or the usage of one subcommand",
+        },
+    ],
+};
+
+pub struct ExpectBuiltin;
+
+#[async_trait]
+impl Builtin for ExpectBuiltin {
+    fn name(&self) -> &str {
+        USAGE.name
+    }
+
+    fn usage(&self) -> &BuiltinUsage {
+        &USAGE
+    }
+
+    async fn run(&self, ctx: &mut dyn BuiltinContext, args: &str) -> Result<()> {
+        handle(ctx, args).await
+    }
+}
+
+pub async fn handle(ctx: &mut dyn BuiltinContext, args: &str) -> Result<()> {
+    debug!(args = args, "builtin_expect_handle start");
+    let Some(mut args) = Args::parse(&USAGE, args, "help")? else {
+        return Ok(());
+    };
+    match args.subcommand() {
+        "run" => {
+            let name = args.required("name")?;
+            let path = args.required("script")?;
+            args.finish()?;
+            let script = Script::load(&config::expect_script_path(&path))?;
+            ctx.run_expect(&name, &script).await?;
+            ui_println(&format!("expect script {path} started on {name}"))?;
+            info!(name = %name, path = %path, "expect_run ok");
+        }
+        "check" => {
+            let path = args.required("script")?;
+            args.finish()?;
+            Script::load(&config::expect_script_path(&path))?;
+            ui_println(&format!("expect script {path} is valid"))?;
+            info!(path = %path, "expect_check ok");
+        }
+        other => {
+            warn!(sub = other, "expect_unsupported");
+            return Err(args.unsupported());
+        }
+    }
+    Ok(())
+}
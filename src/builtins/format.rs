@@ -2,34 +2,56 @@ use tracing::{debug, info};
 
 use crate::shell::{ShellSpec, spec::RemoteBackend};
 
-pub fn format_shell_line(name: &str, spec: &ShellSpec, running: bool) -> String {
+pub fn format_shell_line(
+    name: &str,
+    spec: &ShellSpec,
+    running: bool,
+    cwd: Option<&str>,
+) -> String {
     debug!(
         name = name,
         running = running,
         shell = format!("{:?}", spec),
         "format_shell_line start"
     );
+    let tags = match spec.tags() {
+        [] => String::new(),
+        tags => format!(" #{}", tags.join(" #")),
+    };
     let s = match spec {
-        ShellSpec::Local { program } => {
+        ShellSpec::Local { program, .. } => {
             let status = if running { "[running]" } else { "[stopped]" };
-            format!("  {name}: {program} {status}")
+            format!("  {name}: {program}{tags} {status}")
         }
-        ShellSpec::Remote { host, backend } => {
+        ShellSpec::Remote { host, backend, .. } => {
             let status = if running {
                 "[connected]"
             } else {
                 "[disconnected]"
             };
             match backend {
-                RemoteBackend::Ssh { port, .. } => {
-                    format!("  {name} (ssh): {host}:{port} {status}")
+                RemoteBackend::Ssh {
+                    port, via: None, ..
+                } => {
+                    format!("  {name} (ssh): {host}:{port}{tags} {status}")
+                }
+                RemoteBackend::Ssh {
+                    port,
+                    via: Some(via),
+                    ..
+                } => {
+                    format!("  {name} (ssh): {host}:{port} via {via}{tags} {status}")
                 }
                 RemoteBackend::Telnet { port, .. } => {
-                    format!("  {name} (telnet): {host}:{port} {status}")
+                    format!("  {name} (telnet): {host}:{port}{tags} {status}")
                 }
             }
         }
     };
+    let s = match cwd {
+        Some(cwd) if running => format!("{s} in {cwd}"),
+        _ => s,
+    };
     info!("format_shell_line ok");
     s
 }
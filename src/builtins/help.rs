@@ -0,0 +1,188 @@
+use async_trait::async_trait;
+use tracing::{debug, info, warn};
+
+use crate::{
+    builtins::{
+        Builtin, BuiltinContext,
+        args::{self, BuiltinUsage, Usage},
+        format_shell_line,
+    },
+    error::{BuiltinError, Result},
+    registry,
+    repl::parser::PIPE_OPERATOR,
+    runtime::config::describe_key,
+    ui::ui_println,
+};
+
+pub static USAGE: BuiltinUsage = BuiltinUsage {
+    name: "help",
+    about: "show builtins, registered shells and key bindings",
+    subcommands: &[
+        Usage {
+            name: "<builtin>",
+            synopsis: "<builtin> [subcommand]",
+            about: "show the usage of one builtin or one of its subcommands",
+        },
+        Usage {
+            name: "<shell>",
+            synopsis: "<shell>",
+            about: "show how a registered shell is configured",
+        },
+        Usage {
+            name: "<alias>",
+            synopsis: "<alias>",
+            about: "show what an alias expands to",
+        },
+    ],
+};
+
+pub struct HelpBuiltin;
+
+#[async_trait]
+impl Builtin for HelpBuiltin {
+    fn name(&self) -> &str {
+        USAGE.name
+    }
+
+    fn usage(&self) -> &BuiltinUsage {
+        &USAGE
+    }
+
+    async fn run(&self, ctx: &mut dyn BuiltinContext, args: &str) -> Result<()> {
+        handle(ctx, args).await
+    }
+}
+
+async fn print_overview(ctx: &mut dyn BuiltinContext) -> Result<()> {
+    debug!("help_overview start");
+    ui_println("Builtins:")?;
+    for builtin in ctx.list_builtins() {
+        for line in builtin.usage().help_lines() {
+            ui_println(&format!("  {line}"))?;
+        }
+        if !builtin.aliases().is_empty() {
+            ui_println(&format!("    aliases: {}", builtin.aliases().join(", ")))?;
+        }
+    }
+
+    ui_println("Shells:")?;
+    let mut printed = false;
+    for (name, entry, running) in ctx.list_entries_with_status().await {
+        if let registry::Entry::Shell(spec) = entry {
+            let line = format_shell_line(
+                &name,
+                &spec,
+                running,
+                ctx.session_cwd(&name).as_deref(),
+            );
+            ui_println(&format!("  {:<14}{}", spec.kind_name(), line.trim_start()))?;
+            printed = true;
+        }
+    }
+    if !printed {
+        ui_println("  no shells registered")?;
+    }
+
+    let aliases = ctx.list_aliases();
+    if !aliases.is_empty() {
+        ui_println("Aliases:")?;
+        for (name, lines) in aliases {
+            ui_println(&format!("  {:<14}{}", name, lines.join("; ")))?;
+        }
+    }
+
+    ui_println("Key bindings:")?;
+    match ctx.repl_settings() {
+        Some(settings) => {
+            ui_println(&format!(
+                "  {:<14}open the prefix menu",
+                describe_key(settings.menu_key)
+            ))?;
+            ui_println(&format!(
+                "  {:<14}{} editing",
+                "edit mode",
+                settings.edit_menu.name()
+            ))?;
+        }
+        None => ui_println("  REPL settings unavailable")?,
+    }
+    ui_println(&format!("  {:<14}interrupt the current shell", "Ctrl+c"))?;
+    ui_println(&format!("  {:<14}leave psh", "Ctrl+d"))?;
+
+    let current = ctx
+        .get_current_mode()
+        .map(|n| format!("`{n}`"))
+        .unwrap_or_else(|| "unset".to_string());
+    let default = ctx
+        .get_default_mode()
+        .map(|n| format!("`{n}`"))
+        .unwrap_or_else(|| "unset".to_string());
+    ui_println(&format!(
+        "Prefix a line with `<name>:` to target a shell or builtin; unprefixed lines go to the current prefix ({current}, default {default})."
+    ))?;
+    ui_println(&format!(
+        "Join stages with `{PIPE_OPERATOR}` to feed one shell's output to the next, e.g. `web1: cat /etc/hosts {PIPE_OPERATOR} local: diff - /etc/hosts`."
+    ))?;
+    info!("help_overview ok");
+    Ok(())
+}
+
+fn print_alias(name: &str, ctx: &dyn BuiltinContext) -> Result<bool> {
+    let Some(lines) = ctx.get_alias(name) else {
+        return Ok(false);
+    };
+    ui_println(&format!("{name}: alias"))?;
+    ui_println(&format!("usage: {name} [args...]"))?;
+    ui_println("  runs, in order:")?;
+    for line in lines {
+        ui_println(&format!("    {line}"))?;
+    }
+    Ok(true)
+}
+
+fn print_shell(name: &str, ctx: &dyn BuiltinContext) -> Result<bool> {
+    let spec = ctx.list_entries().into_iter().find_map(|(n, e)| match e {
+        registry::Entry::Shell(spec) if n == name => Some(spec),
+        _ => None,
+    });
+    let Some(spec) = spec else {
+        return Ok(false);
+    };
+    ui_println(&format!("{name}: {} shell", spec.kind_name()))?;
+    ui_println(&format!("usage: {name}: <command>"))?;
+    ui_println(&format!(
+        "  send <command> to {name}, starting the session if needed"
+    ))?;
+    Ok(true)
+}
+
+pub async fn handle(ctx: &mut dyn BuiltinContext, args: &str) -> Result<()> {
+    debug!(args = args, "builtin_help_handle start");
+    let words = args::split(&USAGE, args)?;
+    match words.as_slice() {
+        [] => print_overview(ctx).await?,
+        [topic] if topic == "--help" || topic == "-h" => {
+            args::print_help(&USAGE, None)?;
+        }
+        [topic, rest @ ..] => {
+            if let Some(builtin) = ctx.get_builtin(topic) {
+                args::print_help(builtin.usage(), rest.first().map(String::as_str))?;
+            } else if rest.is_empty() && print_shell(topic, ctx)? {
+                info!(topic = %topic, "help_shell ok");
+            } else if rest.is_empty() && print_alias(topic, ctx)? {
+                info!(topic = %topic, "help_alias ok");
+            } else {
+                warn!(topic = %topic, "help_topic unknown");
+                return Err(BuiltinError::InvalidArgs {
+                    detail: format!(
+                        "no help for `{}`\ntry `help:` for the list of builtins and shells",
+                        words.join(" ")
+                    ),
+                }
+                .into());
+            }
+        }
+    }
+    info!("builtin_help_handle ok");
+    Ok(())
+}
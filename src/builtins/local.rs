@@ -1,18 +1,80 @@
+use async_trait::async_trait;
 use tracing::{debug, info, warn};
 
 use crate::{
-    builtins::{BuiltinContext, format_shell_line},
-    error::{BuiltinError, Result},
+    builtins::{
+        Builtin, BuiltinContext,
+        args::{Args, BuiltinUsage, Usage},
+        format_shell_line,
+    },
+    error::Result,
     registry,
     shell::ShellSpec,
     ui::ui_println,
 };
 
+pub static USAGE: BuiltinUsage = BuiltinUsage {
+    name: "local",
+    about: "manage local shells",
+    subcommands: &[
+        Usage {
+            name: "list",
+            synopsis: "list",
+            about: "list registered local shells and their status",
+        },
+        Usage {
+            name: "add",
+            synopsis: "add <name> <program> [--tags <a,b>]",
+            about: "register a local shell and start it; --tags labels it for policy rules",
+        },
+        Usage {
+            name: "remove",
+            synopsis: "remove <name>",
+            about: "stop a local shell and unregister it",
+        },
+        Usage {
+            name: "start",
+            synopsis: "start <name>",
+            about: "start a registered local shell",
+        },
+        Usage {
+            name: "stop",
+            synopsis: "stop <name>",
+            about: "stop a running local shell",
+        },
+        Usage {
+            name: "help",
+            synopsis: "help [subcommand]",
+            about: "show this help or the usage of one subcommand",
+        },
+    ],
+};
+
+pub struct LocalBuiltin;
+
+#[async_trait]
+impl Builtin for LocalBuiltin {
+    fn name(&self) -> &str {
+        USAGE.name
+    }
+
+    fn usage(&self) -> &BuiltinUsage {
+        &USAGE
+    }
+
+    async fn run(&self, ctx: &mut dyn BuiltinContext, args: &str) -> Result<()> {
+        handle(ctx, args).await
+    }
+}
+
 pub async fn handle(ctx: &mut dyn BuiltinContext, args: &str) -> Result<()> {
     debug!(args = args, "builtin_local_handle start");
-    let parts: Vec<&str> = args.split_whitespace().collect();
-    match parts.as_slice() {
-        [] | ["list"] => {
+    let Some(mut args) = Args::parse(&USAGE, args, "list")? else {
+        return Ok(());
+    };
+    match args.subcommand() {
+        "list" => {
+            args.finish()?;
             let mut printed = false;
             for (name, entry, running) in ctx.list_entries_with_status().await {
                 if let registry::Entry::Shell(spec) = entry
@@ -22,7 +84,12 @@ pub async fn handle(ctx: &mut dyn BuiltinContext, args: &str) -> Result<()> {
                         ui_println("Local shell list:")?;
                         printed = true;
                     }
-                    let line = format_shell_line(&name, &spec, running);
+                    let line = format_shell_line(
+                        &name,
+                        &spec,
+                        running,
+                        ctx.session_cwd(&name).as_deref(),
+                    );
                     ui_println(&line)?;
                 }
             }
@@ -31,38 +98,48 @@ pub async fn handle(ctx: &mut dyn BuiltinContext, args: &str) -> Result<()> {
             }
             info!("local_list ok");
         }
-        ["add", name, program] => {
+        "add" => {
+            let tags = args.option_list("tags")?;
+            let name = args.required("name")?;
+            let program = args.required("program")?;
+            args.finish()?;
             ctx.add_and_start_shell(
-                (*name).to_string(),
+                name.clone(),
                 ShellSpec::Local {
-                    program: (*program).to_string(),
+                    program: program.clone(),
+                    tags,
+                    readonly: false,
+                    on_connect: None,
                 },
             )
             .await?;
-            info!(name = *name, program = *program, "local_add_and_start ok");
+            info!(name = %name, program = %program, "local_add_and_start ok");
         }
-        ["remove", name] => {
-            match ctx.stop_shell_session(name).await {
-                Ok(()) => info!(name = *name, "local_stop ok before remove"),
-                Err(e) => warn!(name = *name, ?e, "local_stop failed before remove"),
+        "remove" => {
+            let name = args.required("name")?;
+            args.finish()?;
+            match ctx.stop_shell_session(&name).await {
+                Ok(()) => info!(name = %name, "local_stop ok before remove"),
+                Err(e) => warn!(name = %name, ?e, "local_stop failed before remove"),
             }
-            ctx.unregister_entry(name);
-            info!(name = *name, "local_remove ok");
+            ctx.unregister_entry(&name);
+            info!(name = %name, "local_remove ok");
         }
-        ["start", name] => {
-            ctx.ensure_shell_session_by_name(name).await?;
-            info!(name = *name, "local_start ok");
+        "start" => {
+            let name = args.required("name")?;
+            args.finish()?;
+            ctx.ensure_shell_session_by_name(&name).await?;
+            info!(name = %name, "local_start ok");
         }
-        ["stop", name] => {
-            ctx.stop_shell_session(name).await?;
-            info!(name = *name, "local_stop ok");
+        "stop" => {
+            let name = args.required("name")?;
+            args.finish()?;
+            ctx.stop_shell_session(&name).await?;
+            info!(name = %name, "local_stop ok");
         }
-        _ => {
-            warn!(args = args, "local_unrecognized");
-            return Err(BuiltinError::LocalUnrecognized {
-                args: args.to_string(),
-            }
-            .into());
+        other => {
+            warn!(sub = other, "local_unsupported");
+            return Err(args.unsupported());
         }
     }
     info!("builtin_local_handle ok");
@@ -0,0 +1,130 @@
+use async_trait::async_trait;
+use tracing::{debug, info};
+
+use crate::{
+    builtins::{
+        Builtin, BuiltinContext,
+        args::{Args, BuiltinUsage, Usage},
+    },
+    error::Result,
+    repl::panes::ViewRequest,
+    ui::ui_println,
+};
+
+pub static USAGE: BuiltinUsage = BuiltinUsage {
+    name: "pane",
+    about: "tile sessions side by side in the full-screen view",
+    subcommands: &[
+        Usage {
+            name: "list",
+            synopsis: "list",
+            about: "list open panes; * marks the focused one",
+        },
+        Usage {
+            name: "add",
+            synopsis: "add <name>",
+            about: "open a pane for a shell, starting it if needed",
+        },
+        Usage {
+            name: "remove",
+            synopsis: "remove <name>",
+            about: "close the pane of a shell; the session keeps running",
+        },
+        Usage {
+            name: "focus",
+            synopsis: "focus <name>",
+            about: "send typed lines to this pane",
+        },
+        Usage {
+            name: "open",
+            synopsis: "open",
+            about: "switch to the full-screen pane view",
+        },
+        Usage {
+            name: "close",
+            synopsis: "close",
+            about: "return from the pane view to the line prompt",
+        },
+        Usage {
+            name: "help",
+            synopsis: "help [subcommand]",
+            about: "show this help or the usage of one subcommand",
+        },
+    ],
+};
+
+pub struct PaneBuiltin;
+
+#[async_trait]
+impl Builtin for PaneBuiltin {
+    fn name(&self) -> &str {
+        USAGE.name
+    }
+
+    fn usage(&self) -> &BuiltinUsage {
+        &USAGE
+    }
+
+    async fn run(&self, ctx: &mut dyn BuiltinContext, args: &str) -> Result<()> {
+        handle(ctx, args).await
+    }
+}
+
+pub async fn handle(ctx: &mut dyn BuiltinContext, args: &str) -> Result<()> {
+    debug!(args = args, "builtin_pane_handle start");
+    let Some(mut args) = Args::parse(&USAGE, args, "list")? else {
+        return Ok(());
+    };
+    match args.subcommand() {
+        "list" => {
+            args.finish()?;
+            let (names, focused) = ctx.list_panes();
+            if names.is_empty() {
+                ui_println("No panes open")?;
+            }
+            for name in names {
+                let marker = if Some(&name) == focused.as_ref() {
+                    "*"
+                } else {
+                    " "
+                };
+                ui_println(&format!("  {marker} {name}"))?;
+            }
+            info!("pane_list ok");
+        }
+        "add" => {
+            let name = args.required("name")?;
+            args.finish()?;
+            if !ctx.add_pane(&name).await? {
+                ui_println(&format!("{name} already has a pane"))?;
+            }
+            info!(name = %name, "pane_add ok");
+        }
+        "remove" => {
+            let name = args.required("name")?;
+            args.finish()?;
+            if !ctx.remove_pane(&name) {
+                ui_println(&format!("{name} has no pane"))?;
+            }
+            info!(name = %name, "pane_remove ok");
+        }
+        "focus" => {
+            let name = args.required("name")?;
+            args.finish()?;
+            ctx.focus_pane(&name)?;
+            info!(name = %name, "pane_focus ok");
+        }
+        "open" => {
+            args.finish()?;
+            ctx.request_view(ViewRequest::Open);
+            info!("pane_open ok");
+        }
+        "close" => {
+            args.finish()?;
+            ctx.request_view(ViewRequest::Close);
+            info!("pane_close ok");
+        }
+        _ => return Err(args.unsupported()),
+    }
+    Ok(())
+}
@@ -1,12 +1,89 @@
+use async_trait::async_trait;
 use tracing::{debug, info};
 
 use crate::{
-    builtins::BuiltinContext,
+    builtins::{
+        Builtin, BuiltinContext,
+        args::{Args, BuiltinUsage, Usage},
+    },
     error::{BuiltinError, Result},
 };
 
-pub async fn handle(_ctx: &mut dyn BuiltinContext, args: &str) -> Result<()> {
-    debug!(args = args, "builtin_quit_handle start");
-    info!("quit requested");
+const LEAVE: &str = "";
+
+static SUBCOMMANDS: &[Usage] = &[
+    Usage {
+        name: LEAVE,
+        synopsis: "",
+        about: "stop the REPL and exit",
+    },
+    Usage {
+        name: "help",
+        synopsis: "help",
+        about: "show this help",
+    },
+];
+
+pub static USAGE: BuiltinUsage = BuiltinUsage {
+    name: "quit",
+    about: "leave psh",
+    subcommands: SUBCOMMANDS,
+};
+
+pub static EXIT_USAGE: BuiltinUsage = BuiltinUsage {
+    name: "exit",
+    about: "leave psh, same as quit",
+    subcommands: SUBCOMMANDS,
+};
+
+pub struct QuitBuiltin;
+
+#[async_trait]
+impl Builtin for QuitBuiltin {
+    fn name(&self) -> &str {
+        USAGE.name
+    }
+
+    fn usage(&self) -> &BuiltinUsage {
+        &USAGE
+    }
+
+    async fn run(&self, ctx: &mut dyn BuiltinContext, args: &str) -> Result<()> {
+        handle(ctx, &USAGE, args).await
+    }
+}
+
+pub struct ExitBuiltin;
+
+#[async_trait]
+impl Builtin for ExitBuiltin {
+    fn name(&self) -> &str {
+        EXIT_USAGE.name
+    }
+
+    fn usage(&self) -> &BuiltinUsage {
+        &EXIT_USAGE
+    }
+
+    async fn run(&self, ctx: &mut dyn BuiltinContext, args: &str) -> Result<()> {
+        handle(ctx, &EXIT_USAGE, args).await
+    }
+}
+
+pub async fn handle(
+    _ctx: &mut dyn BuiltinContext,
+    usage: &'static BuiltinUsage,
+    args: &str,
+) -> Result<()> {
+    debug!(
+        builtin = usage.name,
+        args = args,
+        "builtin_quit_handle start"
+    );
+    let Some(args) = Args::parse(usage, args, LEAVE)? else {
+        return Ok(());
+    };
+    args.finish()?;
+    info!(builtin = usage.name, "quit requested");
     Err(BuiltinError::ExitRequested.into())
 }
@@ -1,8 +1,13 @@
+use async_trait::async_trait;
 use tracing::{debug, info, warn};
 
 use crate::{
-    builtins::{BuiltinContext, format_shell_line},
-    error::{BuiltinError, Result},
+    builtins::{
+        Builtin, BuiltinContext,
+        args::{Args, BuiltinUsage, Usage},
+        format_shell_line,
+    },
+    error::Result,
     registry,
     shell::{ShellSpec, spec::RemoteBackend},
     ui::ui_println,
@@ -10,12 +15,71 @@ use crate::{
 
 const DEFAULT_SSH_PORT: u16 = 22;
 const DEFAULT_TELNET_PORT: u16 = 23;
+const BACKEND_SSH: &str = "ssh";
+const BACKEND_TELNET: &str = "telnet";
+
+pub static USAGE: BuiltinUsage = BuiltinUsage {
+    name: "remote",
+    about: "manage remote shells",
+    subcommands: &[
+        Usage {
+            name: "list",
+            synopsis: "list",
+            about: "list registered remote shells and their status",
+        },
+        Usage {
+            name: "add",
+            synopsis: "add <name> <ssh|telnet> <dest> [port] [--port <n>] [--via <bastion>] [--tags <a,b>] [--on-connect <script>] [-- extra-args...]",
+            about: "register a remote shell and connect it; --via jumps through a bastion (ssh only), --tags labels it for policy rules, --on-connect runs an expect script after connecting",
+        },
+        Usage {
+            name: "remove",
+            synopsis: "remove <name>",
+            about: "disconnect a remote shell and unregister it",
+        },
+        Usage {
+            name: "connect",
+            synopsis: "connect <name>",
+            about: "connect a registered remote shell",
+        },
+        Usage {
+            name: "disconnect",
+            synopsis: "disconnect <name>",
+            about: "disconnect a connected remote shell",
+        },
+        Usage {
+            name: "help",
+            synopsis: "help [subcommand]",
+            about: "show this help or the usage of one subcommand",
+        },
+    ],
+};
+
+pub struct RemoteBuiltin;
+
+#[async_trait]
+impl Builtin for RemoteBuiltin {
+    fn name(&self) -> &str {
+        USAGE.name
+    }
+
+    fn usage(&self) -> &BuiltinUsage {
+        &USAGE
+    }
+
+    async fn run(&self, ctx: &mut dyn BuiltinContext, args: &str) -> Result<()> {
+        handle(ctx, args).await
+    }
+}
 
 pub async fn handle(ctx: &mut dyn BuiltinContext, args: &str) -> Result<()> {
     debug!(args = args, "builtin_remote_handle start");
-    let parts: Vec<&str> = args.split_whitespace().collect();
-    match parts.as_slice() {
-        [] | ["list"] => {
+    let Some(mut args) = Args::parse(&USAGE, args, "list")? else {
+        return Ok(());
+    };
+    match args.subcommand() {
+        "list" => {
+            args.finish()?;
             let mut printed = false;
             for (name, entry, running) in ctx.list_entries_with_status().await {
                 if let registry::Entry::Shell(spec) = entry
@@ -25,7 +89,12 @@ pub async fn handle(ctx: &mut dyn BuiltinContext, args: &str) -> Result<()> {
                         ui_println("Remote shell list:")?;
                         printed = true;
                     }
-                    let line = format_shell_line(&name, &spec, running);
+                    let line = format_shell_line(
+                        &name,
+                        &spec,
+                        running,
+                        ctx.session_cwd(&name).as_deref(),
+                    );
                     ui_println(&line)?;
                 }
             }
@@ -34,100 +103,81 @@ pub async fn handle(ctx: &mut dyn BuiltinContext, args: &str) -> Result<()> {
             }
             info!("remote_list ok");
         }
-        ["add", name, "ssh", dest] => {
-            ctx.add_and_start_shell(
-                name.to_string(),
-                ShellSpec::Remote {
-                    host: dest.to_string(),
-                    backend: RemoteBackend::Ssh {
-                        port: DEFAULT_SSH_PORT,
-                        extra_args: vec![],
-                    },
-                },
-            )
-            .await?;
-            info!(remote = *name, "remote_add ok");
-        }
-        ["add", name, "ssh", dest, rest @ ..] => {
-            let mut port = DEFAULT_SSH_PORT;
-            let mut extra_args: Vec<String> = Vec::new();
-            if let Some(first) = rest.first() {
-                extra_args = match first.parse::<u16>() {
-                    Ok(p) => {
-                        port = p;
-                        rest.iter().skip(1).map(|s| s.to_string()).collect()
-                    }
-                    Err(_) => rest.iter().map(|s| s.to_string()).collect(),
-                }
+        "add" => {
+            let port_flag = args.option_parsed::<u16>("port")?;
+            let via = args.option("via")?;
+            let tags = args.option_list("tags")?;
+            let on_connect = args.option("on-connect")?;
+            let name = args.required("name")?;
+            let backend =
+                args.required_one_of("backend", &[BACKEND_SSH, BACKEND_TELNET])?;
+            let dest = args.required("dest")?;
+            let port_pos = args.optional_parsed::<u16>();
+            let extra_args = args.rest()?;
+            if let (Some(flag), Some(pos)) = (port_flag, port_pos)
+                && flag != pos
+            {
+                warn!(remote = %name, flag, pos, "remote_add port_conflict");
+                return Err(args.error(&format!(
+                    "port {pos} conflicts with `--port {flag}`; give only one"
+                )));
             }
-            ctx.add_and_start_shell(
-                name.to_string(),
-                ShellSpec::Remote {
-                    host: dest.to_string(),
-                    backend: RemoteBackend::Ssh { port, extra_args },
-                },
-            )
-            .await?;
-            info!(remote = *name, "remote_add ok");
-        }
-        ["add", name, "telnet", dest] => {
-            ctx.add_and_start_shell(
-                name.to_string(),
-                ShellSpec::Remote {
-                    host: dest.to_string(),
-                    backend: RemoteBackend::Telnet {
-                        port: DEFAULT_TELNET_PORT,
-                        extra_args: vec![],
-                    },
+            let port = port_flag.or(port_pos);
+            let backend = match backend.as_str() {
+                BACKEND_SSH => RemoteBackend::Ssh {
+                    port: port.unwrap_or(DEFAULT_SSH_PORT),
+                    extra_args,
+                    via,
                 },
-            )
-            .await?;
-            info!(remote = *name, "remote_add ok");
-        }
-        ["add", name, "telnet", dest, rest @ ..] => {
-            let mut port = DEFAULT_TELNET_PORT;
-            let mut extra_args: Vec<String> = Vec::new();
-            if let Some(first) = rest.first() {
-                extra_args = match first.parse::<u16>() {
-                    Ok(p) => {
-                        port = p;
-                        rest.iter().skip(1).map(|s| s.to_string()).collect()
+                _ => {
+                    if via.is_some() {
+                        warn!(remote = %name, "remote_add via_on_telnet");
+                        return Err(args.error("`--via` needs an ssh backend"));
+                    }
+                    RemoteBackend::Telnet {
+                        port: port.unwrap_or(DEFAULT_TELNET_PORT),
+                        extra_args,
                     }
-                    Err(_) => rest.iter().map(|s| s.to_string()).collect(),
                 }
-            }
+            };
             ctx.add_and_start_shell(
-                name.to_string(),
+                name.clone(),
                 ShellSpec::Remote {
-                    host: dest.to_string(),
-                    backend: RemoteBackend::Telnet { port, extra_args },
+                    host: dest,
+                    backend,
+                    tags,
+                    readonly: false,
+                    on_connect,
                 },
             )
             .await?;
-            info!(remote = *name, "remote_add ok");
+            info!(remote = %name, "remote_add ok");
         }
-        ["remove", name] => {
-            match ctx.stop_shell_session(name).await {
-                Ok(()) => info!(name = *name, "remote_stop ok before remove"),
-                Err(e) => warn!(name = *name, ?e, "remote_stop failed before remove"),
+        "remove" => {
+            let name = args.required("name")?;
+            args.finish()?;
+            match ctx.stop_shell_session(&name).await {
+                Ok(()) => info!(name = %name, "remote_stop ok before remove"),
+                Err(e) => warn!(name = %name, ?e, "remote_stop failed before remove"),
             }
-            ctx.unregister_entry(name);
-            info!(name = *name, "local_remove ok");
+            ctx.unregister_entry(&name);
+            info!(name = %name, "remote_remove ok");
         }
-        ["connect", name] => {
-            ctx.ensure_shell_session_by_name(name).await?;
+        "connect" => {
+            let name = args.required("name")?;
+            args.finish()?;
+            ctx.ensure_shell_session_by_name(&name).await?;
             info!(remote = %name, "remote_connect ok")
         }
-        ["disconnect", name] => {
-            ctx.stop_shell_session(name).await?;
+        "disconnect" => {
+            let name = args.required("name")?;
+            args.finish()?;
+            ctx.stop_shell_session(&name).await?;
             info!(remote = %name, "remote_disconnect ok")
         }
-        _ => {
-            warn!(args = args, "remote_unrecognized");
-            return Err(BuiltinError::RemoteUnrecognized {
-                args: args.to_string(),
-            }
-            .into());
+        other => {
+            warn!(sub = other, "remote_unsupported");
+            return Err(args.unsupported());
         }
     }
     info!("builtin_remote_handle ok");
@@ -0,0 +1,71 @@
+use async_trait::async_trait;
+use tracing::{debug, info, warn};
+
+use crate::{
+    builtins::{
+        Builtin, BuiltinContext,
+        args::{Args, BuiltinUsage, Usage},
+    },
+    error::Result,
+    ui::ui_println,
+};
+
+pub static USAGE: BuiltinUsage = BuiltinUsage {
+    name: "secret",
+    about: "answer password prompts without echoing, logging or recording",
+    subcommands: &[
+        Usage {
+            name: "send",
+            synopsis: "send <name>",
+            about: "send the password for a session from the secrets source, or ask for it with echo off",
+        },
+        Usage {
+            name: "ask",
+            synopsis: "ask <name>",
+            about: "ask for a password with echo off and send it to a session",
+        },
+        Usage {
+            name: "help",
+            synopsis: "help [subcommand]",
+            about: "show this help or the usage of one subcommand",
+        },
+    ],
+};
+
+pub struct SecretBuiltin;
+
+#[async_trait]
+impl Builtin for SecretBuiltin {
+    fn name(&self) -> &str {
+        USAGE.name
+    }
+
+    fn usage(&self) -> &BuiltinUsage {
+        &USAGE
+    }
+
+    async fn run(&self, ctx: &mut dyn BuiltinContext, args: &str) -> Result<()> {
+        handle(ctx, args).await
+    }
+}
+
+pub async fn handle(ctx: &mut dyn BuiltinContext, args: &str) -> Result<()> {
+    debug!("builtin_secret_handle start");
+    let Some(mut args) = Args::parse(&USAGE, args, "help")? else {
+        return Ok(());
+    };
+    match args.subcommand() {
+        sub @ ("send" | "ask") => {
+            let name = args.required("name")?;
+            args.finish()?;
+            ctx.send_secret(&name, sub == "ask").await?;
+            ui_println(&format!("password sent to {name}"))?;
+            info!(name = %name, sub = sub, "secret_send ok");
+        }
+        other => {
+            warn!(sub = other, "secret_unsupported");
+            return Err(args.unsupported());
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,97 @@
+use std::sync::Arc;
+
+use tracing::{debug, info, warn};
+
+use crate::builtins::{
+    Builtin,
+    admin::AdminBuiltin,
+    alias::AliasBuiltin,
+    expect::ExpectBuiltin,
+    help::HelpBuiltin,
+    local::LocalBuiltin,
+    pane::PaneBuiltin,
+    quit::{ExitBuiltin, QuitBuiltin},
+    remote::RemoteBuiltin,
+    secret::SecretBuiltin,
+    var::VarBuiltin,
+};
+
+#[derive(Clone, Default)]
+pub struct BuiltinSet {
+    entries: Vec<Arc<dyn Builtin>>,
+}
+
+impl BuiltinSet {
+    pub fn new() -> Self {
+        debug!("builtin_set_new start");
+        let s = Self {
+            entries: Vec::new(),
+        };
+        info!("builtin_set_new ok");
+        s
+    }
+
+    pub fn with_defaults() -> Self {
+        debug!("builtin_set_with_defaults start");
+        let mut s = Self::new();
+        s.register(Arc::new(LocalBuiltin));
+        s.register(Arc::new(RemoteBuiltin));
+        s.register(Arc::new(AdminBuiltin));
+        s.register(Arc::new(AliasBuiltin));
+        s.register(Arc::new(VarBuiltin));
+        s.register(Arc::new(PaneBuiltin));
+        s.register(Arc::new(ExpectBuiltin));
+        s.register(Arc::new(SecretBuiltin));
+        s.register(Arc::new(HelpBuiltin));
+        s.register(Arc::new(QuitBuiltin));
+        s.register(Arc::new(ExitBuiltin));
+        info!(count = s.entries.len(), "builtin_set_with_defaults ok");
+        s
+    }
+
+    pub fn register(&mut self, builtin: Arc<dyn Builtin>) {
+        debug!(name = builtin.name(), "builtin_set_register start");
+        let names = Self::names_of(builtin.as_ref());
+        let before = self.entries.len();
+        self.entries
+            .retain(|b| !Self::names_of(b.as_ref()).iter().any(|n| names.contains(n)));
+        if self.entries.len() != before {
+            warn!(name = builtin.name(), "builtin_set_register replaced");
+        }
+        self.entries.push(builtin);
+        info!(count = self.entries.len(), "builtin_set_register ok");
+    }
+
+    pub fn unregister(&mut self, name: &str) -> Option<Arc<dyn Builtin>> {
+        debug!(name = name, "builtin_set_unregister start");
+        let idx = self.entries.iter().position(|b| b.name() == name)?;
+        let b = self.entries.remove(idx);
+        info!(name = name, "builtin_set_unregister ok");
+        Some(b)
+    }
+
+    pub fn get(&self, name: &str) -> Option<Arc<dyn Builtin>> {
+        self.entries
+            .iter()
+            .find(|b| b.name() == name || b.aliases().contains(&name))
+            .cloned()
+    }
+
+    pub fn list(&self) -> Vec<Arc<dyn Builtin>> {
+        self.entries.clone()
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.entries
+            .iter()
+            .flat_map(|b| Self::names_of(b.as_ref()))
+            .collect()
+    }
+
+    fn names_of(builtin: &dyn Builtin) -> Vec<String> {
+        std::iter::once(builtin.name())
+            .chain(builtin.aliases().iter().copied())
+            .map(str::to_string)
+            .collect()
+    }
+}
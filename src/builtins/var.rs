@@ -0,0 +1,154 @@
+use async_trait::async_trait;
+use tracing::{debug, info, warn};
+
+use crate::{
+    builtins::{
+        Builtin, BuiltinContext,
+        args::{Args, BuiltinUsage, Usage},
+    },
+    error::{BuiltinError, PshError, Result},
+    ui::ui_println,
+};
+
+pub static USAGE: BuiltinUsage = BuiltinUsage {
+    name: "var",
+    about: "psh-side variables substituted as $NAME before lines reach a shell",
+    subcommands: &[
+        Usage {
+            name: "<name>=<value>",
+            synopsis: "<name>=<value>",
+            about: "assign a variable; $(shell: cmd) in the value captures that shell's output",
+        },
+        Usage {
+            name: "list",
+            synopsis: "list",
+            about: "list variables and their values",
+        },
+        Usage {
+            name: "show",
+            synopsis: "show <name>",
+            about: "print the value of one variable",
+        },
+        Usage {
+            name: "set",
+            synopsis: "set <name> <value>",
+            about: "assign a variable without substitution",
+        },
+        Usage {
+            name: "unset",
+            synopsis: "unset <name>",
+            about: "delete a variable",
+        },
+        Usage {
+            name: "help",
+            synopsis: "help [subcommand]",
+            about: "show this help or the usage of one subcommand",
+        },
+    ],
+};
+
+pub struct VarBuiltin;
+
+#[async_trait]
+impl Builtin for VarBuiltin {
+    fn name(&self) -> &str {
+        USAGE.name
+    }
+
+    fn aliases(&self) -> &[&str] {
+        &["set"]
+    }
+
+    fn usage(&self) -> &BuiltinUsage {
+        &USAGE
+    }
+
+    async fn run(&self, ctx: &mut dyn BuiltinContext, args: &str) -> Result<()> {
+        handle(ctx, args).await
+    }
+}
+
+fn unknown_var(name: &str) -> PshError {
+    BuiltinError::InvalidArgs {
+        detail: format!("no variable named `{name}`\ntry `var: list`"),
+    }
+    .into()
+}
+
+fn split_assignment(input: &str) -> Option<(&str, &str)> {
+    let (name, value) = input.trim().split_once('=')?;
+    if name.is_empty() || name.starts_with('-') || name.contains(char::is_whitespace) {
+        return None;
+    }
+    let value = value.trim();
+    let unquoted = ['"', '\''].iter().find_map(|q| {
+        value
+            .strip_prefix(*q)
+            .and_then(|v| v.strip_suffix(*q))
+            .filter(|v| !v.contains(*q))
+    });
+    Some((name, unquoted.unwrap_or(value)))
+}
+
+pub async fn handle(ctx: &mut dyn BuiltinContext, args: &str) -> Result<()> {
+    debug!(args = args, "builtin_var_handle start");
+    if let Some((name, value)) = split_assignment(args) {
+        let value = ctx.substitute(value).await?;
+        ctx.set_var(name.to_string(), value)?;
+        info!(name = name, "var_assign ok");
+        return Ok(());
+    }
+
+    let Some(mut args) = Args::parse(&USAGE, args, "list")? else {
+        return Ok(());
+    };
+    match args.subcommand() {
+        "list" => {
+            args.finish()?;
+            let vars = ctx.list_vars();
+            if vars.is_empty() {
+                ui_println("No variables defined")?;
+            } else {
+                ui_println("Variable list:")?;
+                for (name, value) in vars {
+                    ui_println(&format!("  {name} = {value}"))?;
+                }
+            }
+            info!("var_list ok");
+        }
+        "show" => {
+            let name = args.required("name")?;
+            args.finish()?;
+            match ctx.get_var(&name) {
+                Some(value) => ui_println(&value)?,
+                None => {
+                    warn!(name = %name, "var_show unknown");
+                    return Err(unknown_var(&name));
+                }
+            }
+            info!(name = %name, "var_show ok");
+        }
+        "set" => {
+            let name = args.required("name")?;
+            let value = args.required("value")?;
+            args.finish()?;
+            ctx.set_var(name.clone(), value)?;
+            info!(name = %name, "var_set ok");
+        }
+        "unset" => {
+            let name = args.required("name")?;
+            args.finish()?;
+            if !ctx.remove_var(&name) {
+                warn!(name = %name, "var_unset unknown");
+                return Err(unknown_var(&name));
+            }
+            info!(name = %name, "var_unset ok");
+        }
+        other => {
+            warn!(sub = other, "var_unsupported");
+            return Err(args.unsupported());
+        }
+    }
+    info!("builtin_var_handle ok");
+    Ok(())
+}
@@ -1,16 +1,18 @@
-use clap::{ArgAction, Parser};
+use std::path::PathBuf;
 
-const DEFAULT_COLS: u16 = 80;
-const DEFAULT_ROWS: u16 = 24;
+use clap::{ArgAction, Parser, Subcommand};
+
+pub const DEFAULT_COLS: u16 = 80;
+pub const DEFAULT_ROWS: u16 = 24;
 
 #[derive(Parser, Debug)]
 #[command(name = "psh")]
 #[command(version, about = "Parent Shell")]
 pub struct Cli {
-    #[arg(short, long, default_value_t = DEFAULT_COLS)]
-    pub cols: u16,
-    #[arg(short, long, default_value_t = DEFAULT_ROWS)]
-    pub rows: u16,
+    #[arg(short, long, help = "Session width; defaults to the terminal width")]
+    pub cols: Option<u16>,
+    #[arg(short, long, help = "Session height; defaults to the terminal height")]
+    pub rows: Option<u16>,
     #[arg(short, long, default_value_t = true)]
     pub bash: bool,
     #[arg(short, long, default_value_t = true)]
@@ -22,6 +24,38 @@ pub struct Cli {
         help = "Attach interactively to shell; double Ctrl-] to detach"
     )]
     pub interactive: bool,
+    #[arg(short = 't', long, help = "Start in the split-pane view")]
+    pub tui: bool,
     #[arg(short, long, action = ArgAction::Count)]
     pub verbose: u8,
+    #[arg(
+        short = 'D',
+        long,
+        help = "Run in a background server; `admin: detach` or double Ctrl-] to detach"
+    )]
+    pub detachable: bool,
+    #[arg(
+        long,
+        global = true,
+        help = "Socket of the background server for --detachable and attach"
+    )]
+    pub socket: Option<PathBuf>,
+    #[arg(long, hide = true)]
+    pub daemon_child: bool,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    #[command(about = "Reattach to a detached psh")]
+    Attach,
+    #[command(about = "Watch a session another psh shared with `admin: share`")]
+    Join {
+        name: String,
+        #[arg(short, long, help = "Type into the session; it must be shared write")]
+        write: bool,
+    },
+    #[command(hide = true)]
+    Daemon,
 }
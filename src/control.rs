@@ -0,0 +1,6 @@
+pub mod client;
+pub mod protocol;
+pub mod server;
+
+pub use client::join;
+pub use server::ControlServer;
@@ -0,0 +1,177 @@
+use std::{
+    io::{Write, stdout},
+    path::Path,
+};
+
+use crossterm::terminal;
+use serde::Serialize;
+use serde_json::{Value, json};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines},
+    net::{
+        UnixStream,
+        unix::{OwnedReadHalf, OwnedWriteHalf},
+    },
+};
+use tracing::{debug, info, warn};
+
+use crate::{
+    control::protocol::{
+        InputParams, JSONRPC_VERSION, JoinParams, JoinResult, METHOD_INPUT,
+        METHOD_JOIN, METHOD_LEAVE, NOTIFY_EXITED, NOTIFY_LEFT, NOTIFY_OUTPUT,
+        ViewerParams,
+    },
+    daemon::client::{Input, spawn_stdin_thread},
+    error::{ControlError, Result, UiError},
+    repl::ShareMode,
+    ui,
+};
+
+struct Connection {
+    lines: Lines<BufReader<OwnedReadHalf>>,
+    write: OwnedWriteHalf,
+    next_id: u64,
+}
+
+impl Connection {
+    async fn send<P: Serialize>(&mut self, method: &str, params: P) -> Result<u64> {
+        self.next_id += 1;
+        let request = json!({
+            "jsonrpc": JSONRPC_VERSION,
+            "id": self.next_id,
+            "method": method,
+            "params": params,
+        });
+        let mut line = request.to_string();
+        line.push('\n');
+        self.write
+            .write_all(line.as_bytes())
+            .await
+            .map_err(|source| ControlError::Io { source })?;
+        Ok(self.next_id)
+    }
+
+    async fn recv(&mut self) -> Result<Option<Value>> {
+        let Some(line) = self
+            .lines
+            .next_line()
+            .await
+            .map_err(|source| ControlError::Io { source })?
+        else {
+            return Ok(None);
+        };
+        match serde_json::from_str(&line) {
+            Ok(v) => Ok(Some(v)),
+            Err(e) => {
+                warn!(?e, "control client bad message");
+                Ok(Some(Value::Null))
+            }
+        }
+    }
+}
+
+fn rejected(method: &str, reply: &Value) -> Option<ControlError> {
+    let error = reply.get("error")?;
+    Some(ControlError::Rejected {
+        method: method.to_string(),
+        message: error["message"].as_str().unwrap_or_default().to_string(),
+    })
+}
+
+pub async fn join(path: &Path, name: &str, mode: ShareMode) -> Result<()> {
+    debug!(path = %path.display(), name = name, %mode, "control_join start");
+    let stream = UnixStream::connect(path).await.map_err(|source| {
+        ControlError::ControlConnect {
+            path: path.display().to_string(),
+            source,
+        }
+    })?;
+    let (read, write) = stream.into_split();
+    let mut conn = Connection {
+        lines: BufReader::new(read).lines(),
+        write,
+        next_id: 0,
+    };
+
+    let params = JoinParams {
+        name: name.to_string(),
+        mode,
+    };
+    let id = conn.send(METHOD_JOIN, params).await?;
+    let reply = loop {
+        match conn.recv().await? {
+            Some(msg) if msg["id"] == json!(id) => break msg,
+            Some(_) => {}
+            None => return Err(ControlError::Closed.into()),
+        }
+    };
+    if let Some(e) = rejected(METHOD_JOIN, &reply) {
+        warn!(name = name, "control_join rejected");
+        return Err(e.into());
+    }
+    let joined: JoinResult =
+        serde_json::from_value(reply["result"].clone()).map_err(|e| {
+            ControlError::Rejected {
+                method: METHOD_JOIN.to_string(),
+                message: e.to_string(),
+            }
+        })?;
+
+    terminal::enable_raw_mode().map_err(|e| UiError::RawModeEnable(e.into()))?;
+    let mut input = spawn_stdin_thread();
+    let result: Result<String> = async {
+        let mut out = stdout();
+        out.write_all(joined.screen.as_bytes())
+            .map_err(UiError::IoWrite)?;
+        out.flush().map_err(UiError::IoWrite)?;
+        loop {
+            tokio::select! {
+                msg = conn.recv() => {
+                    let Some(msg) = msg? else {
+                        return Ok("[psh closed the connection]".to_string());
+                    };
+                    match msg["method"].as_str() {
+                        Some(NOTIFY_OUTPUT) => {
+                            let data = msg["params"]["data"].as_str().unwrap_or_default();
+                            out.write_all(data.as_bytes()).map_err(UiError::IoWrite)?;
+                            out.flush().map_err(UiError::IoWrite)?;
+                        }
+                        Some(NOTIFY_EXITED) => return Ok(format!("[{name} exited]")),
+                        Some(NOTIFY_LEFT) => {
+                            return Ok(format!("[{name} is no longer shared]"));
+                        }
+                        _ => {
+                            if let Some(e) = rejected(METHOD_INPUT, &msg) {
+                                warn!(name = name, %e, "control_join input refused");
+                            }
+                        }
+                    }
+                }
+                key = input.recv() => match key {
+                    Some(Input::Bytes(bytes)) if bytes.is_empty() => {}
+                    Some(Input::Bytes(_)) if joined.mode == ShareMode::Read => {}
+                    Some(Input::Bytes(bytes)) => {
+                        let params = InputParams {
+                            viewer: joined.viewer,
+                            data: String::from_utf8_lossy(&bytes).into_owned(),
+                        };
+                        conn.send(METHOD_INPUT, params).await?;
+                    }
+                    Some(Input::Detach) | None => {
+                        let params = ViewerParams { viewer: joined.viewer };
+                        conn.send(METHOD_LEAVE, params).await?;
+                        return Ok(format!("[left {name}]"));
+                    }
+                },
+            }
+        }
+    }
+    .await;
+
+    terminal::disable_raw_mode().map_err(|e| UiError::RawModeDisable(e.into()))?;
+    let msg = result?;
+    ui::ui_println("")?;
+    ui::ui_println(&msg)?;
+    info!(name = name, viewer = joined.viewer, "control_join ok");
+    Ok(())
+}
@@ -0,0 +1,170 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    PshError, registry,
+    repl::{Router, ShareMode},
+    shell::ShellSpec,
+};
+
+pub const JSONRPC_VERSION: &str = "2.0";
+
+pub const PARSE_ERROR: i64 = -32700;
+pub const METHOD_NOT_FOUND: i64 = -32601;
+pub const INVALID_PARAMS: i64 = -32602;
+pub const PSH_ERROR: i64 = -32000;
+
+pub const METHOD_LIST: &str = "sessions.list";
+pub const METHOD_SEND: &str = "session.send";
+pub const METHOD_SUBSCRIBE: &str = "session.subscribe";
+pub const METHOD_UNSUBSCRIBE: &str = "session.unsubscribe";
+pub const METHOD_JOIN: &str = "session.join";
+pub const METHOD_INPUT: &str = "session.input";
+pub const METHOD_LEAVE: &str = "session.leave";
+pub const METHOD_REGISTER: &str = "registry.add";
+pub const METHOD_UNREGISTER: &str = "registry.remove";
+
+pub const NOTIFY_OUTPUT: &str = "session.output";
+pub const NOTIFY_EXITED: &str = "session.exited";
+pub const NOTIFY_LEFT: &str = "session.left";
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Request {
+    #[serde(default)]
+    pub id: Option<Value>,
+    pub method: String,
+    #[serde(default)]
+    pub params: Value,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Response {
+    pub jsonrpc: &'static str,
+    pub id: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<RpcError>,
+}
+
+impl Response {
+    pub fn new(id: Value, outcome: Result<Value, RpcError>) -> Self {
+        let (result, error) = match outcome {
+            Ok(v) => (Some(v), None),
+            Err(e) => (None, Some(e)),
+        };
+        Self {
+            jsonrpc: JSONRPC_VERSION,
+            id,
+            result,
+            error,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Notification {
+    pub jsonrpc: &'static str,
+    pub method: &'static str,
+    pub params: Value,
+}
+
+impl Notification {
+    pub fn new(method: &'static str, params: Value) -> Self {
+        Self {
+            jsonrpc: JSONRPC_VERSION,
+            method,
+            params,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+}
+
+impl RpcError {
+    pub fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+impl From<PshError> for RpcError {
+    fn from(e: PshError) -> Self {
+        Self::new(PSH_ERROR, e.to_string())
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct NameParams {
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SendParams {
+    pub name: String,
+    pub line: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JoinParams {
+    pub name: String,
+    #[serde(default)]
+    pub mode: ShareMode,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JoinResult {
+    pub viewer: u64,
+    pub mode: ShareMode,
+    pub screen: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InputParams {
+    pub viewer: u64,
+    pub data: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ViewerParams {
+    pub viewer: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct RegisterParams {
+    pub name: String,
+    pub spec: ShellSpec,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionInfo {
+    pub name: String,
+    pub kind: &'static str,
+    pub running: bool,
+    pub locked: bool,
+}
+
+impl SessionInfo {
+    pub async fn list(router: &Router) -> Vec<Self> {
+        router
+            .list_entries_with_status()
+            .await
+            .into_iter()
+            .map(|(name, entry, running)| SessionInfo {
+                kind: match &entry {
+                    registry::Entry::Shell(spec) => spec.kind_name(),
+                    registry::Entry::Builtin => "builtin",
+                },
+                locked: router.is_locked(&name),
+                name,
+                running,
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,458 @@
+use std::{
+    collections::HashMap,
+    fs::{self, Permissions},
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use serde::{Serialize, de::DeserializeOwned};
+use serde_json::{Value, json};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::{UnixListener, UnixStream},
+    sync::{Notify, broadcast, mpsc},
+    task::{JoinHandle, JoinSet},
+};
+use tracing::{debug, info, warn};
+
+use crate::{
+    PshError,
+    control::protocol::{
+        INVALID_PARAMS, InputParams, JoinParams, JoinResult, METHOD_INPUT, METHOD_JOIN,
+        METHOD_LEAVE, METHOD_LIST, METHOD_NOT_FOUND, METHOD_REGISTER, METHOD_SEND,
+        METHOD_SUBSCRIBE, METHOD_UNREGISTER, METHOD_UNSUBSCRIBE, NOTIFY_EXITED,
+        NOTIFY_LEFT, NOTIFY_OUTPUT, NameParams, Notification, PARSE_ERROR,
+        RegisterParams, Request, Response, RpcError, SendParams, SessionInfo,
+        ViewerParams,
+    },
+    error::{ControlError, ReplRouterError, Result},
+    repl::{Router, ShareMode, SharedRouter, policy::DenyConfirmer},
+    shell::ShellEvent,
+};
+
+const SOCKET_MODE: u32 = 0o600;
+const SOCKET_DIR_MODE: u32 = 0o700;
+const OUTBOX_CAP: usize = 256;
+
+pub struct ControlServer {
+    path: PathBuf,
+    task: JoinHandle<()>,
+}
+
+impl ControlServer {
+    pub fn start(path: &Path, router: SharedRouter) -> Result<Self> {
+        debug!(path = %path.display(), "control_server_start start");
+        let listener = bind(path)?;
+        let task = tokio::spawn(accept_loop(listener, router));
+        info!(path = %path.display(), "control_server_start ok");
+        Ok(Self {
+            path: path.to_path_buf(),
+            task,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for ControlServer {
+    fn drop(&mut self) {
+        self.task.abort();
+        if let Err(e) = fs::remove_file(&self.path) {
+            warn!(path = %self.path.display(), ?e, "control_server remove socket failed");
+        }
+    }
+}
+
+pub(crate) fn bind(path: &Path) -> Result<UnixListener> {
+    let shown = path.display().to_string();
+    if let Some(dir) = path.parent()
+        && !dir.as_os_str().is_empty()
+        && !dir.exists()
+    {
+        fs::create_dir_all(dir)
+            .and_then(|()| {
+                fs::set_permissions(dir, Permissions::from_mode(SOCKET_DIR_MODE))
+            })
+            .map_err(|source| ControlError::SocketDir {
+                path: dir.display().to_string(),
+                source,
+            })?;
+    }
+    if path.exists() {
+        if std::os::unix::net::UnixStream::connect(path).is_ok() {
+            warn!(path = %shown, "control_bind in_use");
+            return Err(ControlError::InUse { path: shown }.into());
+        }
+        info!(path = %shown, "control_bind removing stale socket");
+        fs::remove_file(path).map_err(|source| ControlError::Bind {
+            path: shown.clone(),
+            source,
+        })?;
+    }
+    let listener = UnixListener::bind(path).map_err(|source| ControlError::Bind {
+        path: shown.clone(),
+        source,
+    })?;
+    fs::set_permissions(path, Permissions::from_mode(SOCKET_MODE)).map_err(
+        |source| ControlError::Bind {
+            path: shown,
+            source,
+        },
+    )?;
+    Ok(listener)
+}
+
+fn same_user(stream: &UnixStream) -> bool {
+    let uid = users::get_current_uid();
+    match stream.peer_cred() {
+        Ok(cred) if cred.uid() == uid => true,
+        Ok(cred) => {
+            warn!(peer = cred.uid(), uid = uid, "control client rejected");
+            false
+        }
+        Err(e) => {
+            warn!(?e, "control client credentials unavailable");
+            false
+        }
+    }
+}
+
+async fn accept_loop(listener: UnixListener, router: SharedRouter) {
+    let mut clients = JoinSet::new();
+    loop {
+        tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok((stream, _)) => {
+                    // The socket is chmodded only after bind, so check the
+                    // peer too rather than trust the file mode alone.
+                    if !same_user(&stream) {
+                        continue;
+                    }
+                    info!("control client connected");
+                    clients.spawn(serve_client(stream, router.clone()));
+                }
+                Err(e) => {
+                    warn!(?e, "control accept failed");
+                    break;
+                }
+            },
+            Some(done) = clients.join_next(), if !clients.is_empty() => {
+                if let Err(e) = done {
+                    warn!(?e, "control client task failed");
+                }
+            }
+        }
+    }
+}
+
+async fn serve_client(stream: UnixStream, router: SharedRouter) {
+    let (read, mut write) = stream.into_split();
+    let (tx, mut rx) = mpsc::channel::<String>(OUTBOX_CAP);
+    let writer = tokio::spawn(async move {
+        while let Some(mut line) = rx.recv().await {
+            line.push('\n');
+            if let Err(e) = write.write_all(line.as_bytes()).await {
+                warn!(?e, "control client write failed");
+                break;
+            }
+        }
+    });
+
+    let mut client = Client {
+        router,
+        tx,
+        subscriptions: HashMap::new(),
+        viewers: HashMap::new(),
+    };
+    let mut lines = BufReader::new(read).lines();
+    loop {
+        match lines.next_line().await {
+            Ok(Some(line)) if line.trim().is_empty() => {}
+            Ok(Some(line)) => {
+                if let Some(reply) = client.handle_line(&line).await
+                    && client.tx.send(reply).await.is_err()
+                {
+                    break;
+                }
+            }
+            Ok(None) => break,
+            Err(e) => {
+                warn!(?e, "control client read failed");
+                break;
+            }
+        }
+    }
+
+    for (_, handle) in client.subscriptions.drain() {
+        handle.abort();
+    }
+    if !client.viewers.is_empty() {
+        let mut router = client.router.lock().await;
+        for (id, viewer) in client.viewers.drain() {
+            viewer.task.abort();
+            router.leave_session(id);
+        }
+    }
+    drop(client);
+    if let Err(e) = writer.await {
+        warn!(?e, "control client writer join failed");
+    }
+    info!("control client disconnected");
+}
+
+struct Client {
+    router: SharedRouter,
+    tx: mpsc::Sender<String>,
+    subscriptions: HashMap<String, JoinHandle<()>>,
+    viewers: HashMap<u64, JoinedViewer>,
+}
+
+struct JoinedViewer {
+    name: String,
+    mode: ShareMode,
+    task: JoinHandle<()>,
+}
+
+impl Client {
+    async fn handle_line(&mut self, line: &str) -> Option<String> {
+        let request: Request = match serde_json::from_str(line) {
+            Ok(r) => r,
+            Err(e) => {
+                warn!(?e, "control request parse failed");
+                let err = RpcError::new(PARSE_ERROR, e.to_string());
+                return encode(&Response::new(Value::Null, Err(err)));
+            }
+        };
+        debug!(method = %request.method, "control request start");
+        let outcome = self.dispatch(&request).await;
+        match &outcome {
+            Ok(_) => info!(method = %request.method, "control request ok"),
+            Err(e) => {
+                warn!(method = %request.method, code = e.code, "control request failed")
+            }
+        }
+        let id = request.id?;
+        encode(&Response::new(id, outcome))
+    }
+
+    async fn dispatch(
+        &mut self,
+        request: &Request,
+    ) -> std::result::Result<Value, RpcError> {
+        match request.method.as_str() {
+            METHOD_LIST => {
+                let router = self.router.lock().await;
+                Ok(json!(SessionInfo::list(&router).await))
+            }
+            METHOD_SEND => {
+                let p: SendParams = params(request)?;
+                self.check_viewer_write(&p.name).await?;
+                unattended(&self.router, async |r| r.send_line(&p.name, &p.line).await)
+                    .await?;
+                Ok(json!(true))
+            }
+            METHOD_SUBSCRIBE => {
+                let p: NameParams = params(request)?;
+                self.check_viewer_read(&p.name).await?;
+                let rx = self.router.lock().await.subscribe(&p.name).await?;
+                let handle = tokio::spawn(forward(p.name.clone(), rx, self.tx.clone()));
+                if let Some(prev) = self.subscriptions.insert(p.name, handle) {
+                    prev.abort();
+                }
+                Ok(json!(true))
+            }
+            METHOD_UNSUBSCRIBE => {
+                let p: NameParams = params(request)?;
+                let removed = self.subscriptions.remove(&p.name);
+                if let Some(handle) = &removed {
+                    handle.abort();
+                }
+                Ok(json!(removed.is_some()))
+            }
+            METHOD_JOIN => {
+                let p: JoinParams = params(request)?;
+                let joined = self
+                    .router
+                    .lock()
+                    .await
+                    .join_session(&p.name, p.mode)
+                    .await?;
+                let task = tokio::spawn(forward_viewer(
+                    p.name.clone(),
+                    joined.id,
+                    joined.events,
+                    joined.kicked,
+                    self.tx.clone(),
+                ));
+                self.viewers.insert(
+                    joined.id,
+                    JoinedViewer {
+                        name: p.name,
+                        mode: joined.mode,
+                        task,
+                    },
+                );
+                Ok(json!(JoinResult {
+                    viewer: joined.id,
+                    mode: joined.mode,
+                    screen: joined.screen,
+                }))
+            }
+            METHOD_INPUT => {
+                let p: InputParams = params(request)?;
+                if !self.viewers.contains_key(&p.viewer) {
+                    let err = ReplRouterError::ViewerUnknown { id: p.viewer };
+                    return Err(PshError::from(err).into());
+                }
+                unattended(&self.router, async |r| {
+                    r.viewer_input(p.viewer, p.data.into_bytes()).await
+                })
+                .await?;
+                Ok(json!(true))
+            }
+            METHOD_LEAVE => {
+                let p: ViewerParams = params(request)?;
+                let Some(viewer) = self.viewers.remove(&p.viewer) else {
+                    return Ok(json!(false));
+                };
+                viewer.task.abort();
+                Ok(json!(self.router.lock().await.leave_session(p.viewer)))
+            }
+            METHOD_REGISTER => {
+                let p: RegisterParams = params(request)?;
+                self.check_not_viewer("register entries")?;
+                self.router.lock().await.add_shell_entry(&p.name, p.spec)?;
+                Ok(json!(true))
+            }
+            METHOD_UNREGISTER => {
+                let p: NameParams = params(request)?;
+                self.check_not_viewer("unregister entries")?;
+                self.router.lock().await.remove_shell_entry(&p.name).await?;
+                Ok(json!(true))
+            }
+            other => Err(RpcError::new(
+                METHOD_NOT_FOUND,
+                format!("unknown method {other}"),
+            )),
+        }
+    }
+
+    // A client that joined a shared session only gets what the share grants:
+    // it may follow and type into that session but not drive the registry.
+    fn check_not_viewer(&self, action: &str) -> Result<()> {
+        if self.viewers.is_empty() {
+            return Ok(());
+        }
+        warn!(action = action, "control viewer restricted");
+        let action = action.to_string();
+        Err(ReplRouterError::ViewerRestricted { action }.into())
+    }
+
+    async fn check_viewer_read(&self, name: &str) -> Result<()> {
+        if self.viewers.is_empty() {
+            return Ok(());
+        }
+        let shared = self.router.lock().await.share_mode(name).is_some();
+        if shared && self.viewers.values().any(|v| v.name == name) {
+            return Ok(());
+        }
+        self.check_not_viewer(&format!("follow {name} without joining it"))
+    }
+
+    async fn check_viewer_write(&self, name: &str) -> Result<()> {
+        if self.viewers.is_empty() {
+            return Ok(());
+        }
+        let Some((&id, viewer)) = self.viewers.iter().find(|(_, v)| v.name == name)
+        else {
+            return self
+                .check_not_viewer(&format!("send to {name} without joining it"));
+        };
+        let share = self.router.lock().await.share_mode(name);
+        if viewer.mode == ShareMode::Write && share == Some(ShareMode::Write) {
+            return Ok(());
+        }
+        warn!(id = id, name = name, "control viewer read_only");
+        let name = name.to_string();
+        Err(ReplRouterError::ViewerReadOnly { id, name }.into())
+    }
+}
+
+// Control clients cannot answer a confirmation, so guarded lines are declined
+// instead of prompting on the terminal that owns the router.
+async fn unattended<T>(
+    router: &SharedRouter,
+    f: impl AsyncFnOnce(&mut Router) -> Result<T>,
+) -> Result<T> {
+    let mut router = router.lock().await;
+    let confirmer = router.replace_confirmer(Arc::new(DenyConfirmer));
+    let res = f(&mut router).await;
+    router.set_confirmer(confirmer);
+    res
+}
+
+fn params<T: DeserializeOwned>(request: &Request) -> std::result::Result<T, RpcError> {
+    serde_json::from_value(request.params.clone())
+        .map_err(|e| RpcError::new(INVALID_PARAMS, e.to_string()))
+}
+
+fn encode<T: Serialize>(value: &T) -> Option<String> {
+    serde_json::to_string(value)
+        .inspect_err(|e| warn!(?e, "control encode failed"))
+        .ok()
+}
+
+async fn forward_viewer(
+    name: String,
+    id: u64,
+    events: broadcast::Receiver<ShellEvent>,
+    kicked: Arc<Notify>,
+    tx: mpsc::Sender<String>,
+) {
+    tokio::select! {
+        () = forward(name.clone(), events, tx.clone()) => {}
+        () = kicked.notified() => {
+            info!(name = %name, viewer = id, "control viewer disconnected by host");
+            let note = Notification::new(NOTIFY_LEFT, json!({ "name": name, "viewer": id }));
+            if let Some(line) = encode(&note) {
+                let _ = tx.send(line).await;
+            }
+        }
+    }
+}
+
+async fn forward(
+    name: String,
+    mut rx: broadcast::Receiver<ShellEvent>,
+    tx: mpsc::Sender<String>,
+) {
+    debug!(name = %name, "control forward start");
+    loop {
+        let note = match rx.recv().await {
+            Ok(ShellEvent::Output(data)) => {
+                Notification::new(NOTIFY_OUTPUT, json!({ "name": name, "data": data }))
+            }
+            Ok(ShellEvent::Exited(_)) | Err(broadcast::error::RecvError::Closed) => {
+                let note = Notification::new(NOTIFY_EXITED, json!({ "name": name }));
+                if let Some(line) = encode(&note) {
+                    let _ = tx.send(line).await;
+                }
+                break;
+            }
+            Ok(_) => continue,
+            Err(broadcast::error::RecvError::Lagged(n)) => {
+                warn!(name = %name, skipped = n, "control forward lagged");
+                continue;
+            }
+        };
+        if let Some(line) = encode(&note)
+            && tx.send(line).await.is_err()
+        {
+            break;
+        }
+    }
+    info!(name = %name, "control forward done");
+}
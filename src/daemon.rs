@@ -0,0 +1,7 @@
+pub mod client;
+pub mod frame;
+pub mod server;
+
+pub use client::{attach, request_detach, start_server};
+pub use frame::Frame;
+pub use server::serve;
@@ -0,0 +1,201 @@
+use std::{
+    env,
+    io::{Read, Write, stdin, stdout},
+    os::unix::process::CommandExt,
+    path::Path,
+    process::{Command, Stdio},
+    thread,
+    time::Duration,
+};
+
+use crossterm::terminal;
+use tokio::{
+    net::UnixStream,
+    signal::unix::{SignalKind, signal},
+    sync::mpsc,
+};
+use tracing::{debug, info, warn};
+
+use crate::{
+    daemon::Frame,
+    error::{ControlError, Result, UiError},
+    ui,
+};
+
+const DETACH_KEY: u8 = 0x1d;
+const STDIN_BUF_SIZE: usize = 1024;
+pub(crate) const INPUT_CHANNEL_CAP: usize = 64;
+const DETACHED_MSG: &str = "[detached from psh; `psh attach` to return]";
+const EXITED_MSG: &str = "[psh exited]";
+const DAEMON_SUBCOMMAND: &str = "daemon";
+const SERVER_START_POLLS: usize = 100;
+const SERVER_START_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+pub(crate) enum Input {
+    Bytes(Vec<u8>),
+    Detach,
+}
+
+pub(crate) fn spawn_stdin_thread() -> mpsc::Receiver<Input> {
+    let (tx, rx) = mpsc::channel(INPUT_CHANNEL_CAP);
+    thread::spawn(move || {
+        let mut buf = [0u8; STDIN_BUF_SIZE];
+        let mut last = None;
+        let mut input = stdin();
+        loop {
+            let n = match input.read(&mut buf) {
+                Ok(0) => break,
+                Ok(n) => n,
+                Err(e) => {
+                    warn!(?e, "attach stdin read failed");
+                    break;
+                }
+            };
+            let mut out = Vec::with_capacity(n);
+            for &byte in &buf[..n] {
+                if byte == DETACH_KEY && last == Some(DETACH_KEY) {
+                    let _ = tx.blocking_send(Input::Bytes(std::mem::take(&mut out)));
+                    let _ = tx.blocking_send(Input::Detach);
+                    return;
+                }
+                if last == Some(DETACH_KEY) {
+                    out.push(DETACH_KEY);
+                }
+                if byte != DETACH_KEY {
+                    out.push(byte);
+                }
+                last = Some(byte);
+            }
+            if tx.blocking_send(Input::Bytes(out)).is_err() {
+                break;
+            }
+        }
+    });
+    rx
+}
+
+pub async fn attach(path: &Path) -> Result<()> {
+    debug!(path = %path.display(), "daemon_attach start");
+    let stream =
+        UnixStream::connect(path)
+            .await
+            .map_err(|source| ControlError::Connect {
+                path: path.display().to_string(),
+                source,
+            })?;
+    let (mut read, mut write) = stream.into_split();
+    let mut winch = signal(SignalKind::window_change()).map_err(UiError::ResizeRead)?;
+    Frame::Attach
+        .write_to(&mut write)
+        .await
+        .map_err(UiError::IoWrite)?;
+    if let Some((cols, rows)) = ui::query_term_size()? {
+        Frame::Resize(cols, rows)
+            .write_to(&mut write)
+            .await
+            .map_err(UiError::IoWrite)?;
+    }
+
+    terminal::enable_raw_mode().map_err(|e| UiError::RawModeEnable(e.into()))?;
+    let mut input = spawn_stdin_thread();
+    let (frames_tx, mut frames) = mpsc::channel::<Option<Frame>>(INPUT_CHANNEL_CAP);
+    let reader = tokio::spawn(async move {
+        loop {
+            let frame = Frame::read_from(&mut read).await.unwrap_or_else(|e| {
+                warn!(?e, "attach read failed");
+                None
+            });
+            let done = frame.is_none();
+            if frames_tx.send(frame).await.is_err() || done {
+                break;
+            }
+        }
+    });
+
+    let result: Result<&str> = async {
+        let mut out = stdout();
+        loop {
+            tokio::select! {
+                frame = frames.recv() => match frame.flatten() {
+                    Some(Frame::Data(bytes)) => {
+                        out.write_all(&bytes).map_err(UiError::IoWrite)?;
+                        out.flush().map_err(UiError::IoWrite)?;
+                    }
+                    Some(Frame::Detach) => return Ok(DETACHED_MSG),
+                    Some(Frame::Exit) | None => return Ok(EXITED_MSG),
+                    Some(Frame::Resize(..) | Frame::Attach) => {}
+                },
+                key = input.recv() => {
+                    let frame = match key {
+                        Some(Input::Bytes(bytes)) if bytes.is_empty() => continue,
+                        Some(Input::Bytes(bytes)) => Frame::Data(bytes),
+                        Some(Input::Detach) | None => return Ok(DETACHED_MSG),
+                    };
+                    frame.write_to(&mut write).await.map_err(UiError::IoWrite)?;
+                }
+                _ = winch.recv() => {
+                    if let Some((cols, rows)) = ui::query_term_size()? {
+                        Frame::Resize(cols, rows)
+                            .write_to(&mut write)
+                            .await
+                            .map_err(UiError::IoWrite)?;
+                    }
+                }
+            }
+        }
+    }
+    .await;
+
+    reader.abort();
+    terminal::disable_raw_mode().map_err(|e| UiError::RawModeDisable(e.into()))?;
+    let msg = result?;
+    ui::ui_println("")?;
+    ui::ui_println(msg)?;
+    info!(path = %path.display(), "daemon_attach ok");
+    Ok(())
+}
+
+pub async fn request_detach(path: &Path) -> Result<()> {
+    debug!(path = %path.display(), "daemon_request_detach start");
+    let mut stream =
+        UnixStream::connect(path)
+            .await
+            .map_err(|source| ControlError::Connect {
+                path: path.display().to_string(),
+                source,
+            })?;
+    Frame::Detach
+        .write_to(&mut stream)
+        .await
+        .map_err(|source| ControlError::Io { source })?;
+    info!(path = %path.display(), "daemon_request_detach ok");
+    Ok(())
+}
+
+pub async fn start_server(path: &Path, flags: &[String]) -> Result<()> {
+    debug!(path = %path.display(), "daemon_start_server start");
+    let shown = path.display().to_string();
+    if std::os::unix::net::UnixStream::connect(path).is_ok() {
+        warn!(path = %shown, "daemon_start_server in_use");
+        return Err(ControlError::InUse { path: shown }.into());
+    }
+    let exe = env::current_exe().map_err(|source| ControlError::Spawn { source })?;
+    Command::new(exe)
+        .args(flags)
+        .arg(DAEMON_SUBCOMMAND)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .process_group(0)
+        .spawn()
+        .map_err(|source| ControlError::Spawn { source })?;
+    for _ in 0..SERVER_START_POLLS {
+        if std::os::unix::net::UnixStream::connect(path).is_ok() {
+            info!(path = %shown, "daemon_start_server ok");
+            return Ok(());
+        }
+        tokio::time::sleep(SERVER_START_POLL_INTERVAL).await;
+    }
+    warn!(path = %shown, "daemon_start_server timed out");
+    Err(ControlError::ServerStart { path: shown }.into())
+}
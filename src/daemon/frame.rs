@@ -0,0 +1,143 @@
+use std::io::{Error as IoError, ErrorKind};
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+const TAG_DATA: u8 = 0;
+const TAG_RESIZE: u8 = 1;
+const TAG_DETACH: u8 = 2;
+const TAG_EXIT: u8 = 3;
+const TAG_ATTACH: u8 = 4;
+const MAX_FRAME_LEN: usize = 1 << 20;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Frame {
+    Data(Vec<u8>),
+    Resize(u16, u16),
+    Detach,
+    Exit,
+    Attach,
+}
+
+impl Frame {
+    pub async fn write_to<W: AsyncWrite + Unpin>(
+        &self,
+        w: &mut W,
+    ) -> Result<(), IoError> {
+        let (tag, payload) = match self {
+            Frame::Data(bytes) => (TAG_DATA, bytes.clone()),
+            Frame::Resize(cols, rows) => {
+                let mut p = cols.to_be_bytes().to_vec();
+                p.extend_from_slice(&rows.to_be_bytes());
+                (TAG_RESIZE, p)
+            }
+            Frame::Detach => (TAG_DETACH, Vec::new()),
+            Frame::Exit => (TAG_EXIT, Vec::new()),
+            Frame::Attach => (TAG_ATTACH, Vec::new()),
+        };
+        let len = u32::try_from(payload.len())
+            .map_err(|_| IoError::new(ErrorKind::InvalidInput, "frame too large"))?;
+        let mut buf = Vec::with_capacity(5 + payload.len());
+        buf.push(tag);
+        buf.extend_from_slice(&len.to_be_bytes());
+        buf.extend_from_slice(&payload);
+        w.write_all(&buf).await?;
+        w.flush().await
+    }
+
+    pub async fn read_from<R: AsyncRead + Unpin>(
+        r: &mut R,
+    ) -> Result<Option<Frame>, IoError> {
+        let tag = match r.read_u8().await {
+            Ok(t) => t,
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let len = r.read_u32().await? as usize;
+        if len > MAX_FRAME_LEN {
+            return Err(IoError::new(ErrorKind::InvalidData, "frame too large"));
+        }
+        let mut payload = vec![0u8; len];
+        r.read_exact(&mut payload).await?;
+        let frame = match tag {
+            TAG_DATA => Frame::Data(payload),
+            TAG_RESIZE if len == 4 => Frame::Resize(
+                u16::from_be_bytes([payload[0], payload[1]]),
+                u16::from_be_bytes([payload[2], payload[3]]),
+            ),
+            TAG_DETACH => Frame::Detach,
+            TAG_EXIT => Frame::Exit,
+            TAG_ATTACH => Frame::Attach,
+            _ => return Err(IoError::new(ErrorKind::InvalidData, "unknown frame")),
+        };
+        Ok(Some(frame))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn encode(frame: &Frame) -> Vec<u8> {
+        let mut buf = Vec::new();
+        frame.write_to(&mut buf).await.expect("write");
+        buf
+    }
+
+    #[tokio::test]
+    async fn frames_round_trip() {
+        let frames = [
+            Frame::Data(b"echo hi\r".to_vec()),
+            Frame::Data(Vec::new()),
+            Frame::Resize(132, 43),
+            Frame::Detach,
+            Frame::Exit,
+            Frame::Attach,
+        ];
+        let mut buf = Vec::new();
+        for frame in &frames {
+            buf.extend(encode(frame).await);
+        }
+        let mut r = buf.as_slice();
+        for frame in &frames {
+            assert_eq!(
+                Frame::read_from(&mut r).await.expect("read").as_ref(),
+                Some(frame)
+            );
+        }
+        assert_eq!(Frame::read_from(&mut r).await.expect("eof"), None);
+    }
+
+    #[tokio::test]
+    async fn rejects_oversize_frames_before_reading_them() {
+        let mut header = vec![TAG_DATA];
+        header.extend_from_slice(&(MAX_FRAME_LEN as u32 + 1).to_be_bytes());
+        let err = Frame::read_from(&mut header.as_slice())
+            .await
+            .expect_err("oversize");
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+
+        let mut at_limit = encode(&Frame::Data(vec![7; MAX_FRAME_LEN])).await;
+        let read = Frame::read_from(&mut at_limit.as_slice())
+            .await
+            .expect("read");
+        assert_eq!(read, Some(Frame::Data(vec![7; MAX_FRAME_LEN])));
+        at_limit.truncate(at_limit.len() - 1);
+        let err = Frame::read_from(&mut at_limit.as_slice())
+            .await
+            .expect_err("truncated");
+        assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+    }
+
+    #[tokio::test]
+    async fn rejects_unknown_and_malformed_frames() {
+        let unknown = [9, 0, 0, 0, 0];
+        let err = Frame::read_from(&mut &unknown[..]).await.expect_err("tag");
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+
+        let short_resize = [TAG_RESIZE, 0, 0, 0, 2, 0, 80];
+        let err = Frame::read_from(&mut &short_resize[..])
+            .await
+            .expect_err("resize");
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,193 @@
+use std::{collections::HashMap, fs, path::Path};
+
+use tokio::{
+    net::{UnixStream, unix::OwnedWriteHalf},
+    signal::unix::{SignalKind, signal},
+    sync::{broadcast::error::RecvError, mpsc},
+    task::JoinHandle,
+};
+use tracing::{debug, info, warn};
+
+use crate::{
+    control::server::bind,
+    daemon::Frame,
+    error::{ControlError, Result},
+    shell::{PtyShell, Shell, ShellEvent, vt::TermEvent},
+};
+
+const SESSION_NAME: &str = "psh";
+const INBOX_CAP: usize = 256;
+
+struct Attached {
+    id: u64,
+    write: OwnedWriteHalf,
+    reader: JoinHandle<()>,
+}
+
+impl Attached {
+    async fn send(&mut self, frame: &Frame) -> bool {
+        match frame.write_to(&mut self.write).await {
+            Ok(()) => true,
+            Err(e) => {
+                warn!(client = self.id, ?e, "daemon client write failed");
+                false
+            }
+        }
+    }
+
+    async fn close(mut self, frame: Frame) {
+        self.send(&frame).await;
+        self.reader.abort();
+    }
+}
+
+pub async fn serve(
+    path: &Path,
+    program: &str,
+    args: &[&str],
+    cols: u16,
+    rows: u16,
+) -> Result<()> {
+    debug!(path = %path.display(), program = program, "daemon_serve start");
+    let listener = bind(path)?;
+    let mut hangup = signal(SignalKind::hangup())
+        .map_err(|source| ControlError::Spawn { source })?;
+    let shell = PtyShell::spawn(SESSION_NAME, program, args, cols, rows).await?;
+    let mut events = shell.subscribe();
+    let (inbox_tx, mut inbox) = mpsc::channel::<(u64, Option<Frame>)>(INBOX_CAP);
+    let mut client: Option<Attached> = None;
+    let mut pending: HashMap<u64, Attached> = HashMap::new();
+    let mut next_id = 0u64;
+
+    loop {
+        tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok((stream, _)) => {
+                    next_id += 1;
+                    debug!(client = next_id, "daemon connection accepted");
+                    pending.insert(next_id, spawn_client(next_id, stream, inbox_tx.clone()));
+                }
+                Err(e) => warn!(?e, "daemon accept failed"),
+            },
+            Some((id, frame)) = inbox.recv() => {
+                if let Some(mut conn) = pending.remove(&id) {
+                    match frame {
+                        Some(Frame::Attach) => {
+                            info!(client = id, "daemon client attached");
+                            if let Some(old) = client.take() {
+                                old.close(Frame::Detach).await;
+                            }
+                            let screen = shell.render_screen().unwrap_or_default();
+                            if conn.send(&Frame::Data(screen.into_bytes())).await {
+                                client = Some(conn);
+                            }
+                        }
+                        Some(Frame::Detach) => {
+                            conn.reader.abort();
+                            if let Some(c) = client.take() {
+                                info!(client = c.id, "daemon detach requested");
+                                c.close(Frame::Detach).await;
+                            }
+                        }
+                        _ => {
+                            warn!(client = id, "daemon connection closed before hello");
+                            conn.reader.abort();
+                        }
+                    }
+                    continue;
+                }
+                if client.as_ref().is_none_or(|c| c.id != id) {
+                    continue;
+                }
+                match frame {
+                    Some(Frame::Data(bytes)) => {
+                        if let Err(e) = shell.send_bytes(bytes).await {
+                            warn!(?e, "daemon forward input failed");
+                        }
+                    }
+                    Some(Frame::Resize(cols, rows)) => {
+                        if let Err(e) = shell.resize(cols, rows).await {
+                            warn!(?e, "daemon resize failed");
+                        }
+                    }
+                    Some(Frame::Attach) => {}
+                    Some(Frame::Detach) | Some(Frame::Exit) | None => {
+                        info!(client = id, "daemon client detached");
+                        if let Some(c) = client.take() {
+                            c.reader.abort();
+                        }
+                    }
+                }
+            }
+            ev = events.recv() => match ev {
+                Ok(ShellEvent::Raw(bytes)) => {
+                    if let Some(c) = client.as_mut()
+                        && !c.send(&Frame::Data(bytes.to_vec())).await
+                    {
+                        client = None;
+                    }
+                }
+                Ok(ShellEvent::Term(TermEvent::CursorQuery)) if client.is_none() => {
+                    let (row, col) = shell
+                        .screen()
+                        .and_then(|s| s.lock().ok().map(|s| s.cursor()))
+                        .unwrap_or_default();
+                    let report = format!("\x1b[{};{}R", row + 1, col + 1);
+                    if let Err(e) = shell.send_bytes(report.into_bytes()).await {
+                        warn!(?e, "daemon cursor report failed");
+                    }
+                }
+                Ok(ShellEvent::Exited(reason)) => {
+                    info!(reason = %reason, "daemon session exited");
+                    break;
+                }
+                Err(RecvError::Closed) => {
+                    info!("daemon session events closed");
+                    break;
+                }
+                Ok(_) => {}
+                Err(RecvError::Lagged(n)) => {
+                    warn!(skipped = n, "daemon output lagged; repainting");
+                    if let Some(c) = client.as_mut() {
+                        let screen = shell.render_screen().unwrap_or_default();
+                        c.send(&Frame::Data(screen.into_bytes())).await;
+                    }
+                }
+            },
+            _ = hangup.recv() => debug!("daemon ignoring hangup"),
+        }
+    }
+
+    if let Some(c) = client.take() {
+        c.close(Frame::Exit).await;
+    }
+    if let Err(e) = fs::remove_file(path) {
+        warn!(path = %path.display(), ?e, "daemon remove socket failed");
+    }
+    info!("daemon_serve ok");
+    Ok(())
+}
+
+fn spawn_client(
+    id: u64,
+    stream: UnixStream,
+    inbox: mpsc::Sender<(u64, Option<Frame>)>,
+) -> Attached {
+    let (mut read, write) = stream.into_split();
+    let reader = tokio::spawn(async move {
+        loop {
+            let frame = match Frame::read_from(&mut read).await {
+                Ok(f) => f,
+                Err(e) => {
+                    warn!(client = id, ?e, "daemon client read failed");
+                    None
+                }
+            };
+            let done = frame.is_none();
+            if inbox.send((id, frame)).await.is_err() || done {
+                break;
+            }
+        }
+    });
+    Attached { id, write, reader }
+}
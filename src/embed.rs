@@ -0,0 +1,218 @@
+use std::sync::Arc;
+
+use tokio::sync::{Mutex, broadcast};
+use tracing::{debug, info, warn};
+
+use crate::{
+    builtins::BuiltinSet,
+    control::protocol::SessionInfo,
+    error::Result,
+    registry::Registry,
+    repl::{
+        Router, SharedRouter,
+        policy::{Confirmer, DenyConfirmer},
+        secrets::{SecretPrompter, SecretSource},
+    },
+    runtime::{
+        bootstrap::{apply_config, choose_default_mode, start_shells},
+        config::{self, PshConfig},
+        logging::{LogControl, init_logging_early, reconfigure_logging_path},
+    },
+    shell::{ShellEvent, ShellFactory, ShellSpec},
+};
+
+const DEFAULT_COLS: u16 = 80;
+const DEFAULT_ROWS: u16 = 24;
+
+pub struct PshBuilder {
+    config: Option<PshConfig>,
+    logging: bool,
+    verbosity: u8,
+    cols: u16,
+    rows: u16,
+    start_shells: bool,
+    confirmer: Option<Arc<dyn Confirmer>>,
+    secret_source: Option<Arc<dyn SecretSource>>,
+    secret_prompter: Option<Arc<dyn SecretPrompter>>,
+    factory: Option<Arc<dyn ShellFactory>>,
+}
+
+impl Default for PshBuilder {
+    fn default() -> Self {
+        Self {
+            config: None,
+            logging: true,
+            verbosity: 0,
+            cols: DEFAULT_COLS,
+            rows: DEFAULT_ROWS,
+            start_shells: false,
+            confirmer: None,
+            secret_source: None,
+            secret_prompter: None,
+            factory: None,
+        }
+    }
+}
+
+impl PshBuilder {
+    pub fn config(mut self, config: PshConfig) -> Self {
+        self.config = Some(config);
+        self
+    }
+
+    pub fn user_config(mut self) -> Self {
+        self.config = Some(config::load_config().0);
+        self
+    }
+
+    pub fn without_logging(mut self) -> Self {
+        self.logging = false;
+        self
+    }
+
+    pub fn verbosity(mut self, verbosity: u8) -> Self {
+        self.verbosity = verbosity;
+        self
+    }
+
+    pub fn size(mut self, cols: u16, rows: u16) -> Self {
+        self.cols = cols;
+        self.rows = rows;
+        self
+    }
+
+    pub fn start_shells(mut self, start: bool) -> Self {
+        self.start_shells = start;
+        self
+    }
+
+    pub fn confirmer(mut self, confirmer: Arc<dyn Confirmer>) -> Self {
+        self.confirmer = Some(confirmer);
+        self
+    }
+
+    pub fn secret_source(mut self, source: Arc<dyn SecretSource>) -> Self {
+        self.secret_source = Some(source);
+        self
+    }
+
+    pub fn secret_prompter(mut self, prompter: Arc<dyn SecretPrompter>) -> Self {
+        self.secret_prompter = Some(prompter);
+        self
+    }
+
+    pub fn shell_factory(mut self, factory: Arc<dyn ShellFactory>) -> Self {
+        self.factory = Some(factory);
+        self
+    }
+
+    pub async fn build(self) -> Result<Psh> {
+        let log_control = self.logging.then(|| init_logging_early(self.verbosity));
+        debug!(logging = self.logging, "psh_build start");
+        let cfg = self.config.unwrap_or_default();
+        let log_control = log_control.map(|mut control| {
+            let path = cfg.logging.as_ref().and_then(|l| l.file.as_ref());
+            reconfigure_logging_path(&mut control, path.map(|s| s.into()));
+            control
+        });
+
+        let builtins = BuiltinSet::with_defaults();
+        let registry = Registry::with_builtins(&builtins);
+        let mut router = Router::new(registry, builtins, self.cols, self.rows);
+        // An embedding host owns the terminal, so guarded lines are declined
+        // unless it supplies a confirmer of its own.
+        router.set_confirmer(self.confirmer.unwrap_or_else(|| Arc::new(DenyConfirmer)));
+        if let Some(factory) = self.factory {
+            router.set_shell_factory(factory);
+        }
+        apply_config(&cfg, &mut router)?;
+        if let Some(source) = self.secret_source {
+            router.set_secret_source(source);
+        }
+        if let Some(prompter) = self.secret_prompter {
+            router.set_secret_prompter(prompter);
+        }
+        if self.start_shells {
+            start_shells(&mut router).await;
+        }
+        choose_default_mode(&cfg, &mut router);
+
+        info!("psh_build ok");
+        Ok(Psh {
+            router: Arc::new(Mutex::new(router)),
+            _log_control: log_control,
+        })
+    }
+}
+
+pub struct Psh {
+    router: SharedRouter,
+    _log_control: Option<LogControl>,
+}
+
+impl Psh {
+    pub fn builder() -> PshBuilder {
+        PshBuilder::default()
+    }
+
+    pub fn router(&self) -> SharedRouter {
+        self.router.clone()
+    }
+
+    pub async fn sessions(&self) -> Vec<SessionInfo> {
+        SessionInfo::list(&*self.router.lock().await).await
+    }
+
+    pub async fn spawn(&self, name: &str, spec: ShellSpec) -> Result<()> {
+        debug!(name = name, "psh_spawn start");
+        let mut router = self.router.lock().await;
+        router.add_shell_entry(name, spec)?;
+        router.ensure_shell_session_by_name(name).await?;
+        info!(name = name, "psh_spawn ok");
+        Ok(())
+    }
+
+    pub async fn start(&self, name: &str) -> Result<()> {
+        let mut router = self.router.lock().await;
+        router.ensure_shell_session_by_name(name).await?;
+        Ok(())
+    }
+
+    pub async fn stop(&self, name: &str) -> Result<()> {
+        self.router.lock().await.stop_shell_session(name).await
+    }
+
+    pub async fn remove(&self, name: &str) -> Result<()> {
+        self.router.lock().await.remove_shell_entry(name).await
+    }
+
+    pub async fn exec(&self, line: &str) -> Result<()> {
+        self.router.lock().await.exec(line).await
+    }
+
+    pub async fn send(&self, name: &str, line: &str) -> Result<()> {
+        self.router.lock().await.send_line(name, line).await
+    }
+
+    pub async fn subscribe(
+        &self,
+        name: &str,
+    ) -> Result<broadcast::Receiver<ShellEvent>> {
+        self.router.lock().await.subscribe(name).await
+    }
+
+    pub async fn screen(&self, name: &str) -> Result<String> {
+        self.router.lock().await.render_screen(name).await
+    }
+
+    pub async fn shutdown(self) {
+        debug!("psh_shutdown start");
+        let mut router = self.router.lock().await;
+        for name in router.list_running_entries().await {
+            if let Err(e) = router.stop_shell_session(&name).await {
+                warn!(name = %name, ?e, "psh_shutdown stop failed");
+            }
+        }
+        info!("psh_shutdown ok");
+    }
+}
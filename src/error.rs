@@ -1,6 +1,8 @@
 use thiserror::Error;
 
 pub mod builtin;
+pub mod control;
+pub mod expect;
 pub mod repl;
 pub mod runtime;
 pub mod shell;
@@ -8,6 +10,8 @@ pub mod sync;
 pub mod ui;
 
 pub use builtin::BuiltinError;
+pub use control::ControlError;
+pub use expect::ExpectError;
 pub use repl::{ReplError, ReplRouterError};
 pub use runtime::RuntimeError;
 pub use shell::ShellError;
@@ -19,6 +23,12 @@ pub enum PshError {
     #[error(transparent)]
     Builtin(#[from] BuiltinError),
 
+    #[error(transparent)]
+    Control(#[from] ControlError),
+
+    #[error(transparent)]
+    Expect(#[from] ExpectError),
+
     #[error(transparent)]
     Repl(#[from] ReplError),
 
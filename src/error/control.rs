@@ -0,0 +1,61 @@
+use std::io::Error as IoError;
+
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum ControlError {
+    #[error("failed to create control socket directory {path}")]
+    SocketDir {
+        path: String,
+        #[source]
+        source: IoError,
+    },
+
+    #[error("failed to bind control socket at {path}")]
+    Bind {
+        path: String,
+        #[source]
+        source: IoError,
+    },
+
+    #[error("socket {path} is in use by another psh")]
+    InUse { path: String },
+
+    #[error("no psh server is listening at {path}; start one with `psh --detachable`")]
+    Connect {
+        path: String,
+        #[source]
+        source: IoError,
+    },
+
+    #[error("no psh is listening on control socket {path}")]
+    ControlConnect {
+        path: String,
+        #[source]
+        source: IoError,
+    },
+
+    #[error("the control socket is disabled ([control] enabled = false)")]
+    ControlDisabled,
+
+    #[error("psh refused {method}: {message}")]
+    Rejected { method: String, message: String },
+
+    #[error("psh closed the control connection")]
+    Closed,
+
+    #[error("control connection failed")]
+    Io {
+        #[source]
+        source: IoError,
+    },
+
+    #[error("failed to start the psh server")]
+    Spawn {
+        #[source]
+        source: IoError,
+    },
+
+    #[error("psh server did not open {path} in time")]
+    ServerStart { path: String },
+}
@@ -0,0 +1,34 @@
+use std::io::Error as IoError;
+
+use thiserror::Error;
+
+use crate::PshError;
+
+#[derive(Debug, Error)]
+pub enum ExpectError {
+    #[error("expect script line {line}: {reason}")]
+    Parse { line: usize, reason: String },
+
+    #[error("failed to read expect script {path}")]
+    Read {
+        path: String,
+        #[source]
+        source: IoError,
+    },
+
+    #[error("timed out after {secs}s waiting for /{pattern}/")]
+    Timeout { pattern: String, secs: f64 },
+
+    #[error("expect script failed: {message}")]
+    Failed { message: String },
+
+    #[error("session {name} closed while the expect script was waiting")]
+    Closed { name: String },
+
+    #[error("on_connect script for {name} failed")]
+    OnConnect {
+        name: String,
+        #[source]
+        source: Box<PshError>,
+    },
+}
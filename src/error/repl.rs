@@ -13,6 +13,100 @@ pub enum ReplRouterError {
 
     #[error("session is not running: {name}")]
     SessionNotRunning { name: String },
+
+    #[error(
+        "session {name} is locked read-only; output still streams, `admin: unlock {name}` to type into it"
+    )]
+    SessionLocked { name: String },
+
+    #[error("session {name} is read-only by configuration (readonly = true)")]
+    SessionReadonly { name: String },
+
+    #[error(
+        "working directory of {name} is unknown; its shell must emit OSC 7 (`admin: cwd-hook {name}` installs a bash/zsh hook)"
+    )]
+    CwdUnknown { name: String },
+
+    #[error("{name} is not an open pane; `pane: add {name}` opens it")]
+    PaneUnknown { name: String },
+
+    #[error(
+        "session {name} is not shared; `admin: share {name}` lets other clients join"
+    )]
+    NotShared { name: String },
+
+    #[error(
+        "session {name} is shared read-only; `admin: share {name} write` allows typing"
+    )]
+    SharedReadOnly { name: String },
+
+    #[error("viewer #{id} of {name} is read-only")]
+    ViewerReadOnly { id: u64, name: String },
+
+    #[error("clients joined to a shared session cannot {action}")]
+    ViewerRestricted { action: String },
+
+    #[error("an expect script is already running on {name}")]
+    ScriptRunning { name: String },
+
+    #[error("no viewer #{id} is joined")]
+    ViewerUnknown { id: u64 },
+
+    #[error("this psh is not detachable; start one with `psh --detachable`")]
+    NotDetachable,
+
+    #[error("an entry named {name} is already registered")]
+    EntryExists { name: String },
+
+    #[error("{name} is a builtin and cannot be changed")]
+    BuiltinEntry { name: String },
+
+    #[error("invalid alias name: {name}")]
+    AliasInvalidName { name: String },
+
+    #[error("alias has no commands: {name}")]
+    AliasEmpty { name: String },
+
+    #[error("alias {name} needs argument ${index}")]
+    AliasMissingArgument { name: String, index: usize },
+
+    #[error("alias expansion too deep at {name}; check for recursive aliases")]
+    AliasDepthExceeded { name: String },
+
+    #[error(
+        "cannot pipe through {name}; only shells and `local:` can be pipeline stages"
+    )]
+    PipeTarget { name: String },
+
+    #[error("pipeline has an empty stage")]
+    PipeEmptyStage,
+
+    #[error("pipeline stage {name} failed: {reason}")]
+    PipeStage { name: String, reason: String },
+
+    #[error("pipeline stage {name} produced no output in time")]
+    PipeTimeout { name: String },
+
+    #[error("session is not being recorded: {name}")]
+    NotRecording { name: String },
+
+    #[error("not sent to {name}: confirmation declined ({reason})")]
+    PolicyDeclined { name: String, reason: String },
+
+    #[error("invalid variable name: {name}")]
+    VarInvalidName { name: String },
+
+    #[error("command substitution for {name} has no command")]
+    SubstitutionEmpty { name: String },
+
+    #[error("command substitution nested too deep at {name}")]
+    SubstitutionDepthExceeded { name: String },
+
+    #[error("no password for {name}: the secrets source had none and none was typed")]
+    SecretUnavailable { name: String },
+
+    #[error("{name} kept asking for a password after {attempts} attempts")]
+    SecretRejected { name: String, attempts: usize },
 }
 
 #[derive(Debug, Error)]
@@ -1,6 +1,7 @@
 use std::io::Error as IoError;
 
 use anyhow::Error as AnyError;
+use regex::Error as RegexError;
 use toml::de::Error as TomlError;
 
 use thiserror::Error;
@@ -21,6 +22,42 @@ pub enum RuntimeError {
         source: TomlError,
     },
 
+    #[error("failed to open audit log at {path}")]
+    AuditOpen {
+        path: String,
+        #[source]
+        source: IoError,
+    },
+
+    #[error("invalid audit redaction pattern {pattern}")]
+    AuditPattern {
+        pattern: String,
+        #[source]
+        source: RegexError,
+    },
+
+    #[error("invalid pattern {pattern} in policy rule {rule}")]
+    PolicyPattern {
+        rule: String,
+        pattern: String,
+        #[source]
+        source: RegexError,
+    },
+
+    #[error("invalid password prompt pattern {pattern}")]
+    SecretsPattern {
+        pattern: String,
+        #[source]
+        source: RegexError,
+    },
+
+    #[error("secrets command {program} could not run")]
+    SecretsCommand {
+        program: String,
+        #[source]
+        source: IoError,
+    },
+
     #[error("failed to reconfigure logging")]
     LoggingReconfigure {
         #[source]
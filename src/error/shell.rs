@@ -1,3 +1,5 @@
+use std::io::Error as IoError;
+
 use anyhow::Error as AnyError;
 use thiserror::Error;
 
@@ -29,6 +31,31 @@ pub enum ShellError {
     #[error("failed to wait on child")]
     Wait(#[source] AnyError),
 
+    #[error("failed to reach {name} via bastion {via}")]
+    Bastion {
+        name: String,
+        via: String,
+        #[source]
+        source: AnyError,
+    },
+
+    #[error("recording i/o failed for {path}")]
+    Recording {
+        path: String,
+        #[source]
+        source: IoError,
+    },
+
+    #[error("recording {path} is not valid asciicast v2 at line {line}: {reason}")]
+    RecordingFormat {
+        path: String,
+        line: usize,
+        reason: String,
+    },
+
+    #[error("shell {name} does not support {what}")]
+    Unsupported { name: String, what: &'static str },
+
     #[error(transparent)]
     Sync(#[from] SyncError),
 }
@@ -0,0 +1,211 @@
+use std::{sync::Arc, time::Duration};
+
+use tokio::time::{Instant, sleep};
+use tracing::{debug, info};
+
+use crate::{
+    builtins::BuiltinSet,
+    error::Result,
+    registry::{self, Registry},
+    repl::{
+        Router,
+        policy::Confirmer,
+        secrets::{SecretPrompter, StaticSecrets},
+    },
+    shell::{
+        Secret, ShellSpec,
+        mock::{MockFactory, MockScript, MockShell},
+        spec::RemoteBackend,
+    },
+    ui,
+};
+
+const HARNESS_COLS: u16 = 80;
+const HARNESS_ROWS: u16 = 24;
+const MOCK_PROGRAM: &str = "mock";
+const MOCK_PORT: u16 = 23;
+const WAIT_TIMEOUT: Duration = Duration::from_secs(5);
+const WAIT_INTERVAL: Duration = Duration::from_millis(10);
+
+struct FixedAnswer(bool);
+
+impl Confirmer for FixedAnswer {
+    fn confirm(&self, _reason: &str, _expected: &str) -> Result<bool> {
+        Ok(self.0)
+    }
+}
+
+struct FixedSecret(Option<String>);
+
+impl SecretPrompter for FixedSecret {
+    fn prompt(&self, _name: &str, _prompt: &str) -> Result<Option<Secret>> {
+        Ok(self.0.as_deref().map(Secret::new))
+    }
+}
+
+pub struct Harness {
+    router: Router,
+    factory: MockFactory,
+    secrets: StaticSecrets,
+}
+
+impl Default for Harness {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Harness {
+    pub fn new() -> Self {
+        debug!("harness_new start");
+        let builtins = BuiltinSet::with_defaults();
+        let registry = Registry::with_builtins(&builtins);
+        let mut router = Router::new(registry, builtins, HARNESS_COLS, HARNESS_ROWS);
+        let factory = MockFactory::new();
+        router.set_shell_factory(Arc::new(factory.clone()));
+        router.set_confirmer(Arc::new(FixedAnswer(false)));
+        let secrets = StaticSecrets::new();
+        router.set_secret_source(Arc::new(secrets.clone()));
+        router.set_secret_prompter(Arc::new(FixedSecret(None)));
+        info!("harness_new ok");
+        Self {
+            router,
+            factory,
+            secrets,
+        }
+    }
+
+    pub fn shell(mut self, name: &str, script: MockScript) -> Self {
+        self.factory.script(name, script);
+        self.router.register_entry(
+            name.to_string(),
+            registry::Entry::Shell(ShellSpec::Local {
+                program: MOCK_PROGRAM.to_string(),
+                tags: Vec::new(),
+                readonly: false,
+                on_connect: None,
+            }),
+        );
+        self
+    }
+
+    pub fn remote(mut self, name: &str, script: MockScript) -> Self {
+        self.factory.script(name, script);
+        self.router.register_entry(
+            name.to_string(),
+            registry::Entry::Shell(ShellSpec::Remote {
+                host: name.to_string(),
+                backend: RemoteBackend::Telnet {
+                    port: MOCK_PORT,
+                    extra_args: Vec::new(),
+                },
+                tags: Vec::new(),
+                readonly: false,
+                on_connect: None,
+            }),
+        );
+        self
+    }
+
+    pub fn default_shell(self, name: &str) -> Self {
+        self.router.set_default_mode(name);
+        self.router.set_current_mode(name);
+        self
+    }
+
+    pub fn confirm(mut self, answer: bool) -> Self {
+        self.router.set_confirmer(Arc::new(FixedAnswer(answer)));
+        self
+    }
+
+    pub fn secret(self, name: &str, secret: &str) -> Self {
+        self.secrets.insert(name, secret);
+        self
+    }
+
+    pub fn typed_secret(mut self, secret: &str) -> Self {
+        self.router
+            .set_secret_prompter(Arc::new(FixedSecret(Some(secret.to_string()))));
+        self
+    }
+
+    pub fn router(&mut self) -> &mut Router {
+        &mut self.router
+    }
+
+    pub fn registry(&self) -> Registry {
+        self.router.get_registry_clone()
+    }
+
+    pub async fn exec(&mut self, line: &str) -> Result<String> {
+        debug!(line = line, "harness_exec start");
+        let (result, output) = ui::capture_output(self.router.exec(line)).await;
+        result?;
+        info!(line = line, "harness_exec ok");
+        Ok(output)
+    }
+
+    pub fn mock(&self, name: &str) -> Option<Arc<MockShell>> {
+        self.factory.shell(name)
+    }
+
+    pub fn inputs(&self, name: &str) -> Vec<String> {
+        self.mock(name).map(|m| m.inputs()).unwrap_or_default()
+    }
+
+    pub fn output(&self, name: &str) -> String {
+        self.mock(name).map(|m| m.output()).unwrap_or_default()
+    }
+
+    pub fn last_status(&self, name: &str) -> Option<i32> {
+        self.router
+            .session_status()
+            .last(name)
+            .and_then(|l| l.exit_status)
+    }
+
+    pub fn prompting(&self, name: &str) -> bool {
+        self.router.session_status().is_prompting(name)
+    }
+
+    pub fn script_running(&self, name: &str) -> bool {
+        self.router.script_running(name)
+    }
+
+    pub async fn running(&self) -> Vec<String> {
+        self.router.list_running_entries().await
+    }
+
+    pub async fn eventually<T>(
+        &self,
+        mut check: impl FnMut(&Self) -> Option<T>,
+    ) -> Option<T> {
+        let deadline = Instant::now() + WAIT_TIMEOUT;
+        loop {
+            if let Some(v) = check(self) {
+                return Some(v);
+            }
+            if Instant::now() >= deadline {
+                return None;
+            }
+            sleep(WAIT_INTERVAL).await;
+        }
+    }
+
+    pub async fn wait_for_exit(&self, name: &str) -> bool {
+        let deadline = Instant::now() + WAIT_TIMEOUT;
+        while self.running().await.iter().any(|n| n == name) {
+            if Instant::now() >= deadline {
+                return false;
+            }
+            sleep(WAIT_INTERVAL).await;
+        }
+        true
+    }
+
+    pub async fn wait_for_output(&self, name: &str, needle: &str) -> bool {
+        self.eventually(|h| h.output(name).contains(needle).then_some(()))
+            .await
+            .is_some()
+    }
+}
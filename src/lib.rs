@@ -1,9 +1,15 @@
 pub mod builtins;
+pub mod control;
+pub mod daemon;
+pub mod embed;
 pub mod error;
+#[cfg(feature = "mock-shell")]
+pub mod harness;
 pub mod registry;
 pub mod repl;
 pub mod runtime;
 pub mod shell;
 pub mod ui;
 
+pub use embed::{Psh, PshBuilder};
 pub use error::{PshError, Result, ShellError};
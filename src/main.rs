@@ -1,22 +1,115 @@
-use anyhow::Result;
+use std::{
+    env,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::{Context, Result};
 use clap::Parser;
-use tracing::debug;
+use tokio::sync::Mutex;
+use tracing::{debug, warn};
 
-use psh::{repl, runtime};
+use psh::{
+    control::{self, ControlServer},
+    daemon, error,
+    repl::{self, ShareMode, panes::ViewRequest},
+    runtime::{self, config},
+    ui,
+};
 
 mod cli;
 
+const DAEMON_CHILD_FLAG: &str = "--daemon-child";
+
+fn forwarded_flags(args: &cli::Cli, socket: &Path) -> Vec<String> {
+    let mut flags = vec!["--socket".to_string(), socket.display().to_string()];
+    if let Some(cols) = args.cols {
+        flags.push(format!("--cols={cols}"));
+    }
+    if let Some(rows) = args.rows {
+        flags.push(format!("--rows={rows}"));
+    }
+    if args.tui {
+        flags.push("--tui".to_string());
+    }
+    flags.extend((0..args.verbose).map(|_| "-v".to_string()));
+    flags
+}
+
+async fn run_daemon(args: &cli::Cli, socket: &Path) -> Result<()> {
+    let exe = env::current_exe().context("locating the psh executable")?;
+    let mut child_args = forwarded_flags(args, socket);
+    child_args.push(DAEMON_CHILD_FLAG.to_string());
+    let child_args: Vec<&str> = child_args.iter().map(String::as_str).collect();
+    let cols = args.cols.unwrap_or(cli::DEFAULT_COLS);
+    let rows = args.rows.unwrap_or(cli::DEFAULT_ROWS);
+    daemon::serve(socket, &exe.to_string_lossy(), &child_args, cols, rows).await?;
+    Ok(())
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     let args = cli::Cli::parse();
 
     debug!("main start");
 
-    let app = runtime::bootstrap(args.cols, args.rows, args.verbose).await?;
+    let socket: PathBuf = args
+        .socket
+        .clone()
+        .unwrap_or_else(config::default_attach_socket);
+    match args.command {
+        Some(cli::Command::Attach) => {
+            daemon::attach(&socket).await?;
+            return Ok(());
+        }
+        Some(cli::Command::Join { ref name, write }) => {
+            let (cfg, _) = config::load_config();
+            let path = config::control_socket_from_config(&cfg)
+                .ok_or(error::ControlError::ControlDisabled)?;
+            let mode = match write {
+                true => ShareMode::Write,
+                false => ShareMode::Read,
+            };
+            control::join(&path, name, mode).await?;
+            return Ok(());
+        }
+        Some(cli::Command::Daemon) => return run_daemon(&args, &socket).await,
+        None if args.detachable && !args.daemon_child => {
+            daemon::start_server(&socket, &forwarded_flags(&args, &socket)).await?;
+            daemon::attach(&socket).await?;
+            return Ok(());
+        }
+        None => {}
+    }
+
+    let (term_cols, term_rows) =
+        ui::query_term_size()?.unwrap_or((cli::DEFAULT_COLS, cli::DEFAULT_ROWS));
+    let cols = args.cols.unwrap_or(term_cols);
+    let rows = args.rows.unwrap_or(term_rows);
+
+    let app = runtime::bootstrap(cols, rows, args.verbose).await?;
     let mut router = app.router;
     let settings = app.repl_settings;
 
-    repl::run_line(&mut router, &settings).await?;
+    if args.tui {
+        router.request_view(ViewRequest::Open);
+    }
+    if args.daemon_child {
+        router.set_daemon_socket(Some(socket));
+    }
+
+    let router = Arc::new(Mutex::new(router));
+    let _control = app.control_socket.and_then(|path| {
+        match ControlServer::start(&path, router.clone()) {
+            Ok(server) => Some(server),
+            Err(e) => {
+                warn!(?e, "control server start failed");
+                None
+            }
+        }
+    });
+
+    repl::run_line(&router, &settings).await?;
 
     Ok(())
 }
@@ -2,7 +2,7 @@ use std::collections::HashMap;
 
 use tracing::{debug, info, warn};
 
-use crate::shell::ShellSpec;
+use crate::{builtins::BuiltinSet, shell::ShellSpec};
 
 #[derive(Debug, Clone)]
 pub enum Entry {
@@ -27,14 +27,12 @@ impl Registry {
         s
     }
 
-    pub fn with_builtins() -> Self {
+    pub fn with_builtins(builtins: &BuiltinSet) -> Self {
         debug!("registry_with_builtins start");
         let mut r = Self::new();
-        r.register_entry("local", Entry::Builtin);
-        r.register_entry("remote", Entry::Builtin);
-        r.register_entry("admin", Entry::Builtin);
-        r.register_entry("quit", Entry::Builtin);
-        r.register_entry("exit", Entry::Builtin);
+        for name in builtins.names() {
+            r.register_entry(name, Entry::Builtin);
+        }
         info!("registry_with_builtins ok");
         r
     }
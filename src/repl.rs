@@ -1,8 +1,28 @@
+pub mod alias;
 pub mod line;
+pub mod lock;
 pub mod mode;
+pub mod panes;
 pub mod parser;
+pub mod pipe;
+pub mod policy;
 pub mod router;
+pub mod scripts;
+pub mod secrets;
+pub mod status;
+pub mod vars;
+pub mod viewers;
+pub mod winsize;
 
+pub use alias::AliasTable;
 pub use line::run as run_line;
+pub use lock::LockState;
 pub use mode::ModeState;
-pub use router::Router;
+pub use panes::PaneSet;
+pub use policy::Policy;
+pub use router::{Router, SharedRouter};
+pub use secrets::Secrets;
+pub use status::SessionStatus;
+pub use vars::VarTable;
+pub use viewers::{ShareMode, ViewerSet};
+pub use winsize::TermSize;
@@ -0,0 +1,146 @@
+use std::collections::BTreeMap;
+
+use tracing::{debug, info, warn};
+
+use crate::error::{ReplRouterError, Result};
+
+const PARAM_SIGIL: char = '$';
+const PARAM_ALL: char = '@';
+const PARAM_COUNT: char = '#';
+
+#[derive(Debug, Default, Clone)]
+pub struct AliasTable {
+    entries: BTreeMap<String, Vec<String>>,
+}
+
+impl AliasTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set(&mut self, name: &str, lines: Vec<String>) -> Result<()> {
+        debug!(name = name, lines = lines.len(), "alias_set start");
+        if name.is_empty() || name.contains(':') || name.contains(char::is_whitespace) {
+            warn!(name = name, "alias_set invalid name");
+            return Err(ReplRouterError::AliasInvalidName {
+                name: name.to_string(),
+            }
+            .into());
+        }
+        if lines.iter().all(|l| l.trim().is_empty()) {
+            warn!(name = name, "alias_set empty body");
+            return Err(ReplRouterError::AliasEmpty {
+                name: name.to_string(),
+            }
+            .into());
+        }
+        if self.entries.insert(name.to_string(), lines).is_some() {
+            warn!(name = name, "alias_set replaced");
+        }
+        info!(name = name, count = self.entries.len(), "alias_set ok");
+        Ok(())
+    }
+
+    pub fn remove(&mut self, name: &str) -> bool {
+        debug!(name = name, "alias_remove start");
+        let removed = self.entries.remove(name).is_some();
+        info!(name = name, removed = removed, "alias_remove ok");
+        removed
+    }
+
+    pub fn get(&self, name: &str) -> Option<Vec<String>> {
+        self.entries.get(name).cloned()
+    }
+
+    pub fn list(&self) -> Vec<(String, Vec<String>)> {
+        self.entries
+            .iter()
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect()
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.entries.keys().cloned().collect()
+    }
+
+    pub fn lookup<'a>(&self, input: &'a str) -> Option<(String, &'a str)> {
+        let trimmed = input.trim_start();
+        let (head, rest) = match trimmed.find(char::is_whitespace) {
+            Some(idx) => (&trimmed[..idx], trimmed[idx..].trim_start()),
+            None => (trimmed, ""),
+        };
+        self.entries
+            .contains_key(head)
+            .then(|| (head.to_string(), rest))
+    }
+}
+
+pub fn expand(name: &str, body: &[String], args: &[String]) -> Result<Vec<String>> {
+    debug!(name = name, args = args.len(), "alias_expand start");
+    let mut used_params = false;
+    let mut out: Vec<String> = Vec::with_capacity(body.len());
+    for line in body.iter().filter(|l| !l.trim().is_empty()) {
+        let mut expanded = String::with_capacity(line.len());
+        let mut chars = line.chars().peekable();
+        while let Some(ch) = chars.next() {
+            if ch != PARAM_SIGIL {
+                expanded.push(ch);
+                continue;
+            }
+            match chars.peek().copied() {
+                Some(PARAM_SIGIL) => {
+                    chars.next();
+                    expanded.push(PARAM_SIGIL);
+                }
+                Some(PARAM_ALL) => {
+                    chars.next();
+                    used_params = true;
+                    expanded.push_str(&args.join(" "));
+                }
+                Some(PARAM_COUNT) => {
+                    chars.next();
+                    used_params = true;
+                    expanded.push_str(&args.len().to_string());
+                }
+                Some(d) if d.is_ascii_digit() && d != '0' => {
+                    let mut digits = String::new();
+                    while let Some(d) =
+                        chars.peek().copied().filter(char::is_ascii_digit)
+                    {
+                        digits.push(d);
+                        chars.next();
+                    }
+                    used_params = true;
+                    let index: usize = digits.parse().unwrap_or(usize::MAX);
+                    match args.get(index - 1) {
+                        Some(v) => expanded.push_str(v),
+                        None => {
+                            warn!(
+                                name = name,
+                                index = index,
+                                "alias_expand missing arg"
+                            );
+                            return Err(ReplRouterError::AliasMissingArgument {
+                                name: name.to_string(),
+                                index,
+                            }
+                            .into());
+                        }
+                    }
+                }
+                _ => expanded.push(PARAM_SIGIL),
+            }
+        }
+        out.push(expanded);
+    }
+
+    if !used_params
+        && !args.is_empty()
+        && let Some(last) = out.last_mut()
+    {
+        last.push(' ');
+        last.push_str(&args.join(" "));
+    }
+    info!(name = name, lines = out.len(), "alias_expand ok");
+    Ok(out)
+}
@@ -1,39 +1,84 @@
-use std::io::{Write, stdout};
+use std::{
+    io::{Write, stdout},
+    time::Duration,
+};
 
 use reedline::Signal;
+use tokio::time::{self, Instant};
 use tracing::{debug, error, info, warn};
 
 use crate::{
     PshError,
     error::{BuiltinError, Result, UiError},
-    repl::{Router, parser::Parsed},
-    runtime::ReplSettings,
-    shell::Shell,
+    registry::Entry,
+    repl::{SharedRouter, panes::ViewRequest, parser::Parsed},
+    runtime::{ReplSettings, config::describe_key},
     ui::{
         PshPrompt,
-        editor::keymap::{MENU_SENTINEL, make_reedline},
+        editor::{
+            completer::CompletionIndex,
+            keymap::{MENU_SENTINEL, make_reedline},
+        },
         prefix_menu::choose_prefix,
+        tui,
     },
 };
 
 const CTRL_C_LITERAL: u8 = 0x03;
+const PROMPT_SETTLE: Duration = Duration::from_millis(300);
+const PROMPT_POLL: Duration = Duration::from_millis(20);
 
-pub async fn run(router: &mut Router, settings: &ReplSettings) -> Result<()> {
+pub async fn run(shared: &SharedRouter, settings: &ReplSettings) -> Result<()> {
     debug!("repl_line_run start");
-    println!("Type lines like:");
     println!(
-        " bash: <cmd> | zsh: <cmd> | local: list | local: add mysh zsh | remote: add r1 user@host | remote: connect r1 | admin: default get | admin: default set bash | quit"
+        "Prefix a line with `<name>:` to target a shell or builtin, e.g. `bash: ls` or `remote: list`."
+    );
+    println!(
+        "Type `help:` for builtins, shells and key bindings; {} opens the prefix menu.",
+        describe_key(settings.menu_key)
     );
 
-    let mut rl = make_reedline(settings);
-    info!("reedline create ok");
-
+    let completions = CompletionIndex::new();
     let mut prompt = PshPrompt::new(settings);
-    prompt.set_registry(router.get_registry_clone());
-    prompt.set_mode_state(router.mode_state());
+    let mut sent = false;
+    let panes = {
+        let router = shared.lock().await;
+        completions.replace(router.completion_entries());
+        prompt.set_registry(router.get_registry_clone());
+        prompt.set_mode_state(router.mode_state());
+        prompt.set_lock_state(router.lock_state());
+        prompt.set_session_status(router.session_status());
+        router.pane_state()
+    };
+    let mut rl = make_reedline(settings, completions.clone());
+    info!("reedline create ok");
 
     loop {
-        match rl.read_line(&prompt) {
+        if panes.take_request() == Some(ViewRequest::Open) {
+            match tui::run(shared, settings).await {
+                Ok(()) => info!("pane view closed"),
+                Err(PshError::Builtin(BuiltinError::ExitRequested)) => {
+                    info!("quit via builtin in pane view");
+                    break;
+                }
+                Err(e) => {
+                    error!(?e, "pane view failed");
+                    print_error(&e)?;
+                }
+            }
+            let router = shared.lock().await;
+            completions.replace(router.completion_entries());
+            prompt.set_registry(router.get_registry_clone());
+            continue;
+        }
+
+        if answer_prompt(shared, std::mem::take(&mut sent)).await? {
+            continue;
+        }
+
+        let sig = rl.read_line(&prompt);
+        let mut router = shared.lock().await;
+        match sig {
             Ok(sig) => match sig {
                 Signal::Success(line) => {
                     debug!(len = line.len(), "read_line success");
@@ -42,7 +87,7 @@ pub async fn run(router: &mut Router, settings: &ReplSettings) -> Result<()> {
                     if line == MENU_SENTINEL {
                         debug!("menu sentinel detected");
                         let current = router.get_current_mode();
-                        match choose_prefix(router, settings, current.as_deref())
+                        match choose_prefix(&router, settings, current.as_deref())
                             .await?
                         {
                             Some(new_name) => {
@@ -62,14 +107,18 @@ pub async fn run(router: &mut Router, settings: &ReplSettings) -> Result<()> {
                     }
 
                     match router.parse_preview(&line) {
-                        Parsed::Entry { name, .. } => {
+                        Parsed::Entry { name, entry, .. } => {
                             router.set_current_mode(&name);
+                            sent = matches!(entry, Entry::Shell(_));
                             info!(name = %name, "explicit prefix set_current_mode ok");
                         }
-                        Parsed::Default { .. } => {}
+                        Parsed::Default { .. } => sent = true,
                     }
 
-                    match router.exec(&line).await {
+                    let res = router.exec(&line).await;
+                    completions.replace(router.completion_entries());
+                    prompt.set_registry(router.get_registry_clone());
+                    match res {
                         Ok(()) => info!("router exec ok"),
                         Err(PshError::Builtin(BuiltinError::ExitRequested)) => {
                             info!("quit via builtin");
@@ -77,24 +126,14 @@ pub async fn run(router: &mut Router, settings: &ReplSettings) -> Result<()> {
                         }
                         Err(e) => {
                             error!(?e, "router exec failed");
-                            let mut out = stdout();
-                            out.write_all(b"\r").map_err(UiError::IoWrite)?;
-                            out.write_all(format!("error: {e}").as_bytes())
-                                .map_err(UiError::IoWrite)?;
-                            out.write_all(b"\r\n").map_err(UiError::IoWrite)?;
-                            out.flush().map_err(UiError::IoWrite)?;
+                            print_error(&e)?;
                         }
                     }
                 }
                 Signal::CtrlC => {
                     info!("ctrl-c requested");
                     if let Some(name) = router.get_current_mode() {
-                        let res = match router.ensure_shell_session_by_name(&name).await
-                        {
-                            Ok(s) => s.send_bytes(vec![CTRL_C_LITERAL]).await,
-                            Err(e) => Err(e),
-                        };
-                        match res {
+                        match router.send_bytes(&name, vec![CTRL_C_LITERAL]).await {
                             Ok(()) => info!(shell = %name, "ctrl_c forwarded"),
                             Err(e) => warn!(?e,shell = %name, "ctrl_c forward_failed"),
                         }
@@ -121,3 +160,46 @@ pub async fn run(router: &mut Router, settings: &ReplSettings) -> Result<()> {
     info!("repl_line_run done");
     Ok(())
 }
+
+// A password prompt (sudo, su, ssh-add) follows the line that caused it within
+// moments. Reading the answer here keeps it off the screen, out of the editor
+// history and out of the audit log and recordings.
+async fn answer_prompt(shared: &SharedRouter, settle: bool) -> Result<bool> {
+    let Some(name) = shared.lock().await.get_current_mode() else {
+        return Ok(false);
+    };
+    let deadline = Instant::now()
+        + if settle {
+            PROMPT_SETTLE
+        } else {
+            Duration::ZERO
+        };
+    while !shared.lock().await.prompt_pending(&name).await {
+        if Instant::now() >= deadline {
+            return Ok(false);
+        }
+        time::sleep(PROMPT_POLL).await;
+    }
+    debug!(name = %name, "repl_answer_prompt start");
+    let mut router = shared.lock().await;
+    router.session_status().set_prompting(&name, false);
+    match router.send_secret(&name, true).await {
+        Ok(()) => info!(name = %name, "repl_answer_prompt ok"),
+        Err(e) => {
+            warn!(name = %name, ?e, "repl_answer_prompt failed");
+            print_error(&e)?;
+        }
+    }
+    Ok(true)
+}
+
+fn print_error(e: &PshError) -> Result<()> {
+    let mut out = stdout();
+    for msg in format!("error: {e}").lines() {
+        out.write_all(b"\r").map_err(UiError::IoWrite)?;
+        out.write_all(msg.as_bytes()).map_err(UiError::IoWrite)?;
+        out.write_all(b"\r\n").map_err(UiError::IoWrite)?;
+    }
+    out.flush().map_err(UiError::IoWrite)?;
+    Ok(())
+}
@@ -0,0 +1,50 @@
+use std::{
+    collections::BTreeSet,
+    sync::{Arc, RwLock},
+};
+
+use tracing::{debug, info};
+
+#[derive(Clone, Default)]
+pub struct LockState {
+    names: Arc<RwLock<BTreeSet<String>>>,
+}
+
+impl LockState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn lock(&self, name: &str) -> bool {
+        debug!(name = name, "lock_state_lock start");
+        let added = self
+            .names
+            .write()
+            .map(|mut w| w.insert(name.to_string()))
+            .unwrap_or(false);
+        info!(name = name, added = added, "lock_state_lock ok");
+        added
+    }
+
+    pub fn unlock(&self, name: &str) -> bool {
+        debug!(name = name, "lock_state_unlock start");
+        let removed = self
+            .names
+            .write()
+            .map(|mut w| w.remove(name))
+            .unwrap_or(false);
+        info!(name = name, removed = removed, "lock_state_unlock ok");
+        removed
+    }
+
+    pub fn is_locked(&self, name: &str) -> bool {
+        self.names.read().map(|r| r.contains(name)).unwrap_or(false)
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.names
+            .read()
+            .map(|r| r.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+}
@@ -0,0 +1,123 @@
+use std::sync::{Arc, RwLock};
+
+use tracing::{debug, info};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ViewRequest {
+    Open,
+    Close,
+}
+
+#[derive(Default)]
+struct Inner {
+    names: Vec<String>,
+    focus: usize,
+    request: Option<ViewRequest>,
+    active: bool,
+}
+
+#[derive(Clone, Default)]
+pub struct PaneSet {
+    inner: Arc<RwLock<Inner>>,
+}
+
+impl PaneSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&self, name: &str) -> bool {
+        debug!(name = name, "pane_set_add start");
+        let added = self
+            .inner
+            .write()
+            .map(|mut w| {
+                if w.names.iter().any(|n| n == name) {
+                    return false;
+                }
+                w.names.push(name.to_string());
+                w.focus = w.names.len() - 1;
+                true
+            })
+            .unwrap_or(false);
+        info!(name = name, added = added, "pane_set_add ok");
+        added
+    }
+
+    pub fn remove(&self, name: &str) -> bool {
+        debug!(name = name, "pane_set_remove start");
+        let removed = self
+            .inner
+            .write()
+            .map(|mut w| {
+                let Some(idx) = w.names.iter().position(|n| n == name) else {
+                    return false;
+                };
+                w.names.remove(idx);
+                if w.focus >= w.names.len() {
+                    w.focus = w.names.len().saturating_sub(1);
+                }
+                true
+            })
+            .unwrap_or(false);
+        info!(name = name, removed = removed, "pane_set_remove ok");
+        removed
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.inner
+            .read()
+            .map(|r| r.names.clone())
+            .unwrap_or_default()
+    }
+
+    pub fn focused(&self) -> Option<String> {
+        self.inner
+            .read()
+            .ok()
+            .and_then(|r| r.names.get(r.focus).cloned())
+    }
+
+    pub fn focus(&self, name: &str) -> bool {
+        self.inner
+            .write()
+            .map(|mut w| match w.names.iter().position(|n| n == name) {
+                Some(idx) => {
+                    w.focus = idx;
+                    true
+                }
+                None => false,
+            })
+            .unwrap_or(false)
+    }
+
+    pub fn focus_next(&self) -> Option<String> {
+        let mut w = self.inner.write().ok()?;
+        if w.names.is_empty() {
+            return None;
+        }
+        w.focus = (w.focus + 1) % w.names.len();
+        w.names.get(w.focus).cloned()
+    }
+
+    pub fn request(&self, request: ViewRequest) {
+        debug!(?request, "pane_set_request");
+        if let Ok(mut w) = self.inner.write() {
+            w.request = Some(request);
+        }
+    }
+
+    pub fn take_request(&self) -> Option<ViewRequest> {
+        self.inner.write().ok().and_then(|mut w| w.request.take())
+    }
+
+    pub fn set_active(&self, active: bool) {
+        if let Ok(mut w) = self.inner.write() {
+            w.active = active;
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.inner.read().map(|r| r.active).unwrap_or(false)
+    }
+}
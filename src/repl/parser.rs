@@ -2,6 +2,8 @@ use tracing::{debug, info};
 
 use crate::registry::{self, Registry};
 
+pub const PIPE_OPERATOR: &str = "|>";
+
 #[derive(Debug, Clone)]
 pub enum Parsed {
     Default {
@@ -59,3 +61,43 @@ pub fn parse(registry: &Registry, input: &str) -> Parsed {
         command: input.trim().to_string(),
     }
 }
+
+pub fn split_pipeline(input: &str) -> Vec<&str> {
+    debug!(input = input, "split_pipeline start");
+    let mut stages: Vec<&str> = Vec::new();
+    let mut quote: Option<char> = None;
+    let mut escaped = false;
+    let mut start = 0usize;
+    let mut chars = input.char_indices().peekable();
+    while let Some((idx, ch)) = chars.next() {
+        if escaped {
+            escaped = false;
+            continue;
+        }
+        match (quote, ch) {
+            (Some('\''), '\'') => quote = None,
+            (Some('\''), _) => {}
+            (_, '\\') => escaped = true,
+            (Some('"'), '"') => quote = None,
+            (Some(_), _) => {}
+            (None, '\'' | '"') => quote = Some(ch),
+            (None, _) if input[idx..].starts_with(PIPE_OPERATOR) => {
+                stages.push(input[start..idx].trim());
+                start = idx + PIPE_OPERATOR.len();
+                chars.next();
+            }
+            (None, _) => {}
+        }
+    }
+    stages.push(input[start..].trim());
+    info!(stages = stages.len(), "split_pipeline ok");
+    stages
+}
+
+pub fn parse_pipeline(registry: &Registry, input: &str) -> Option<Vec<Parsed>> {
+    let stages = split_pipeline(input);
+    if stages.len() < 2 {
+        return None;
+    }
+    Some(stages.into_iter().map(|s| parse(registry, s)).collect())
+}
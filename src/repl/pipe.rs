@@ -0,0 +1,476 @@
+use std::{
+    process::Stdio,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    process::Command,
+    sync::mpsc,
+    task::JoinSet,
+    time::timeout,
+};
+use tracing::{debug, error, info, warn};
+
+use crate::{
+    error::{PshError, ReplRouterError, Result, ShellError, SyncError},
+    shell::{Shell, ShellEvent},
+};
+
+const PIPE_CHANNEL_CAP: usize = 256;
+const PIPE_IDLE_TIMEOUT: Duration = Duration::from_secs(120);
+const LOCAL_SHELL: &str = "sh";
+const LOCAL_SHELL_FLAG: &str = "-c";
+const MARKER_PREFIX: &str = "__PSH_PIPE";
+const BASE64_LINE_BYTES: usize = 57;
+const LOCAL_READ_BUF_SIZE: usize = 4096;
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+const BASE64_PAD: char = '=';
+
+static PIPE_SEQ: AtomicU64 = AtomicU64::new(0);
+
+pub enum Endpoint {
+    Session { name: String, shell: Arc<dyn Shell> },
+    Local,
+}
+
+impl Endpoint {
+    pub fn label(&self) -> &str {
+        match self {
+            Endpoint::Session { name, .. } => name,
+            Endpoint::Local => "local",
+        }
+    }
+}
+
+pub struct Stage {
+    pub endpoint: Endpoint,
+    pub command: String,
+}
+
+pub(crate) fn base64_encode(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let b = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ];
+        let n = (u32::from(b[0]) << 16) | (u32::from(b[1]) << 8) | u32::from(b[2]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(
+                    BASE64_ALPHABET[((n >> (18 - 6 * i)) & 0x3f) as usize] as char,
+                );
+            } else {
+                out.push(BASE64_PAD);
+            }
+        }
+    }
+    out
+}
+
+fn base64_decode(line: &str) -> std::result::Result<Vec<u8>, String> {
+    if !line.len().is_multiple_of(4) {
+        return Err(format!("{} characters is not a multiple of 4", line.len()));
+    }
+    let data = line.trim_end_matches(BASE64_PAD);
+    if line.len() - data.len() > 2 {
+        return Err("too much padding".to_string());
+    }
+    let mut out = Vec::with_capacity(line.len() / 4 * 3);
+    let mut acc = 0u32;
+    let mut bits = 0u32;
+    for (idx, c) in data.bytes().enumerate() {
+        let Some(v) = BASE64_ALPHABET.iter().position(|a| *a == c) else {
+            return Err(format!(
+                "unexpected {:?} at column {}",
+                char::from(c),
+                idx + 1
+            ));
+        };
+        acc = (acc << 6) | v as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((acc >> bits) as u8);
+            acc &= (1 << bits) - 1;
+        }
+    }
+    Ok(out)
+}
+
+fn markers() -> (String, String, String) {
+    let id = format!(
+        "{}_{}",
+        std::process::id(),
+        PIPE_SEQ.fetch_add(1, Ordering::Relaxed)
+    );
+    (
+        format!("{MARKER_PREFIX}_BEGIN_{id}"),
+        format!("{MARKER_PREFIX}_END_{id}"),
+        format!("{MARKER_PREFIX}_EOF_{id}"),
+    )
+}
+
+fn wrap_session_command(
+    command: &str,
+    has_input: bool,
+    captures: bool,
+    begin: &str,
+    end: &str,
+    eof: &str,
+) -> String {
+    let body = if has_input {
+        format!("base64 -d <<'{eof}' | {{ {command}; }}")
+    } else {
+        command.to_string()
+    };
+    if !captures {
+        return body;
+    }
+    let split = |m: &str| {
+        let (head, tail) = m.split_at(MARKER_PREFIX.len());
+        format!("printf '\\n%s%s\\n' '{head}' '{tail}'")
+    };
+    format!("{}; {{ {body}; }} | base64; {}", split(begin), split(end))
+}
+
+#[cfg(feature = "mock-shell")]
+fn capture_marker(s: &str) -> Option<(String, &str)> {
+    let s = s.strip_prefix("printf '\\n%s%s\\n' '")?;
+    let (head, s) = s.split_once("' '")?;
+    let (tail, s) = s.split_once('\'')?;
+    Some((format!("{head}{tail}"), s))
+}
+
+// Splits a line built by `wrap_session_command` for a capture back into its
+// begin marker, command and end marker, so the mock shell can answer it.
+#[cfg(feature = "mock-shell")]
+pub(crate) fn unwrap_capture(line: &str) -> Option<(String, &str, String)> {
+    let (begin, rest) = capture_marker(line)?;
+    let rest = rest.strip_prefix("; { ")?;
+    let (body, rest) = rest.rsplit_once("; } | base64; ")?;
+    let (end, rest) = capture_marker(rest)?;
+    rest.is_empty().then_some((begin, body, end))
+}
+
+fn stage_error(stage: &str, reason: impl Into<String>) -> ReplRouterError {
+    ReplRouterError::PipeStage {
+        name: stage.to_string(),
+        reason: reason.into(),
+    }
+}
+
+async fn run_session_stage(
+    name: String,
+    shell: Arc<dyn Shell>,
+    command: String,
+    input: Option<mpsc::Receiver<String>>,
+    output: Option<mpsc::Sender<String>>,
+) -> Result<Option<i32>> {
+    debug!(stage = %name, "pipe_session_stage start");
+    let (begin, end, eof) = markers();
+    let line = wrap_session_command(
+        &command,
+        input.is_some(),
+        output.is_some(),
+        &begin,
+        &end,
+        &eof,
+    );
+
+    let mut rx = shell.subscribe();
+    shell.send_line(line).await?;
+
+    let feeder = input.map(|mut input| {
+        let shell = shell.clone();
+        let eof = eof.clone();
+        let name = name.clone();
+        tokio::spawn(async move {
+            while let Some(chunk) = input.recv().await {
+                decode_chunk(&name, &chunk)?;
+                shell.send_line(chunk).await?;
+            }
+            shell.send_line(eof).await
+        })
+    });
+
+    if let Some(output) = output {
+        let mut pending = String::new();
+        let mut capturing = false;
+        'stream: loop {
+            let event = timeout(PIPE_IDLE_TIMEOUT, rx.recv())
+                .await
+                .map_err(|_| ReplRouterError::PipeTimeout { name: name.clone() })?;
+            match event {
+                Ok(ShellEvent::Output(chunk)) => {
+                    pending.push_str(&chunk);
+                    while let Some(idx) = pending.find('\n') {
+                        let raw: String = pending.drain(..=idx).collect();
+                        let text = raw.trim();
+                        if !capturing {
+                            capturing = text == begin;
+                            continue;
+                        }
+                        if text == end {
+                            break 'stream;
+                        }
+                        if text.is_empty() {
+                            continue;
+                        }
+                        output.send(text.to_string()).await.map_err(|e| {
+                            ShellError::from(SyncError::ChannelClosed {
+                                context: format!("pipe output {name}: {e}"),
+                            })
+                        })?;
+                    }
+                }
+                Ok(ShellEvent::Exited(reason)) => {
+                    warn!(stage = %name, %reason, "pipe_session_stage exited");
+                    return Err(stage_error(
+                        &name,
+                        format!("session exited: {reason}"),
+                    )
+                    .into());
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    error!(stage = %name, ?e, "pipe_session_stage recv failed");
+                    return Err(stage_error(&name, format!("output lost: {e}")).into());
+                }
+            }
+        }
+    }
+
+    if let Some(feeder) = feeder {
+        feeder
+            .await
+            .map_err(|e| ShellError::from(SyncError::Join(e)))??;
+    }
+    info!(stage = %name, "pipe_session_stage ok");
+    Ok(None)
+}
+
+async fn run_local_stage(
+    command: String,
+    input: Option<mpsc::Receiver<String>>,
+    output: Option<mpsc::Sender<String>>,
+) -> Result<Option<i32>> {
+    debug!(command = %command, "pipe_local_stage start");
+    let mut child = Command::new(LOCAL_SHELL)
+        .arg(LOCAL_SHELL_FLAG)
+        .arg(&command)
+        .stdin(if input.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(if output.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::inherit()
+        })
+        .spawn()
+        .map_err(|e| stage_error("local", format!("spawn failed: {e}")))?;
+
+    let feeder = match (input, child.stdin.take()) {
+        (Some(mut input), Some(mut stdin)) => Some(tokio::spawn(async move {
+            while let Some(chunk) = input.recv().await {
+                let bytes = decode_chunk("local", &chunk)?;
+                if let Err(e) = stdin.write_all(&bytes).await {
+                    warn!(?e, "pipe_local_stage stdin closed early");
+                    break;
+                }
+            }
+            Ok::<(), PshError>(())
+        })),
+        _ => None,
+    };
+
+    if let (Some(output), Some(mut stdout)) = (output, child.stdout.take()) {
+        let mut carry: Vec<u8> = Vec::new();
+        let mut buf = [0u8; LOCAL_READ_BUF_SIZE];
+        loop {
+            let n = stdout
+                .read(&mut buf)
+                .await
+                .map_err(|e| stage_error("local", format!("read failed: {e}")))?;
+            if n == 0 {
+                break;
+            }
+            carry.extend_from_slice(&buf[..n]);
+            let whole = carry.len() / BASE64_LINE_BYTES * BASE64_LINE_BYTES;
+            for chunk in carry[..whole].chunks(BASE64_LINE_BYTES) {
+                send_chunk(&output, chunk).await?;
+            }
+            carry.drain(..whole);
+        }
+        if !carry.is_empty() {
+            send_chunk(&output, &carry).await?;
+        }
+    }
+
+    let fed = match feeder {
+        Some(feeder) => feeder
+            .await
+            .map_err(|e| ShellError::from(SyncError::Join(e)))?,
+        None => Ok(()),
+    };
+    if let Err(e) = fed {
+        warn!(?e, "pipe_local_stage input rejected");
+        child.kill().await.ok();
+        return Err(e);
+    }
+    let status = child
+        .wait()
+        .await
+        .map_err(|e| stage_error("local", format!("wait failed: {e}")))?;
+    info!(status = %status, "pipe_local_stage ok");
+    Ok(status.code())
+}
+
+fn decode_chunk(stage: &str, chunk: &str) -> Result<Vec<u8>> {
+    base64_decode(chunk).map_err(|reason| {
+        warn!(stage = stage, %reason, "pipe_decode failed");
+        stage_error(stage, format!("invalid base64 input: {reason}")).into()
+    })
+}
+
+async fn send_chunk(output: &mpsc::Sender<String>, chunk: &[u8]) -> Result<()> {
+    output.send(base64_encode(chunk)).await.map_err(|e| {
+        ShellError::from(SyncError::ChannelClosed {
+            context: format!("pipe output local: {e}"),
+        })
+        .into()
+    })
+}
+
+pub async fn capture(
+    endpoint: Endpoint,
+    command: String,
+) -> Result<(String, Option<i32>)> {
+    debug!(stage = endpoint.label(), "pipe_capture start");
+    let (tx, mut rx) = mpsc::channel::<String>(PIPE_CHANNEL_CAP);
+    let stage = async move {
+        match endpoint {
+            Endpoint::Session { name, shell } => {
+                run_session_stage(name, shell, command, None, Some(tx)).await
+            }
+            Endpoint::Local => run_local_stage(command, None, Some(tx)).await,
+        }
+    };
+    let collect = async {
+        let mut bytes = Vec::new();
+        while let Some(line) = rx.recv().await {
+            bytes.extend(decode_chunk("capture", &line)?);
+        }
+        Ok::<_, PshError>(bytes)
+    };
+    let (res, bytes) = tokio::join!(stage, collect);
+    let bytes = bytes?;
+    let status = res?;
+    let text = String::from_utf8_lossy(&bytes)
+        .trim_end_matches(['\n', '\r'])
+        .to_string();
+    info!(len = text.len(), "pipe_capture ok");
+    Ok((text, status))
+}
+
+pub async fn run(stages: Vec<Stage>) -> Result<Vec<Option<i32>>> {
+    debug!(stages = stages.len(), "pipe_run start");
+    if stages.iter().any(|s| s.command.trim().is_empty()) {
+        warn!("pipe_run empty stage");
+        return Err(ReplRouterError::PipeEmptyStage.into());
+    }
+    let count = stages.len();
+    let mut set: JoinSet<(usize, String, Result<Option<i32>>)> = JoinSet::new();
+    let mut upstream: Option<mpsc::Receiver<String>> = None;
+
+    for (idx, stage) in stages.into_iter().enumerate() {
+        let (output, next) = if idx + 1 < count {
+            let (tx, rx) = mpsc::channel::<String>(PIPE_CHANNEL_CAP);
+            (Some(tx), Some(rx))
+        } else {
+            (None, None)
+        };
+        let input = upstream.take();
+        upstream = next;
+        let label = stage.endpoint.label().to_string();
+        match stage.endpoint {
+            Endpoint::Session { name, shell } => {
+                set.spawn(async move {
+                    (
+                        idx,
+                        label,
+                        run_session_stage(name, shell, stage.command, input, output)
+                            .await,
+                    )
+                });
+            }
+            Endpoint::Local => {
+                set.spawn(async move {
+                    (
+                        idx,
+                        label,
+                        run_local_stage(stage.command, input, output).await,
+                    )
+                });
+            }
+        }
+    }
+
+    let mut first_err = None;
+    let mut statuses: Vec<Option<i32>> = vec![None; count];
+    while let Some(joined) = set.join_next().await {
+        match joined {
+            Ok((idx, label, Ok(status))) => {
+                info!(stage = %label, ?status, "pipe_run stage done");
+                statuses[idx] = status;
+            }
+            Ok((_, label, Err(e))) => {
+                warn!(stage = %label, ?e, "pipe_run stage failed");
+                first_err.get_or_insert(e);
+            }
+            Err(e) => {
+                error!(?e, "pipe_run join failed");
+                first_err.get_or_insert(ShellError::from(SyncError::Join(e)).into());
+            }
+        }
+    }
+    match first_err {
+        Some(e) => Err(e),
+        None => {
+            info!("pipe_run ok");
+            Ok(statuses)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base64_round_trips() {
+        for input in [&b""[..], b"a", b"ab", b"abc", b"\x00\xff\x10 line\n"] {
+            let encoded = base64_encode(input);
+            assert_eq!(base64_decode(&encoded).as_deref(), Ok(input), "{encoded}");
+        }
+        assert_eq!(base64_encode(b"ab"), "YWI=");
+    }
+
+    #[test]
+    fn base64_rejects_invalid_input() {
+        assert!(base64_decode("YW*=").is_err());
+        assert!(base64_decode("YWI").is_err());
+        assert!(base64_decode("Y===").is_err());
+        assert!(base64_decode("YW=I").is_err());
+        assert!(base64_decode("$ YWI=").is_err());
+    }
+}
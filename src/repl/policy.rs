@@ -0,0 +1,284 @@
+use std::collections::BTreeSet;
+
+use regex::Regex;
+use tracing::{debug, info, warn};
+
+use crate::{
+    error::{Result, RuntimeError},
+    registry::{self, Registry},
+    repl::parser::Parsed,
+    runtime::config::PolicySection,
+};
+
+const DEFAULT_RULE_NAME: &str = "destructive-on-prod";
+const DEFAULT_RULE_TAG: &str = "prod";
+const DEFAULT_RULE_PATTERNS: &[&str] = &[
+    r"\brm\s+-[a-zA-Z]*(?:rf|fr)\b",
+    r"\b(?:shutdown|reboot|halt|poweroff)\b",
+    r"(?i)\bdrop\s+(?:table|database)\b",
+];
+
+#[derive(Debug, Clone)]
+pub struct PolicyRule {
+    pub name: String,
+    pub tags: Vec<String>,
+    patterns: Vec<Regex>,
+}
+
+impl PolicyRule {
+    pub fn new(name: &str, tags: Vec<String>, patterns: &[String]) -> Result<Self> {
+        let patterns = patterns
+            .iter()
+            .map(|p| {
+                Regex::new(p).map_err(|source| RuntimeError::PolicyPattern {
+                    rule: name.to_string(),
+                    pattern: p.clone(),
+                    source,
+                })
+            })
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(Self {
+            name: name.to_string(),
+            tags,
+            patterns,
+        })
+    }
+
+    fn applies_to(&self, tags: &[String]) -> bool {
+        self.tags.is_empty() || self.tags.iter().any(|t| tags.contains(t))
+    }
+
+    fn matching(&self, command: &str) -> Option<&Regex> {
+        self.patterns.iter().find(|re| re.is_match(command))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verdict {
+    Allow,
+    Confirm {
+        target: String,
+        expected: String,
+        reason: String,
+    },
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Policy {
+    rules: Vec<PolicyRule>,
+    max_fanout: Option<usize>,
+}
+
+impl Policy {
+    pub fn new(rules: Vec<PolicyRule>, max_fanout: Option<usize>) -> Self {
+        Self { rules, max_fanout }
+    }
+
+    pub fn from_config(section: Option<&PolicySection>) -> Result<Self> {
+        debug!(present = section.is_some(), "policy_from_config start");
+        let rules = match section.and_then(|s| s.rules.as_ref()) {
+            Some(rules) => rules
+                .iter()
+                .map(|r| PolicyRule::new(&r.name, r.tags.clone(), &r.patterns))
+                .collect::<Result<Vec<_>>>()?,
+            None => vec![PolicyRule::new(
+                DEFAULT_RULE_NAME,
+                vec![DEFAULT_RULE_TAG.to_string()],
+                &DEFAULT_RULE_PATTERNS
+                    .iter()
+                    .map(|p| p.to_string())
+                    .collect::<Vec<_>>(),
+            )?],
+        };
+        let policy = Self::new(rules, section.and_then(|s| s.max_fanout));
+        info!(
+            rules = policy.rules.len(),
+            max_fanout = ?policy.max_fanout,
+            "policy_from_config ok"
+        );
+        Ok(policy)
+    }
+
+    pub fn rules(&self) -> &[PolicyRule] {
+        &self.rules
+    }
+
+    pub fn max_fanout(&self) -> Option<usize> {
+        self.max_fanout
+    }
+
+    pub fn evaluate(
+        &self,
+        registry: &Registry,
+        parsed: &Parsed,
+        current: Option<&str>,
+    ) -> Verdict {
+        debug!("policy_evaluate start");
+        let (name, command) = match parsed {
+            Parsed::Entry { name, command, .. } => (name.as_str(), command.as_str()),
+            Parsed::Default { command } => match current {
+                Some(name) => (name, command.as_str()),
+                None => return Verdict::Allow,
+            },
+        };
+        let spec = match parsed {
+            Parsed::Entry {
+                entry: registry::Entry::Shell(spec),
+                ..
+            } => Some(spec.clone()),
+            Parsed::Entry { .. } => None,
+            Parsed::Default { .. } => registry.get_shell_spec(name),
+        };
+        let Some(spec) = spec else {
+            return Verdict::Allow;
+        };
+        for rule in self.rules.iter().filter(|r| r.applies_to(spec.tags())) {
+            if let Some(re) = rule.matching(command) {
+                warn!(name = name, rule = %rule.name, "policy_evaluate confirm");
+                return Verdict::Confirm {
+                    target: name.to_string(),
+                    expected: spec.host().unwrap_or(name).to_string(),
+                    reason: format!("`{command}` matches rule {} ({re})", rule.name),
+                };
+            }
+        }
+        info!(name = name, "policy_evaluate allow");
+        Verdict::Allow
+    }
+
+    pub fn evaluate_fanout(&self, targets: &BTreeSet<String>) -> Verdict {
+        match self.max_fanout {
+            Some(max) if targets.len() > max => {
+                warn!(count = targets.len(), max, "policy_fanout confirm");
+                let names: Vec<&str> = targets.iter().map(String::as_str).collect();
+                Verdict::Confirm {
+                    target: names.join(", "),
+                    expected: targets.len().to_string(),
+                    reason: format!(
+                        "this reaches {} shells ({}), more than the limit of {max}",
+                        targets.len(),
+                        names.join(", ")
+                    ),
+                }
+            }
+            _ => Verdict::Allow,
+        }
+    }
+}
+
+pub trait Confirmer: Send + Sync {
+    fn confirm(&self, reason: &str, expected: &str) -> Result<bool>;
+}
+
+pub struct DenyConfirmer;
+
+impl Confirmer for DenyConfirmer {
+    fn confirm(&self, reason: &str, _expected: &str) -> Result<bool> {
+        warn!(reason = reason, "deny_confirm declined");
+        Ok(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{repl::parser, shell::ShellSpec};
+
+    fn local(tags: &[&str]) -> registry::Entry {
+        registry::Entry::Shell(ShellSpec::Local {
+            program: "sh".to_string(),
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            readonly: false,
+            on_connect: None,
+        })
+    }
+
+    fn registry() -> Registry {
+        let mut r = Registry::new();
+        r.register_entry("db", local(&["prod"]));
+        r.register_entry("dev", local(&["staging"]));
+        r
+    }
+
+    fn targets(names: &[&str]) -> BTreeSet<String> {
+        names.iter().map(|n| n.to_string()).collect()
+    }
+
+    #[test]
+    fn confirms_matching_commands_on_tagged_shells() {
+        let policy = Policy::from_config(None).expect("default policy");
+        let r = registry();
+
+        let verdict = policy.evaluate(&r, &parser::parse(&r, "db: rm -rf /srv"), None);
+        let Verdict::Confirm {
+            target,
+            expected,
+            reason,
+        } = verdict
+        else {
+            panic!("expected confirm, got {verdict:?}");
+        };
+        assert_eq!((target.as_str(), expected.as_str()), ("db", "db"));
+        assert!(reason.contains(DEFAULT_RULE_NAME), "{reason}");
+
+        let verdict =
+            policy.evaluate(&r, &parser::parse(&r, "sudo reboot"), Some("db"));
+        assert!(matches!(verdict, Verdict::Confirm { .. }), "{verdict:?}");
+        let verdict = policy.evaluate(&r, &parser::parse(&r, "db: ls -la"), None);
+        assert_eq!(verdict, Verdict::Allow);
+    }
+
+    #[test]
+    fn allows_matching_commands_on_other_tags() {
+        let policy = Policy::from_config(None).expect("default policy");
+        let r = registry();
+
+        let verdict = policy.evaluate(&r, &parser::parse(&r, "dev: rm -rf /srv"), None);
+        assert_eq!(verdict, Verdict::Allow);
+        let verdict = policy.evaluate(&r, &parser::parse(&r, "reboot"), None);
+        assert_eq!(verdict, Verdict::Allow);
+    }
+
+    #[test]
+    fn untagged_rules_apply_everywhere() {
+        let rule =
+            PolicyRule::new("all", Vec::new(), &["^halt$".to_string()]).expect("rule");
+        let policy = Policy::new(vec![rule], None);
+        let r = registry();
+
+        let verdict = policy.evaluate(&r, &parser::parse(&r, "dev: halt"), None);
+        assert!(matches!(verdict, Verdict::Confirm { .. }), "{verdict:?}");
+    }
+
+    #[test]
+    fn rejects_invalid_patterns() {
+        let err =
+            PolicyRule::new("bad", Vec::new(), &["(".to_string()]).expect_err("bad");
+        assert!(err.to_string().contains("bad"), "{err}");
+    }
+
+    #[test]
+    fn confirms_fanout_above_the_limit() {
+        let policy = Policy::new(Vec::new(), Some(2));
+
+        assert_eq!(
+            policy.evaluate_fanout(&targets(&["a", "b"])),
+            Verdict::Allow
+        );
+        let verdict = policy.evaluate_fanout(&targets(&["a", "b", "c"]));
+        assert_eq!(
+            verdict,
+            Verdict::Confirm {
+                target: "a, b, c".to_string(),
+                expected: "3".to_string(),
+                reason: "this reaches 3 shells (a, b, c), more than the limit of 2"
+                    .to_string(),
+            }
+        );
+        let unlimited = Policy::new(Vec::new(), None);
+        assert_eq!(
+            unlimited.evaluate_fanout(&targets(&["a", "b", "c"])),
+            Verdict::Allow
+        );
+    }
+}
@@ -1,40 +1,113 @@
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeSet, HashMap, HashSet, VecDeque},
+    future::Future,
+    path::PathBuf,
+    pin::Pin,
     sync::Arc,
+    time::{Duration, Instant},
 };
 
+use anyhow::anyhow;
 use async_trait::async_trait;
-use tokio::sync::Mutex;
+use tokio::{
+    signal::unix::{SignalKind, signal},
+    sync::{
+        Mutex,
+        broadcast::{self, error::RecvError},
+    },
+    time,
+};
 use tracing::{debug, error, info, warn};
 
 use crate::{
-    builtins::{self, BuiltinContext},
-    error::{ReplRouterError, Result},
+    builtins::{self, Builtin, BuiltinContext, BuiltinSet, args},
+    error::{ExpectError, ReplRouterError, Result, ShellError},
     registry::{self, Registry},
     repl::{
-        ModeState,
+        AliasTable, LockState, ModeState, PaneSet, Policy, Secrets, SessionStatus,
+        ShareMode, TermSize, VarTable, ViewerSet, alias,
+        panes::ViewRequest,
         parser::{self, Parsed},
+        pipe,
+        policy::{Confirmer, Verdict},
+        scripts::{ScriptJobs, SessionGate},
+        secrets::{SecretPrompter, SecretSource},
+        vars,
+        viewers::{Joined, Viewer},
+    },
+    runtime::{AuditLog, RecordingSettings, ReplSettings, config},
+    shell::{
+        PtyFactory, Secret, Shell, ShellEvent, ShellFactory, ShellSpec,
+        expect::Script,
+        factory, integration,
+        vt::{PromptMark, TermEvent},
+    },
+    ui::{
+        self, confirm::StdinConfirmer, editor::completer::CompletionEntry,
+        secret::TerminalSecretPrompter,
     },
-    shell::{PtyShell, Shell, ShellEvent, ShellSpec, factory},
 };
 
+pub type SharedRouter = Arc<Mutex<Router>>;
+
+const MAX_ALIAS_DEPTH: usize = 16;
+const MAX_SUBSTITUTION_DEPTH: usize = 8;
+const MAX_SECRET_ATTEMPTS: usize = 3;
+const SECRET_SETTLE: Duration = Duration::from_secs(1);
+const SECRET_TAIL_CAP: usize = 1024;
+const KILL_LINE: u8 = 0x15;
+const CTRL_C: u8 = 0x03;
+const CTRL_D: u8 = 0x04;
+
 pub struct Router {
     registry: Registry,
+    builtins: BuiltinSet,
+    aliases: AliasTable,
+    vars: VarTable,
     mode: ModeState,
-    sessions: Arc<Mutex<HashMap<String, Arc<PtyShell>>>>,
-    cols: u16,
-    rows: u16,
+    locks: LockState,
+    status: SessionStatus,
+    scripts: ScriptJobs,
+    panes: PaneSet,
+    viewers: ViewerSet,
+    sessions: Arc<Mutex<HashMap<String, Arc<dyn Shell>>>>,
+    settings: Option<ReplSettings>,
+    recording: RecordingSettings,
+    audit: Option<AuditLog>,
+    policy: Policy,
+    confirmer: Arc<dyn Confirmer>,
+    secrets: Secrets,
+    secret_prompter: Arc<dyn SecretPrompter>,
+    factory: Arc<dyn ShellFactory>,
+    size: TermSize,
+    daemon_socket: Option<PathBuf>,
 }
 
 impl Router {
-    pub fn new(registry: Registry, cols: u16, rows: u16) -> Self {
+    pub fn new(registry: Registry, builtins: BuiltinSet, cols: u16, rows: u16) -> Self {
         debug!(cols, rows, "router_new start");
         let s = Self {
             registry,
+            builtins,
+            aliases: AliasTable::new(),
+            vars: VarTable::new(),
             mode: ModeState::default(),
+            locks: LockState::new(),
+            status: SessionStatus::new(),
+            scripts: ScriptJobs::new(),
+            panes: PaneSet::new(),
+            viewers: ViewerSet::new(),
             sessions: Arc::new(Mutex::new(HashMap::new())),
-            cols,
-            rows,
+            settings: None,
+            recording: RecordingSettings::default(),
+            audit: None,
+            policy: Policy::default(),
+            confirmer: Arc::new(StdinConfirmer),
+            secrets: Secrets::default(),
+            secret_prompter: Arc::new(TerminalSecretPrompter),
+            factory: Arc::new(PtyFactory),
+            size: TermSize::new(cols, rows),
+            daemon_socket: None,
         };
         info!("router_new ok");
         s
@@ -61,6 +134,426 @@ impl Router {
         m
     }
 
+    pub fn session_status(&self) -> SessionStatus {
+        self.status.clone()
+    }
+
+    pub fn term_size(&self) -> (u16, u16) {
+        self.size.get()
+    }
+
+    pub fn set_daemon_socket(&mut self, path: Option<PathBuf>) {
+        debug!(detachable = path.is_some(), "router_set_daemon_socket");
+        self.daemon_socket = path;
+    }
+
+    pub fn daemon_socket(&self) -> Option<PathBuf> {
+        self.daemon_socket.clone()
+    }
+
+    pub fn term_size_state(&self) -> TermSize {
+        self.size.clone()
+    }
+
+    pub async fn set_term_size(&self, cols: u16, rows: u16) {
+        debug!(cols, rows, "router_set_term_size start");
+        if !self.size.set(cols, rows) {
+            return;
+        }
+        let resize_all = self.settings.as_ref().is_some_and(|s| s.resize_all);
+        let focused = self.mode.get_current();
+        propagate_size(
+            &self.sessions,
+            &self.panes,
+            (cols, rows),
+            resize_all,
+            focused,
+        )
+        .await;
+        info!(cols, rows, "router_set_term_size ok");
+    }
+
+    pub fn watch_term_size(&self) {
+        debug!("router_watch_term_size start");
+        let size = self.size.clone();
+        let sessions = self.sessions.clone();
+        let panes = self.panes.clone();
+        let mode = self.mode.clone();
+        let resize_all = self.settings.as_ref().is_some_and(|s| s.resize_all);
+        tokio::spawn(async move {
+            let mut winch = match signal(SignalKind::window_change()) {
+                Ok(s) => s,
+                Err(e) => {
+                    warn!(?e, "term_size_watch signal install failed");
+                    return;
+                }
+            };
+            while winch.recv().await.is_some() {
+                let (cols, rows) = match ui::query_term_size() {
+                    Ok(Some(s)) => s,
+                    Ok(None) => continue,
+                    Err(e) => {
+                        warn!(?e, "term_size_watch query failed");
+                        continue;
+                    }
+                };
+                if !size.set(cols, rows) {
+                    continue;
+                }
+                info!(cols, rows, "term_size_watch changed");
+                let focused = mode.get_current();
+                propagate_size(&sessions, &panes, (cols, rows), resize_all, focused)
+                    .await;
+            }
+        });
+        info!("router_watch_term_size ok");
+    }
+
+    async fn sync_session_size(&self, name: &str, session: &dyn Shell) {
+        if self.panes.is_active() {
+            return;
+        }
+        let (cols, rows) = self.size.get();
+        if session.size() == (cols, rows) {
+            return;
+        }
+        if let Err(e) = session.resize(cols, rows).await {
+            warn!(name = name, ?e, "sync_session_size failed");
+        }
+    }
+
+    pub fn pane_state(&self) -> PaneSet {
+        self.panes.clone()
+    }
+
+    pub async fn add_pane(&mut self, name: &str) -> Result<bool> {
+        debug!(name = name, "router_add_pane start");
+        if self.registry.get_shell_spec(name).is_none() {
+            warn!(name = name, "router_add_pane not a shell");
+            return Err(ReplRouterError::UnknownShell {
+                name: name.to_string(),
+            }
+            .into());
+        }
+        self.ensure_shell_session_by_name(name).await?;
+        let added = self.panes.add(name);
+        info!(name = name, added = added, "router_add_pane ok");
+        Ok(added)
+    }
+
+    pub fn remove_pane(&mut self, name: &str) -> bool {
+        debug!(name = name, "router_remove_pane start");
+        let removed = self.panes.remove(name);
+        info!(name = name, removed = removed, "router_remove_pane ok");
+        removed
+    }
+
+    pub fn focus_pane(&mut self, name: &str) -> Result<()> {
+        debug!(name = name, "router_focus_pane start");
+        if !self.panes.focus(name) {
+            warn!(name = name, "router_focus_pane unknown");
+            return Err(ReplRouterError::PaneUnknown {
+                name: name.to_string(),
+            }
+            .into());
+        }
+        info!(name = name, "router_focus_pane ok");
+        Ok(())
+    }
+
+    pub fn request_view(&mut self, request: ViewRequest) {
+        self.panes.request(request);
+    }
+
+    pub fn session_cwd(&self, name: &str) -> Option<String> {
+        self.status.cwd(name)
+    }
+
+    pub async fn install_prompt_hook(&mut self, name: &str) -> Result<()> {
+        debug!(name = name, "router_install_prompt_hook start");
+        self.ensure_writable(name)?;
+        let s = self.ensure_shell_session_by_name(name).await?;
+        let hook = match self.registry.get_shell_spec(name) {
+            Some(spec) => integration::prompt_hook(&spec),
+            None => integration::PROMPT_HOOK.to_string(),
+        };
+        self.status.sent_internal(name);
+        s.send_line(hook).await?;
+        info!(name = name, "router_install_prompt_hook ok");
+        Ok(())
+    }
+
+    pub async fn cd_sync(&mut self, from: &str, to: &str) -> Result<String> {
+        debug!(from = from, to = to, "router_cd_sync start");
+        if self.registry.get_shell_spec(to).is_none() {
+            warn!(to = to, "router_cd_sync unknown target");
+            return Err(ReplRouterError::UnknownShell {
+                name: to.to_string(),
+            }
+            .into());
+        }
+        let Some(path) = self.status.cwd(from) else {
+            warn!(from = from, "router_cd_sync cwd unknown");
+            return Err(ReplRouterError::CwdUnknown {
+                name: from.to_string(),
+            }
+            .into());
+        };
+        self.exec_by_prefix(to, &integration::cd_command(&path))
+            .await?;
+        info!(from = from, to = to, path = %path, "router_cd_sync ok");
+        Ok(path)
+    }
+
+    pub fn lock_state(&self) -> LockState {
+        self.locks.clone()
+    }
+
+    pub fn lock_session(&mut self, name: &str) -> Result<()> {
+        debug!(name = name, "router_lock_session start");
+        if self.registry.get_shell_spec(name).is_none() {
+            warn!(name = name, "router_lock_session unknown");
+            return Err(ReplRouterError::UnknownShell {
+                name: name.to_string(),
+            }
+            .into());
+        }
+        self.locks.lock(name);
+        info!(name = name, "router_lock_session ok");
+        Ok(())
+    }
+
+    pub fn unlock_session(&mut self, name: &str) -> Result<bool> {
+        debug!(name = name, "router_unlock_session start");
+        if self
+            .registry
+            .get_shell_spec(name)
+            .is_some_and(|s| s.is_readonly())
+        {
+            warn!(name = name, "router_unlock_session readonly spec");
+            return Err(ReplRouterError::SessionReadonly {
+                name: name.to_string(),
+            }
+            .into());
+        }
+        let unlocked = self.locks.unlock(name);
+        info!(name = name, unlocked = unlocked, "router_unlock_session ok");
+        Ok(unlocked)
+    }
+
+    pub fn is_locked(&self, name: &str) -> bool {
+        self.locks.is_locked(name)
+            || self
+                .registry
+                .get_shell_spec(name)
+                .is_some_and(|s| s.is_readonly())
+    }
+
+    fn ensure_writable(&self, name: &str) -> Result<()> {
+        if self
+            .registry
+            .get_shell_spec(name)
+            .is_some_and(|s| s.is_readonly())
+        {
+            warn!(name = name, "ensure_writable readonly");
+            return Err(ReplRouterError::SessionReadonly {
+                name: name.to_string(),
+            }
+            .into());
+        }
+        if self.locks.is_locked(name) {
+            warn!(name = name, "ensure_writable locked");
+            return Err(ReplRouterError::SessionLocked {
+                name: name.to_string(),
+            }
+            .into());
+        }
+        Ok(())
+    }
+
+    pub async fn send_line(&mut self, name: &str, line: &str) -> Result<()> {
+        debug!(name = name, "router_send_line start");
+        if self.registry.get_shell_spec(name).is_none() {
+            warn!(name = name, "router_send_line unknown");
+            return Err(ReplRouterError::UnknownShell {
+                name: name.to_string(),
+            }
+            .into());
+        }
+        self.exec_by_prefix(name, line).await?;
+        info!(name = name, "router_send_line ok");
+        Ok(())
+    }
+
+    pub async fn subscribe(
+        &mut self,
+        name: &str,
+    ) -> Result<broadcast::Receiver<ShellEvent>> {
+        debug!(name = name, "router_subscribe start");
+        let session = self.ensure_shell_session_by_name(name).await?;
+        info!(name = name, "router_subscribe ok");
+        Ok(session.subscribe())
+    }
+
+    pub async fn send_bytes(&mut self, name: &str, bytes: Vec<u8>) -> Result<()> {
+        debug!(name = name, size = bytes.len(), "router_send_bytes start");
+        self.ensure_writable(name)?;
+        let s = self.ensure_shell_session_by_name(name).await?;
+        s.send_bytes(bytes).await?;
+        info!(name = name, "router_send_bytes ok");
+        Ok(())
+    }
+
+    pub fn viewer_state(&self) -> ViewerSet {
+        self.viewers.clone()
+    }
+
+    pub fn share_session(&mut self, name: &str, mode: ShareMode) -> Result<()> {
+        debug!(name = name, %mode, "router_share_session start");
+        if self.registry.get_shell_spec(name).is_none() {
+            warn!(name = name, "router_share_session unknown");
+            return Err(ReplRouterError::UnknownShell {
+                name: name.to_string(),
+            }
+            .into());
+        }
+        self.viewers.share(name, mode);
+        info!(name = name, %mode, "router_share_session ok");
+        Ok(())
+    }
+
+    pub fn unshare_session(&mut self, name: &str) -> usize {
+        debug!(name = name, "router_unshare_session start");
+        let kicked = self.viewers.unshare(name);
+        info!(name = name, kicked = kicked, "router_unshare_session ok");
+        kicked
+    }
+
+    pub fn share_mode(&self, name: &str) -> Option<ShareMode> {
+        self.viewers.share_mode(name)
+    }
+
+    pub fn list_viewers(&self, name: &str) -> Vec<Viewer> {
+        self.viewers.viewers(name)
+    }
+
+    pub async fn join_session(
+        &mut self,
+        name: &str,
+        mode: ShareMode,
+    ) -> Result<Joined> {
+        debug!(name = name, %mode, "router_join_session start");
+        let Some(shared) = self.viewers.share_mode(name) else {
+            warn!(name = name, "router_join_session not_shared");
+            return Err(ReplRouterError::NotShared {
+                name: name.to_string(),
+            }
+            .into());
+        };
+        if mode > shared {
+            warn!(name = name, %mode, %shared, "router_join_session mode_denied");
+            return Err(ReplRouterError::SharedReadOnly {
+                name: name.to_string(),
+            }
+            .into());
+        }
+        let session = self.ensure_shell_session_by_name(name).await?;
+        let events = session.subscribe();
+        let screen = session.render_screen()?;
+        let (id, kicked) = self.viewers.join(name, mode).ok_or_else(|| {
+            ReplRouterError::NotShared {
+                name: name.to_string(),
+            }
+        })?;
+        info!(name = name, id = id, %mode, "router_join_session ok");
+        Ok(Joined {
+            id,
+            mode,
+            screen,
+            events,
+            kicked,
+        })
+    }
+
+    pub fn leave_session(&mut self, id: u64) -> bool {
+        self.viewers.leave(id)
+    }
+
+    pub async fn viewer_input(&mut self, id: u64, bytes: Vec<u8>) -> Result<()> {
+        debug!(id = id, size = bytes.len(), "router_viewer_input start");
+        let Some(viewer) = self.viewers.get(id) else {
+            warn!(id = id, "router_viewer_input unknown");
+            return Err(ReplRouterError::ViewerUnknown { id }.into());
+        };
+        let allowed = viewer.mode == ShareMode::Write
+            && self.viewers.share_mode(&viewer.session) == Some(ShareMode::Write);
+        if !allowed {
+            warn!(id = id, name = %viewer.session, "router_viewer_input read_only");
+            return Err(ReplRouterError::ViewerReadOnly {
+                id,
+                name: viewer.session,
+            }
+            .into());
+        }
+        self.ensure_writable(&viewer.session)?;
+        let session = self.running_session(&viewer.session).await?;
+        let name = viewer.session;
+        let mut chunk = Vec::new();
+        for byte in bytes {
+            let hide = self.status.is_prompting(&name);
+            let Some(typed) = self.viewers.feed(id, byte, hide) else {
+                if !hide || matches!(byte, CTRL_C | CTRL_D) {
+                    chunk.push(byte);
+                }
+                continue;
+            };
+            if typed.hidden && self.answers_prompt(&name, session.as_ref(), &typed.text)
+            {
+                if !chunk.is_empty() {
+                    session.send_bytes(std::mem::take(&mut chunk)).await?;
+                }
+                session.send_secret(&Secret::new(typed.text)).await?;
+                info!(id = id, name = %name, "router_viewer_input answered prompt");
+                continue;
+            }
+            if typed.hidden {
+                chunk.extend_from_slice(typed.text.as_bytes());
+            }
+            let shown = typed
+                .edited
+                .then(|| cursor_line(session.as_ref()))
+                .flatten();
+            let checked = self
+                .guard(&name, &typed.text)
+                .and_then(|()| shown.map_or(Ok(()), |line| self.guard(&name, &line)));
+            if let Err(e) = checked {
+                warn!(id = id, name = %name, "router_viewer_input declined");
+                chunk.push(KILL_LINE);
+                session.send_bytes(chunk).await?;
+                return Err(e);
+            }
+            if !typed.text.trim().is_empty() {
+                self.audit_sent(&name, &typed.text);
+            }
+            chunk.push(byte);
+        }
+        if !chunk.is_empty() {
+            session.send_bytes(chunk).await?;
+        }
+        info!(id = id, name = %name, "router_viewer_input ok");
+        Ok(())
+    }
+
+    pub fn set_repl_settings(&mut self, settings: ReplSettings) {
+        debug!("router_set_repl_settings start");
+        self.settings = Some(settings);
+        info!("router_set_repl_settings ok");
+    }
+
+    pub fn repl_settings(&self) -> Option<ReplSettings> {
+        self.settings.clone()
+    }
+
     pub fn get_current_mode(&self) -> Option<String> {
         debug!("get_current_mode_name start");
         let r = self.mode.get_current();
@@ -105,16 +598,26 @@ impl Router {
         debug!(name = name, "exec_by_prefix start");
         match self.registry.get_entry(name) {
             Some(registry::Entry::Shell(spec)) => {
+                self.ensure_writable(name)?;
                 let s = self.ensure_shell_session_by_spec(name, &spec).await?;
-                s.send_line(command.to_string()).await?;
+                self.sync_session_size(name, s.as_ref()).await;
+                if self.answers_prompt(name, s.as_ref(), command) {
+                    s.send_secret(&Secret::new(command)).await?;
+                    info!(name = name, "exec_by_prefix answered password prompt");
+                    return Ok(());
+                }
+                let command = self.substitute(command).await?;
+                self.guard(name, &command)?;
+                self.audit_sent(name, &command);
+                match self.scripts.hold(name, command) {
+                    Some(command) => s.send_line(command).await?,
+                    None => info!(name = name, "exec_by_prefix held for script"),
+                }
                 info!(name = name, "exec_by_prefix shell ok");
             }
-            Some(registry::Entry::Builtin) => match name {
-                "local" => builtins::local::handle(self, command).await?,
-                "remote" => builtins::remote::handle(self, command).await?,
-                "admin" => builtins::admin::handle(self, command).await?,
-                "quit" | "exit" => builtins::quit::handle(self, command).await?,
-                other => warn!(builtin = other, "exec_by_prefix builtin unknown,"),
+            Some(registry::Entry::Builtin) => match self.builtins.get(name) {
+                Some(builtin) => builtin.run(self, command).await?,
+                None => warn!(builtin = name, "exec_by_prefix builtin unknown"),
             },
             None => {
                 warn!(name = name, "exec_by_prefix unknown");
@@ -128,8 +631,54 @@ impl Router {
         Ok(())
     }
 
+    pub fn expand_aliases(&self, input: &str) -> Result<Vec<String>> {
+        debug!(input = input, "expand_aliases start");
+        let mut pending: VecDeque<(String, Vec<String>)> =
+            VecDeque::from([(input.to_string(), Vec::new())]);
+        let mut out: Vec<String> = Vec::new();
+        while let Some((line, chain)) = pending.pop_front() {
+            let Some((name, rest)) = self.aliases.lookup(&line) else {
+                out.push(line);
+                continue;
+            };
+            // `alias: set ls "ls --color"` runs the real ls, as in a shell.
+            if chain.last() == Some(&name) {
+                out.push(line);
+                continue;
+            }
+            if chain.len() >= MAX_ALIAS_DEPTH || chain.contains(&name) {
+                warn!(name = %name, "expand_aliases too deep");
+                return Err(ReplRouterError::AliasDepthExceeded { name }.into());
+            }
+            let params = args::split(&builtins::alias::USAGE, rest)?;
+            let body = self.aliases.get(&name).unwrap_or_default();
+            let lines = alias::expand(&name, &body, &params)?;
+            let mut chain = chain;
+            chain.push(name);
+            for l in lines.into_iter().rev() {
+                pending.push_front((l, chain.clone()));
+            }
+        }
+        info!(lines = out.len(), "expand_aliases ok");
+        Ok(out)
+    }
+
     pub async fn exec(&mut self, input: &str) -> Result<()> {
         debug!(input = input, "router_exec start");
+        let lines = self.expand_aliases(input)?;
+        self.guard_fanout(&lines)?;
+        for line in lines {
+            match parser::parse_pipeline(&self.registry, &line) {
+                Some(stages) => self.exec_pipeline(stages).await?,
+                None => self.exec_line(&line).await?,
+            }
+        }
+        info!("router_exec ok");
+        Ok(())
+    }
+
+    async fn exec_line(&mut self, input: &str) -> Result<()> {
+        debug!(input = input, "router_exec_line start");
         match parser::parse(&self.registry, input) {
             Parsed::Entry { name, command, .. } => {
                 self.set_current_mode(&name);
@@ -147,10 +696,140 @@ impl Router {
                 }
             }
         }
-        info!("router_exec ok");
+        info!("router_exec_line ok");
         Ok(())
     }
 
+    async fn resolve_endpoint(&mut self, name: String) -> Result<pipe::Endpoint> {
+        debug!(name = %name, "resolve_endpoint start");
+        let endpoint = match self.registry.get_entry(&name) {
+            Some(registry::Entry::Shell(spec)) => pipe::Endpoint::Session {
+                shell: self.ensure_shell_session_by_spec(&name, &spec).await?,
+                name,
+            },
+            Some(registry::Entry::Builtin) if name == builtins::local::USAGE.name => {
+                pipe::Endpoint::Local
+            }
+            Some(registry::Entry::Builtin) => {
+                warn!(name = %name, "resolve_endpoint builtin");
+                return Err(ReplRouterError::PipeTarget { name }.into());
+            }
+            None => {
+                warn!(name = %name, "resolve_endpoint unknown");
+                return Err(ReplRouterError::UnknownShell { name }.into());
+            }
+        };
+        info!(name = endpoint.label(), "resolve_endpoint ok");
+        Ok(endpoint)
+    }
+
+    async fn exec_pipeline(&mut self, stages: Vec<Parsed>) -> Result<()> {
+        debug!(stages = stages.len(), "router_exec_pipeline start");
+        let mut resolved: Vec<pipe::Stage> = Vec::with_capacity(stages.len());
+        let mut audited: Vec<(String, String)> = Vec::with_capacity(stages.len());
+        for stage in stages {
+            let (name, command) = match stage {
+                Parsed::Entry { name, command, .. } => (name, command),
+                Parsed::Default { command } => {
+                    let target =
+                        self.mode.get_current().or_else(|| self.mode.get_default());
+                    let Some(name) = target else {
+                        warn!("router_exec_pipeline no_current_or_default");
+                        return Err(ReplRouterError::DefaultShellUnset.into());
+                    };
+                    (name, command)
+                }
+            };
+            if command.trim().is_empty() {
+                warn!(name = %name, "router_exec_pipeline empty stage");
+                return Err(ReplRouterError::PipeEmptyStage.into());
+            }
+            self.ensure_writable(&name)?;
+            let command = self.substitute(&command).await?;
+            self.guard(&name, &command)?;
+            if self.registry.get_shell_spec(&name).is_some() {
+                self.status.sent_internal(&name);
+            }
+            audited.push((name.clone(), command.clone()));
+            let endpoint = self.resolve_endpoint(name).await?;
+            resolved.push(pipe::Stage { endpoint, command });
+        }
+        let started = Instant::now();
+        let result = pipe::run(resolved).await;
+        let elapsed = started.elapsed();
+        let statuses = result.as_deref().unwrap_or_default();
+        for (idx, (name, command)) in audited.iter().enumerate() {
+            let status = statuses.get(idx).copied().flatten();
+            self.audit(name, command, status);
+            if self.registry.get_shell_spec(name).is_some() {
+                self.status.record(name, status, elapsed);
+            }
+        }
+        result?;
+        info!("router_exec_pipeline ok");
+        Ok(())
+    }
+
+    pub async fn substitute(&mut self, input: &str) -> Result<String> {
+        debug!(input = input, "router_substitute start");
+        let out = self.substitute_at(input.to_string(), 0).await?;
+        info!("router_substitute ok");
+        Ok(out)
+    }
+
+    fn substitute_at(
+        &mut self,
+        input: String,
+        depth: usize,
+    ) -> Pin<Box<dyn Future<Output = Result<String>> + Send + '_>> {
+        Box::pin(async move {
+            let segments = vars::scan(&input, &self.vars, |name| {
+                name == builtins::local::USAGE.name
+                    || self.registry.get_shell_spec(name).is_some()
+            });
+            let mut out = String::with_capacity(input.len());
+            for segment in segments {
+                match segment {
+                    vars::Segment::Text(text) => out.push_str(&text),
+                    vars::Segment::Capture { target, command } => {
+                        if command.is_empty() {
+                            warn!(name = %target, "router_substitute empty");
+                            return Err(ReplRouterError::SubstitutionEmpty {
+                                name: target,
+                            }
+                            .into());
+                        }
+                        if depth >= MAX_SUBSTITUTION_DEPTH {
+                            warn!(name = %target, "router_substitute too deep");
+                            return Err(ReplRouterError::SubstitutionDepthExceeded {
+                                name: target,
+                            }
+                            .into());
+                        }
+                        self.ensure_writable(&target)?;
+                        let command = self.substitute_at(command, depth + 1).await?;
+                        self.guard(&target, &command)?;
+                        let endpoint = self.resolve_endpoint(target.clone()).await?;
+                        let session = self.registry.get_shell_spec(&target).is_some();
+                        if session {
+                            self.status.sent_internal(&target);
+                        }
+                        let started = Instant::now();
+                        let result = pipe::capture(endpoint, command.clone()).await;
+                        let status =
+                            result.as_ref().ok().and_then(|(_, status)| *status);
+                        self.audit(&target, &command, status);
+                        if session {
+                            self.status.record(&target, status, started.elapsed());
+                        }
+                        out.push_str(&result?.0);
+                    }
+                }
+            }
+            Ok(out)
+        })
+    }
+
     pub async fn add_and_start_shell(
         &mut self,
         name: &str,
@@ -174,6 +853,7 @@ impl Router {
             let mut map = self.sessions.lock().await;
             map.remove(name)
         };
+        self.status.stopped(name);
         match opt {
             Some(s) => {
                 if let Err(e) = s.shutdown().await {
@@ -195,7 +875,7 @@ impl Router {
     pub async fn ensure_shell_session_by_name(
         &mut self,
         name: &str,
-    ) -> Result<Arc<PtyShell>> {
+    ) -> Result<Arc<dyn Shell>> {
         debug!(name = name, "ensure_shell_session_by_name start");
         let Some(spec) = self.registry.get_shell_spec(name) else {
             error!(name = name, "ensure_shell_session_by_name unknown");
@@ -213,7 +893,7 @@ impl Router {
         &mut self,
         name: &str,
         spec: &ShellSpec,
-    ) -> Result<Arc<PtyShell>> {
+    ) -> Result<Arc<dyn Shell>> {
         debug!(
             name = name,
             kind = format!("{:?}", spec),
@@ -228,35 +908,85 @@ impl Router {
             }
         }
 
-        let s = factory::spawn(name, spec, self.cols, self.rows).await?;
-        let s = Arc::new(s);
+        let spec = self.resolve_jump_chain(name, spec)?;
+        let (cols, rows) = self.size.get();
+        let s = self.factory.spawn(name, &spec, cols, rows).await?;
+        let remote_hook =
+            spec.host().is_some() && integration::supports_prompt_hook(&spec);
+        let connect_rx =
+            (self.secrets.detects(&spec) || spec.via().is_some() || remote_hook)
+                .then(|| s.subscribe());
 
         {
             let mut map = self.sessions.lock().await;
             map.insert(name.to_string(), s.clone());
             info!(count = map.len(), "sessions count after insert");
         }
+        self.status.started(name);
+
+        if !remote_hook && integration::supports_prompt_hook(&spec) {
+            self.send_prompt_hook(name, &spec, s.as_ref()).await;
+        }
 
         info!(name = name, "ensure_shell_session_by_spec miss_created");
 
+        if self.recording.records(name) {
+            match s.start_recording(&self.recording.path_for(name)) {
+                Ok(path) => {
+                    info!(name = name, path = %path.display(), "session recording")
+                }
+                Err(e) => warn!(name = name, ?e, "session recording failed"),
+            }
+        }
+
         let mut rx = s.subscribe();
         let sessions_arc = self.sessions.clone();
+        let status = self.status.clone();
         let name_owned = name.to_string();
+        let audit = self.audit.clone();
+        let audit_spec = spec.clone();
+        let secrets = self.secrets.watches().then(|| self.secrets.clone());
+        let mut tail = match secrets {
+            Some(_) => s.render_screen().unwrap_or_default(),
+            None => String::new(),
+        };
         tokio::spawn(async move {
             loop {
                 match rx.recv().await {
+                    Ok(ShellEvent::Output(text)) if let Some(secrets) = &secrets => {
+                        push_tail(&mut tail, &text);
+                        let prompting = secrets.prompt_in(&tail).is_some();
+                        status.set_prompting(&name_owned, prompting);
+                    }
                     Ok(ShellEvent::Exited(reason)) => {
                         let mut map = sessions_arc.lock().await;
                         if map.remove(&name_owned).is_some() {
+                            status.disconnected(&name_owned);
                             info!(name = %name_owned, %reason, count = map.len(), "session removed (auto-cleanup)");
                         } else {
                             warn!(name = %name_owned, %reason, "session not found for removal");
                         }
                         break;
                     }
-                    Ok(ShellEvent::Output(_)) => {}
-                    Err(e) => {
-                        warn!(?e, name = %name_owned, "event recv failed in watcher");
+                    Ok(ShellEvent::Term(TermEvent::Cwd(path))) => {
+                        status.set_cwd(&name_owned, path)
+                    }
+                    Ok(ShellEvent::Term(TermEvent::Prompt(PromptMark::Finished(
+                        code,
+                    )))) => {
+                        status.set_prompting(&name_owned, false);
+                        if let Some(line) = status.finished(&name_owned, code)
+                            && let Some(audit) = &audit
+                        {
+                            audit.record(&name_owned, Some(&audit_spec), &line, code);
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(RecvError::Lagged(n)) => {
+                        warn!(name = %name_owned, skipped = n, "watcher lagged");
+                    }
+                    Err(RecvError::Closed) => {
+                        warn!(name = %name_owned, "watcher events closed");
                         break;
                     }
                 }
@@ -264,9 +994,128 @@ impl Router {
             info!(name = %name_owned, "watcher done");
         });
 
+        if let Some(rx) = connect_rx {
+            self.watch_connect(name, &spec, s.as_ref(), rx)
+                .await
+                .inspect_err(|e| warn!(name = name, ?e, "session login failed"))?;
+        }
+
+        if let Some(path) = spec.on_connect() {
+            let script =
+                Script::load(&config::expect_script_path(path)).map_err(|e| {
+                    warn!(name = name, path = path, ?e, "session on_connect failed");
+                    ExpectError::OnConnect {
+                        name: name.to_string(),
+                        source: Box::new(e),
+                    }
+                })?;
+            let hook = remote_hook.then(|| integration::prompt_hook(&spec));
+            let seed = s.render_screen().ok();
+            self.scripts
+                .spawn(self.gate(name, s.clone()), script, seed, hook)?;
+            info!(name = name, path = path, "session on_connect started");
+        } else if remote_hook {
+            self.send_prompt_hook(name, &spec, s.as_ref()).await;
+        }
+
         Ok(s)
     }
 
+    fn gate(&self, name: &str, shell: Arc<dyn Shell>) -> SessionGate {
+        SessionGate {
+            name: name.to_string(),
+            spec: self.registry.get_shell_spec(name),
+            shell,
+            registry: self.registry.clone(),
+            locks: self.locks.clone(),
+            policy: self.policy.clone(),
+            audit: self.audit.clone(),
+            status: self.status.clone(),
+        }
+    }
+
+    pub fn script_running(&self, name: &str) -> bool {
+        self.scripts.is_running(name)
+    }
+
+    async fn send_prompt_hook(&self, name: &str, spec: &ShellSpec, s: &dyn Shell) {
+        self.status.sent_internal(name);
+        match s.send_line(integration::prompt_hook(spec)).await {
+            Ok(()) => info!(name = name, "session prompt hook installed"),
+            Err(e) => warn!(name = name, ?e, "session prompt hook failed"),
+        }
+    }
+
+    pub async fn run_expect(&mut self, name: &str, script: &Script) -> Result<()> {
+        debug!(name = name, "router_run_expect start");
+        self.ensure_writable(name)?;
+        let s = self.ensure_shell_session_by_name(name).await?;
+        self.scripts
+            .spawn(self.gate(name, s), script.clone(), None, None)?;
+        info!(name = name, "router_run_expect ok");
+        Ok(())
+    }
+
+    fn resolve_jump_chain(&self, name: &str, spec: &ShellSpec) -> Result<ShellSpec> {
+        debug!(name = name, "resolve_jump_chain start");
+        let Some(first) = spec.via() else {
+            info!(name = name, "resolve_jump_chain direct");
+            return Ok(spec.clone());
+        };
+        let bastion_err = |source: anyhow::Error| ShellError::Bastion {
+            name: name.to_string(),
+            via: first.to_string(),
+            source,
+        };
+
+        let mut hops: Vec<String> = Vec::new();
+        let mut seen: HashSet<String> = HashSet::from([name.to_string()]);
+        let mut next = Some(first.to_string());
+        while let Some(via) = next.take() {
+            if !seen.insert(via.clone()) {
+                warn!(name = name, via = %via, "resolve_jump_chain cycle");
+                return Err(
+                    bastion_err(anyhow!("bastion chain loops back to {via}")).into()
+                );
+            }
+            match self.registry.get_entry(&via) {
+                Some(registry::Entry::Shell(bastion)) => {
+                    let Some(hop) = bastion.jump_host() else {
+                        warn!(via = %via, "resolve_jump_chain bastion not ssh");
+                        return Err(bastion_err(anyhow!(
+                            "bastion {via} is a {} shell, not an ssh remote",
+                            bastion.kind_name()
+                        ))
+                        .into());
+                    };
+                    // `-J` has no room for per-hop options, so they would be
+                    // silently dropped.
+                    if !bastion.extra_args().is_empty() {
+                        warn!(via = %via, "resolve_jump_chain bastion extra args");
+                        return Err(bastion_err(anyhow!(
+                            "bastion {via} sets extra_args, which cannot be passed \
+                             through -J; move them to ~/.ssh/config"
+                        ))
+                        .into());
+                    }
+                    hops.push(hop);
+                    next = bastion.via().map(str::to_string);
+                }
+                Some(registry::Entry::Builtin) => {
+                    warn!(via = %via, "resolve_jump_chain bastion builtin");
+                    return Err(
+                        bastion_err(anyhow!("bastion {via} is a builtin")).into()
+                    );
+                }
+                None => hops.push(via),
+            }
+        }
+        hops.reverse();
+        let jumps = hops.join(",");
+        info!(name = name, jumps = %jumps, "resolve_jump_chain ok");
+        Ok(spec.with_via(Some(jumps)))
+    }
+
     pub async fn list_entries_with_status(
         &self,
     ) -> Vec<(String, registry::Entry, bool)> {
@@ -289,6 +1138,338 @@ impl Router {
         v
     }
 
+    pub fn set_recording_settings(&mut self, settings: RecordingSettings) {
+        debug!(dir = %settings.dir.display(), "router_set_recording_settings start");
+        self.recording = settings;
+        info!("router_set_recording_settings ok");
+    }
+
+    pub fn set_audit_log(&mut self, audit: Option<AuditLog>) {
+        debug!(enabled = audit.is_some(), "router_set_audit_log start");
+        self.audit = audit;
+        info!("router_set_audit_log ok");
+    }
+
+    fn audit(&self, name: &str, line: &str, exit_status: Option<i32>) {
+        if let Some(audit) = &self.audit {
+            let spec = self.registry.get_shell_spec(name);
+            audit.record(name, spec.as_ref(), line, exit_status);
+        }
+    }
+
+    // The exit status is not known yet; the watcher writes a second record with
+    // it when the prompt hook reports the command finished.
+    fn audit_sent(&self, name: &str, line: &str) {
+        self.audit(name, line, None);
+        match self.audit {
+            Some(_) => self.status.sent_audited(name, line),
+            None => self.status.sent(name),
+        }
+    }
+
+    pub fn set_policy(&mut self, policy: Policy) {
+        debug!(rules = policy.rules().len(), "router_set_policy start");
+        self.policy = policy;
+        info!("router_set_policy ok");
+    }
+
+    pub fn set_shell_factory(&mut self, factory: Arc<dyn ShellFactory>) {
+        self.factory = factory;
+        info!("router_set_shell_factory ok");
+    }
+
+    pub fn set_confirmer(&mut self, confirmer: Arc<dyn Confirmer>) {
+        self.confirmer = confirmer;
+    }
+
+    pub fn replace_confirmer(
+        &mut self,
+        confirmer: Arc<dyn Confirmer>,
+    ) -> Arc<dyn Confirmer> {
+        std::mem::replace(&mut self.confirmer, confirmer)
+    }
+
+    pub fn set_secrets(&mut self, secrets: Secrets) {
+        self.secrets = secrets;
+        info!("router_set_secrets ok");
+    }
+
+    pub fn set_secret_source(&mut self, source: Arc<dyn SecretSource>) {
+        self.secrets.set_source(source);
+        info!("router_set_secret_source ok");
+    }
+
+    pub fn set_secret_prompter(&mut self, prompter: Arc<dyn SecretPrompter>) {
+        self.secret_prompter = prompter;
+    }
+
+    pub async fn send_secret(&mut self, name: &str, ask: bool) -> Result<()> {
+        debug!(name = name, ask = ask, "router_send_secret start");
+        self.ensure_writable(name)?;
+        let s = self.ensure_shell_session_by_name(name).await?;
+        let host = self
+            .registry
+            .get_shell_spec(name)
+            .and_then(|spec| spec.host().map(str::to_string));
+        let found = match ask {
+            true => None,
+            false => self.secrets.lookup(name, host.as_deref()).await,
+        };
+        let secret = match found {
+            Some(secret) => secret,
+            None => self.ask_secret(name, "Password:")?,
+        };
+        s.send_secret(&secret).await?;
+        self.status.set_prompting(name, false);
+        info!(name = name, "router_send_secret ok");
+        Ok(())
+    }
+
+    fn ask_secret(&self, name: &str, prompt: &str) -> Result<Secret> {
+        self.secret_prompter.prompt(name, prompt)?.ok_or_else(|| {
+            warn!(name = name, "ask_secret unavailable");
+            ReplRouterError::SecretUnavailable {
+                name: name.to_string(),
+            }
+            .into()
+        })
+    }
+
+    async fn watch_connect(
+        &self,
+        name: &str,
+        spec: &ShellSpec,
+        s: &dyn Shell,
+        mut rx: broadcast::Receiver<ShellEvent>,
+    ) -> Result<()> {
+        debug!(name = name, "watch_connect start");
+        let deadline = time::Instant::now() + self.secrets.timeout();
+        let answer = self.secrets.detects(spec);
+        let mut tail = s.render_screen().unwrap_or_default();
+        let mut attempts = 0;
+        let mut seen = !tail.trim().is_empty();
+        loop {
+            if answer && let Some(prompt) = self.secrets.prompt_in(&tail) {
+                attempts += 1;
+                if attempts > MAX_SECRET_ATTEMPTS {
+                    warn!(name = name, attempts, "watch_connect rejected");
+                    return Err(ReplRouterError::SecretRejected {
+                        name: name.to_string(),
+                        attempts: MAX_SECRET_ATTEMPTS,
+                    }
+                    .into());
+                }
+                let found = match attempts {
+                    1 => self.secrets.lookup(name, spec.host()).await,
+                    _ => None,
+                };
+                let secret = match found {
+                    Some(secret) => secret,
+                    None => self.ask_secret(name, prompt)?,
+                };
+                s.send_secret(&secret).await?;
+                self.status.set_prompting(name, false);
+                info!(name = name, attempts, "watch_connect answered");
+                tail.clear();
+            } else if seen && !cursor_text(s, &tail).is_empty() {
+                // The cursor sits after something other than a secret prompt,
+                // most likely the shell prompt, so there is nothing to settle.
+                info!(name = name, attempts, "watch_connect waiting on input");
+                break;
+            }
+            let wait = match !seen && attempts == 0 {
+                true => deadline,
+                false => deadline.min(time::Instant::now() + SECRET_SETTLE),
+            };
+            match time::timeout_at(wait, rx.recv()).await {
+                Ok(Ok(ShellEvent::Output(text))) => {
+                    seen = true;
+                    push_tail(&mut tail, &text);
+                }
+                Ok(Ok(ShellEvent::Term(TermEvent::Prompt(_)))) => break,
+                Ok(Ok(ShellEvent::Exited(_))) | Ok(Err(RecvError::Closed)) => {
+                    let Some(via) = spec.via() else {
+                        break;
+                    };
+                    if let Some(reason) = factory::bastion_failure(via, &tail) {
+                        warn!(name = name, via = via, "watch_connect bastion failed");
+                        return Err(ShellError::Bastion {
+                            name: name.to_string(),
+                            via: via.to_string(),
+                            source: anyhow!(reason),
+                        }
+                        .into());
+                    }
+                    break;
+                }
+                Ok(Ok(_)) | Ok(Err(RecvError::Lagged(_))) => {}
+                Err(_) => break,
+            }
+        }
+        info!(name = name, attempts, "watch_connect ok");
+        Ok(())
+    }
+
+    fn confirm_verdict(&self, verdict: Verdict) -> Result<()> {
+        let Verdict::Confirm {
+            target,
+            expected,
+            reason,
+        } = verdict
+        else {
+            return Ok(());
+        };
+        if self.panes.is_active() {
+            warn!(target = %target, "policy declined in pane view");
+            return Err(ReplRouterError::PolicyDeclined {
+                name: target,
+                reason: format!("{reason}; leave the pane view to confirm it"),
+            }
+            .into());
+        }
+        if self.confirmer.confirm(&reason, &expected)? {
+            info!(target = %target, "policy confirmed");
+            return Ok(());
+        }
+        warn!(target = %target, "policy declined");
+        Err(ReplRouterError::PolicyDeclined {
+            name: target,
+            reason,
+        }
+        .into())
+    }
+
+    fn verdict(&self, name: &str, command: &str) -> Verdict {
+        let Some(entry) = self.registry.get_entry(name) else {
+            return Verdict::Allow;
+        };
+        let parsed = Parsed::Entry {
+            name: name.to_string(),
+            entry,
+            command: command.to_string(),
+        };
+        self.policy.evaluate(&self.registry, &parsed, None)
+    }
+
+    fn guard(&self, name: &str, command: &str) -> Result<()> {
+        self.confirm_verdict(self.verdict(name, command))
+    }
+
+    // The watcher can lag behind the session, so a screen, when there is one,
+    // must still show the prompt. A line the policy would guard is a command.
+    fn answers_prompt(&self, name: &str, s: &dyn Shell, line: &str) -> bool {
+        self.status.take_prompting(name)
+            && self.shows_prompt(s)
+            && !matches!(self.verdict(name, line), Verdict::Confirm { .. })
+    }
+
+    fn shows_prompt(&self, s: &dyn Shell) -> bool {
+        cursor_line(s).is_none_or(|l| self.secrets.prompt_in(&l).is_some())
+    }
+
+    pub async fn prompt_pending(&self, name: &str) -> bool {
+        if !self.status.is_prompting(name) {
+            return false;
+        }
+        self.running_shell(name)
+            .await
+            .is_some_and(|s| self.shows_prompt(s.as_ref()))
+    }
+
+    fn guard_fanout(&self, lines: &[String]) -> Result<()> {
+        if self.policy.max_fanout().is_none() {
+            return Ok(());
+        }
+        let mut current = self.mode.get_current().or_else(|| self.mode.get_default());
+        let mut targets: BTreeSet<String> = BTreeSet::new();
+        for line in lines {
+            let pipeline = parser::parse_pipeline(&self.registry, line);
+            let single = pipeline.is_none();
+            for parsed in
+                pipeline.unwrap_or_else(|| vec![parser::parse(&self.registry, line)])
+            {
+                let name = match parsed {
+                    Parsed::Entry { name, .. } => {
+                        if single {
+                            current = Some(name.clone());
+                        }
+                        name
+                    }
+                    Parsed::Default { .. } => match &current {
+                        Some(name) => name.clone(),
+                        None => continue,
+                    },
+                };
+                if self.registry.get_shell_spec(&name).is_some() {
+                    targets.insert(name);
+                }
+            }
+        }
+        self.confirm_verdict(self.policy.evaluate_fanout(&targets))
+    }
+
+    async fn running_session(&self, name: &str) -> Result<Arc<dyn Shell>> {
+        let map = self.sessions.lock().await;
+        map.get(name).cloned().ok_or_else(|| {
+            warn!(name = name, "running_session not_running");
+            ReplRouterError::SessionNotRunning {
+                name: name.to_string(),
+            }
+            .into()
+        })
+    }
+
+    pub async fn running_shell(&self, name: &str) -> Option<Arc<dyn Shell>> {
+        self.sessions.lock().await.get(name).cloned()
+    }
+
+    pub async fn render_screen(&self, name: &str) -> Result<String> {
+        debug!(name = name, "router_render_screen start");
+        let session = self.running_session(name).await?;
+        let out = session.render_screen()?;
+        info!(name = name, "router_render_screen ok");
+        Ok(out)
+    }
+
+    pub async fn start_recording(
+        &mut self,
+        name: &str,
+        path: Option<PathBuf>,
+    ) -> Result<PathBuf> {
+        debug!(name = name, "router_start_recording start");
+        let session = self.running_session(name).await?;
+        let path = path.unwrap_or_else(|| self.recording.path_for(name));
+        let path = session.start_recording(&path)?;
+        info!(name = name, path = %path.display(), "router_start_recording ok");
+        Ok(path)
+    }
+
+    pub async fn stop_recording(&mut self, name: &str) -> Result<PathBuf> {
+        debug!(name = name, "router_stop_recording start");
+        let session = self.running_session(name).await?;
+        let Some(path) = session.stop_recording() else {
+            warn!(name = name, "router_stop_recording not_recording");
+            return Err(ReplRouterError::NotRecording {
+                name: name.to_string(),
+            }
+            .into());
+        };
+        info!(name = name, path = %path.display(), "router_stop_recording ok");
+        Ok(path)
+    }
+
+    pub async fn list_recordings(&self) -> Vec<(String, PathBuf)> {
+        debug!("router_list_recordings start");
+        let map = self.sessions.lock().await;
+        let mut out: Vec<(String, PathBuf)> = map
+            .iter()
+            .filter_map(|(n, s)| s.recording_path().map(|p| (n.clone(), p)))
+            .collect();
+        out.sort();
+        info!(count = out.len(), "router_list_recordings ok");
+        out
+    }
+
     pub async fn list_running_entries(&self) -> Vec<String> {
         debug!("list_running_shell_names start");
         let map = self.sessions.lock().await;
@@ -298,6 +1479,115 @@ impl Router {
         names
     }
 
+    pub fn register_builtin(&mut self, builtin: Arc<dyn Builtin>) {
+        debug!(name = builtin.name(), "router_register_builtin start");
+        let names: Vec<String> = std::iter::once(builtin.name())
+            .chain(builtin.aliases().iter().copied())
+            .map(str::to_string)
+            .collect();
+        self.builtins.register(builtin);
+        for name in names {
+            self.registry.register_entry(name, registry::Entry::Builtin);
+        }
+        info!("router_register_builtin ok");
+    }
+
+    pub fn unregister_builtin(&mut self, name: &str) {
+        debug!(name = name, "router_unregister_builtin start");
+        match self.builtins.unregister(name) {
+            Some(builtin) => {
+                self.registry.unregister_entry(builtin.name());
+                for alias in builtin.aliases() {
+                    self.registry.unregister_entry(alias);
+                }
+                info!(name = name, "router_unregister_builtin ok");
+            }
+            None => warn!(name = name, "router_unregister_builtin not_found"),
+        }
+    }
+
+    pub fn list_builtins(&self) -> Vec<Arc<dyn Builtin>> {
+        self.builtins.list()
+    }
+
+    pub fn get_builtin(&self, name: &str) -> Option<Arc<dyn Builtin>> {
+        self.builtins.get(name)
+    }
+
+    pub fn completion_entries(&self) -> Vec<CompletionEntry> {
+        debug!("completion_entries start");
+        let mut v: Vec<CompletionEntry> = self
+            .registry
+            .list_entries()
+            .into_iter()
+            .map(|(name, entry)| {
+                let hints = match entry {
+                    registry::Entry::Builtin => self
+                        .builtins
+                        .get(&name)
+                        .map(|b| b.completions())
+                        .unwrap_or_default(),
+                    registry::Entry::Shell(_) => Vec::new(),
+                };
+                CompletionEntry {
+                    name,
+                    hints,
+                    prefix: true,
+                }
+            })
+            .collect();
+        v.extend(
+            self.aliases
+                .names()
+                .into_iter()
+                .map(|name| CompletionEntry {
+                    name,
+                    hints: Vec::new(),
+                    prefix: false,
+                }),
+        );
+        info!(count = v.len(), "completion_entries ok");
+        v
+    }
+
+    pub fn list_aliases(&self) -> Vec<(String, Vec<String>)> {
+        self.aliases.list()
+    }
+
+    pub fn get_alias(&self, name: &str) -> Option<Vec<String>> {
+        self.aliases.get(name)
+    }
+
+    pub fn set_alias(&mut self, name: &str, lines: Vec<String>) -> Result<()> {
+        debug!(name = name, "router_set_alias start");
+        self.aliases.set(name, lines)?;
+        info!(name = name, "router_set_alias ok");
+        Ok(())
+    }
+
+    pub fn remove_alias(&mut self, name: &str) -> bool {
+        self.aliases.remove(name)
+    }
+
+    pub fn list_vars(&self) -> Vec<(String, String)> {
+        self.vars.list()
+    }
+
+    pub fn get_var(&self, name: &str) -> Option<String> {
+        self.vars.get(name)
+    }
+
+    pub fn set_var(&mut self, name: &str, value: String) -> Result<()> {
+        debug!(name = name, "router_set_var start");
+        self.vars.set(name, value)?;
+        info!(name = name, "router_set_var ok");
+        Ok(())
+    }
+
+    pub fn remove_var(&mut self, name: &str) -> bool {
+        self.vars.remove(name)
+    }
+
     pub fn register_entry(&mut self, name: String, entry: registry::Entry) {
         debug!(name = %name, entry = format!("{:?}", entry), "router_register_entry start");
         self.registry.register_entry(name, entry);
@@ -309,6 +1599,52 @@ impl Router {
         self.registry.unregister_entry(name);
         info!("router_unregister_entry ok")
     }
+
+    pub fn add_shell_entry(&mut self, name: &str, spec: ShellSpec) -> Result<()> {
+        debug!(name = name, "router_add_shell_entry start");
+        if self.registry.has_entry(name) {
+            warn!(name = name, "router_add_shell_entry exists");
+            return Err(ReplRouterError::EntryExists {
+                name: name.to_string(),
+            }
+            .into());
+        }
+        self.registry
+            .register_entry(name.to_string(), registry::Entry::Shell(spec));
+        info!(name = name, "router_add_shell_entry ok");
+        Ok(())
+    }
+
+    pub async fn remove_shell_entry(&mut self, name: &str) -> Result<()> {
+        debug!(name = name, "router_remove_shell_entry start");
+        match self.registry.get_entry(name) {
+            Some(registry::Entry::Shell(_)) => {}
+            Some(registry::Entry::Builtin) => {
+                warn!(name = name, "router_remove_shell_entry builtin");
+                return Err(ReplRouterError::BuiltinEntry {
+                    name: name.to_string(),
+                }
+                .into());
+            }
+            None => {
+                warn!(name = name, "router_remove_shell_entry unknown");
+                return Err(ReplRouterError::UnknownShell {
+                    name: name.to_string(),
+                }
+                .into());
+            }
+        }
+        if self.running_shell(name).await.is_some()
+            && let Err(e) = self.stop_shell_session(name).await
+        {
+            warn!(name = name, ?e, "router_remove_shell_entry stop failed");
+        }
+        self.panes.remove(name);
+        self.viewers.unshare(name);
+        self.registry.unregister_entry(name);
+        info!(name = name, "router_remove_shell_entry ok");
+        Ok(())
+    }
 }
 
 #[async_trait]
@@ -328,7 +1664,7 @@ impl BuiltinContext for Router {
     async fn ensure_shell_session_by_name(
         &mut self,
         name: &str,
-    ) -> Result<Arc<PtyShell>> {
+    ) -> Result<Arc<dyn Shell>> {
         Router::ensure_shell_session_by_name(self, name).await
     }
 
@@ -340,6 +1676,98 @@ impl BuiltinContext for Router {
         Router::list_running_entries(self).await
     }
 
+    fn lock_session(&mut self, name: &str) -> Result<()> {
+        Router::lock_session(self, name)
+    }
+
+    fn unlock_session(&mut self, name: &str) -> Result<bool> {
+        Router::unlock_session(self, name)
+    }
+
+    fn is_locked(&self, name: &str) -> bool {
+        Router::is_locked(self, name)
+    }
+
+    fn session_cwd(&self, name: &str) -> Option<String> {
+        Router::session_cwd(self, name)
+    }
+
+    async fn add_pane(&mut self, name: &str) -> Result<bool> {
+        Router::add_pane(self, name).await
+    }
+
+    fn remove_pane(&mut self, name: &str) -> bool {
+        Router::remove_pane(self, name)
+    }
+
+    fn focus_pane(&mut self, name: &str) -> Result<()> {
+        Router::focus_pane(self, name)
+    }
+
+    fn list_panes(&self) -> (Vec<String>, Option<String>) {
+        (self.panes.names(), self.panes.focused())
+    }
+
+    fn request_view(&mut self, request: ViewRequest) {
+        Router::request_view(self, request)
+    }
+
+    async fn render_screen(&self, name: &str) -> Result<String> {
+        Router::render_screen(self, name).await
+    }
+
+    fn share_session(&mut self, name: &str, mode: ShareMode) -> Result<()> {
+        Router::share_session(self, name, mode)
+    }
+
+    fn unshare_session(&mut self, name: &str) -> usize {
+        Router::unshare_session(self, name)
+    }
+
+    fn share_mode(&self, name: &str) -> Option<ShareMode> {
+        Router::share_mode(self, name)
+    }
+
+    fn list_viewers(&self, name: &str) -> Vec<Viewer> {
+        Router::list_viewers(self, name)
+    }
+
+    async fn run_expect(&mut self, name: &str, script: &Script) -> Result<()> {
+        Router::run_expect(self, name, script).await
+    }
+
+    async fn send_secret(&mut self, name: &str, ask: bool) -> Result<()> {
+        Router::send_secret(self, name, ask).await
+    }
+
+    fn daemon_socket(&self) -> Option<PathBuf> {
+        Router::daemon_socket(self)
+    }
+
+    async fn install_prompt_hook(&mut self, name: &str) -> Result<()> {
+        Router::install_prompt_hook(self, name).await
+    }
+
+    async fn cd_sync(&mut self, from: &str, to: &str) -> Result<String> {
+        Router::cd_sync(self, from, to).await
+    }
+
+    async fn start_recording(
+        &mut self,
+        name: &str,
+        path: Option<PathBuf>,
+    ) -> Result<PathBuf> {
+        Router::start_recording(self, name, path).await
+    }
+
+    async fn stop_recording(&mut self, name: &str) -> Result<PathBuf> {
+        Router::stop_recording(self, name).await
+    }
+
+    async fn list_recordings(&self) -> Vec<(String, PathBuf)> {
+        Router::list_recordings(self).await
+    }
+
     fn list_entries(&self) -> Vec<(String, registry::Entry)> {
         self.registry.list_entries()
     }
@@ -353,18 +1781,120 @@ impl BuiltinContext for Router {
     }
 
     fn get_current_mode(&self) -> Option<String> {
-        Router::get_current_mode(&self)
+        Router::get_current_mode(self)
     }
 
     fn set_current_mode(&mut self, name: &str) -> bool {
-        Router::set_current_mode(&self, name)
+        Router::set_current_mode(self, name)
     }
 
     fn get_default_mode(&self) -> Option<String> {
-        Router::get_current_mode(&self)
+        Router::get_default_mode(self)
     }
 
     fn set_default_mode(&mut self, name: &str) -> bool {
-        Router::set_default_mode(&self, name)
+        Router::set_default_mode(self, name)
+    }
+
+    fn list_aliases(&self) -> Vec<(String, Vec<String>)> {
+        Router::list_aliases(self)
+    }
+
+    fn get_alias(&self, name: &str) -> Option<Vec<String>> {
+        Router::get_alias(self, name)
+    }
+
+    fn set_alias(&mut self, name: String, lines: Vec<String>) -> Result<()> {
+        Router::set_alias(self, &name, lines)
     }
+
+    fn remove_alias(&mut self, name: &str) -> bool {
+        Router::remove_alias(self, name)
+    }
+
+    fn list_vars(&self) -> Vec<(String, String)> {
+        Router::list_vars(self)
+    }
+
+    fn get_var(&self, name: &str) -> Option<String> {
+        Router::get_var(self, name)
+    }
+
+    fn set_var(&mut self, name: String, value: String) -> Result<()> {
+        Router::set_var(self, &name, value)
+    }
+
+    fn remove_var(&mut self, name: &str) -> bool {
+        Router::remove_var(self, name)
+    }
+
+    async fn substitute(&mut self, input: &str) -> Result<String> {
+        Router::substitute(self, input).await
+    }
+
+    fn list_builtins(&self) -> Vec<Arc<dyn Builtin>> {
+        Router::list_builtins(self)
+    }
+
+    fn get_builtin(&self, name: &str) -> Option<Arc<dyn Builtin>> {
+        Router::get_builtin(self, name)
+    }
+
+    fn repl_settings(&self) -> Option<ReplSettings> {
+        Router::repl_settings(self)
+    }
+}
+
+async fn propagate_size(
+    sessions: &Mutex<HashMap<String, Arc<dyn Shell>>>,
+    panes: &PaneSet,
+    (cols, rows): (u16, u16),
+    resize_all: bool,
+    focused: Option<String>,
+) {
+    if panes.is_active() {
+        debug!("propagate_size skipped for pane view");
+        return;
+    }
+    let targets: Vec<(String, Arc<dyn Shell>)> = {
+        let map = sessions.lock().await;
+        map.iter()
+            .filter(|(name, _)| resize_all || focused.as_ref() == Some(*name))
+            .map(|(name, s)| (name.clone(), s.clone()))
+            .collect()
+    };
+    for (name, session) in targets {
+        if let Err(e) = session.resize(cols, rows).await {
+            warn!(name = %name, ?e, "propagate_size resize failed");
+        }
+    }
+}
+
+fn push_tail(tail: &mut String, text: &str) {
+    tail.push_str(text);
+    if tail.len() > SECRET_TAIL_CAP {
+        let mut cut = tail.len() - SECRET_TAIL_CAP;
+        while !tail.is_char_boundary(cut) {
+            cut += 1;
+        }
+        tail.drain(..cut);
+    }
+}
+
+fn cursor_text(shell: &dyn Shell, tail: &str) -> String {
+    cursor_line(shell).unwrap_or_else(|| {
+        tail.rsplit('\n')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_string()
+    })
+}
+
+fn cursor_line(shell: &dyn Shell) -> Option<String> {
+    let screen = shell.screen()?;
+    let screen = screen.lock().ok()?;
+    let (row, _) = screen.cursor();
+    let line: String = screen.rows().nth(row)?.iter().map(|c| c.ch).collect();
+    Some(line.trim().to_string())
 }
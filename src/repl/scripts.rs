@@ -0,0 +1,182 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+};
+
+use async_trait::async_trait;
+use tracing::{debug, info, warn};
+
+use crate::{
+    error::{ReplRouterError, Result},
+    registry::Registry,
+    repl::{LockState, Policy, SessionStatus, parser::Parsed, policy::Verdict},
+    runtime::AuditLog,
+    shell::{
+        Shell, ShellSpec,
+        expect::{LineSink, Script},
+    },
+    ui::ui_println,
+};
+
+// Lines a script sends go through the same checks as `name: line`, but nobody
+// can answer a confirmation while it runs, so guarded lines are declined.
+#[derive(Clone)]
+pub struct SessionGate {
+    pub(crate) name: String,
+    pub(crate) spec: Option<ShellSpec>,
+    pub(crate) shell: Arc<dyn Shell>,
+    pub(crate) registry: Registry,
+    pub(crate) locks: LockState,
+    pub(crate) policy: Policy,
+    pub(crate) audit: Option<AuditLog>,
+    pub(crate) status: SessionStatus,
+}
+
+impl SessionGate {
+    fn check(&self, line: &str) -> Result<()> {
+        let name = self.name.clone();
+        if self.spec.as_ref().is_some_and(ShellSpec::is_readonly) {
+            return Err(ReplRouterError::SessionReadonly { name }.into());
+        }
+        if self.locks.is_locked(&name) {
+            return Err(ReplRouterError::SessionLocked { name }.into());
+        }
+        let Some(entry) = self.registry.get_entry(&name) else {
+            return Ok(());
+        };
+        let parsed = Parsed::Entry {
+            name,
+            entry,
+            command: line.to_string(),
+        };
+        match self.policy.evaluate(&self.registry, &parsed, None) {
+            Verdict::Confirm { target, reason, .. } => {
+                warn!(target = %target, "script line declined");
+                Err(ReplRouterError::PolicyDeclined {
+                    name: target,
+                    reason: format!("{reason}; scripts cannot confirm it"),
+                }
+                .into())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[async_trait]
+impl LineSink for SessionGate {
+    async fn send(&self, line: String) -> Result<()> {
+        self.check(&line)?;
+        match &self.audit {
+            Some(audit) => {
+                audit.record(&self.name, self.spec.as_ref(), &line, None);
+                self.status.sent_audited(&self.name, &line);
+            }
+            None => self.status.sent(&self.name),
+        }
+        self.shell.send_line(line).await
+    }
+}
+
+// Sessions with a script in flight. Lines routed to them meanwhile are held
+// and sent in order once the script succeeds, like keys typed ahead.
+#[derive(Clone, Default)]
+pub struct ScriptJobs {
+    held: Arc<Mutex<HashMap<String, VecDeque<String>>>>,
+}
+
+impl ScriptJobs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_running(&self, name: &str) -> bool {
+        self.held.lock().is_ok_and(|h| h.contains_key(name))
+    }
+
+    // Returns the line back when no script runs on the session.
+    pub fn hold(&self, name: &str, line: String) -> Option<String> {
+        let Ok(mut held) = self.held.lock() else {
+            return Some(line);
+        };
+        match held.get_mut(name) {
+            Some(queue) => {
+                info!(name = name, "script_jobs held line");
+                queue.push_back(line);
+                None
+            }
+            None => Some(line),
+        }
+    }
+
+    fn start(&self, name: &str) -> bool {
+        let Ok(mut held) = self.held.lock() else {
+            return false;
+        };
+        if held.contains_key(name) {
+            return false;
+        }
+        held.insert(name.to_string(), VecDeque::new());
+        true
+    }
+
+    fn next_held(&self, name: &str) -> Option<String> {
+        let mut held = self.held.lock().ok()?;
+        let line = held.get_mut(name).and_then(VecDeque::pop_front);
+        if line.is_none() {
+            held.remove(name);
+        }
+        line
+    }
+
+    fn drop_held(&self, name: &str) -> usize {
+        self.held
+            .lock()
+            .ok()
+            .and_then(|mut h| h.remove(name))
+            .map_or(0, |q| q.len())
+    }
+
+    pub fn spawn(
+        &self,
+        gate: SessionGate,
+        script: Script,
+        seed: Option<String>,
+        after: Option<String>,
+    ) -> Result<()> {
+        let name = gate.name.clone();
+        debug!(name = %name, "script_jobs_spawn start");
+        if !self.start(&name) {
+            warn!(name = %name, "script_jobs_spawn busy");
+            return Err(ReplRouterError::ScriptRunning { name }.into());
+        }
+        let jobs = self.clone();
+        tokio::spawn(async move {
+            let res = script.run(gate.shell.as_ref(), &gate, seed).await;
+            if let Err(e) = res {
+                let dropped = jobs.drop_held(&gate.name);
+                warn!(name = %gate.name, ?e, dropped = dropped, "script failed");
+                let mut msg = format!("[{}] expect script failed: {e}", gate.name);
+                if dropped > 0 {
+                    msg.push_str(&format!("; {dropped} held lines dropped"));
+                }
+                let _ = ui_println(&msg);
+                return;
+            }
+            info!(name = %gate.name, "script finished");
+            if let Some(line) = after {
+                gate.status.sent_internal(&gate.name);
+                if let Err(e) = gate.shell.send_line(line).await {
+                    warn!(name = %gate.name, ?e, "script after line failed");
+                }
+            }
+            while let Some(line) = jobs.next_held(&gate.name) {
+                if let Err(e) = gate.shell.send_line(line).await {
+                    warn!(name = %gate.name, ?e, "script held line failed");
+                }
+            }
+        });
+        info!(name = %name, "script_jobs_spawn ok");
+        Ok(())
+    }
+}
@@ -0,0 +1,206 @@
+use std::{
+    collections::HashMap,
+    process::Stdio,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use regex::Regex;
+use tokio::process::Command;
+use tracing::{debug, info, warn};
+
+use crate::{
+    error::{Result, RuntimeError},
+    runtime::config::SecretsSection,
+    shell::{Secret, ShellSpec},
+};
+
+const DEFAULT_PROMPT_PATTERN: &str =
+    r"(?i)(?:password|passphrase|passcode|pin)(?: for [^:\n]*)?:\s*$";
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+const NAME_PLACEHOLDER: &str = "{name}";
+const HOST_PLACEHOLDER: &str = "{host}";
+
+#[async_trait]
+pub trait SecretSource: Send + Sync {
+    async fn lookup(&self, name: &str, host: Option<&str>) -> Result<Option<Secret>>;
+}
+
+pub trait SecretPrompter: Send + Sync {
+    fn prompt(&self, name: &str, prompt: &str) -> Result<Option<Secret>>;
+}
+
+pub struct CommandSource {
+    argv: Vec<String>,
+}
+
+impl CommandSource {
+    pub fn new(argv: Vec<String>) -> Self {
+        Self { argv }
+    }
+}
+
+#[async_trait]
+impl SecretSource for CommandSource {
+    async fn lookup(&self, name: &str, host: Option<&str>) -> Result<Option<Secret>> {
+        let Some((program, args)) = self.argv.split_first() else {
+            return Ok(None);
+        };
+        debug!(name = name, program = %program, "command_source_lookup start");
+        let expand = |arg: &String| {
+            arg.replace(NAME_PLACEHOLDER, name)
+                .replace(HOST_PLACEHOLDER, host.unwrap_or(name))
+        };
+        let output = Command::new(expand(program))
+            .args(args.iter().map(expand))
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output()
+            .await
+            .map_err(|source| RuntimeError::SecretsCommand {
+                program: program.clone(),
+                source,
+            })?;
+        if !output.status.success() {
+            warn!(name = name, status = %output.status, "command_source_lookup none");
+            return Ok(None);
+        }
+        let stdout = Secret::new(String::from_utf8_lossy(&output.stdout));
+        let secret = Secret::new(stdout.expose().lines().next().unwrap_or_default());
+        if secret.is_empty() {
+            warn!(name = name, "command_source_lookup empty");
+            return Ok(None);
+        }
+        info!(name = name, "command_source_lookup ok");
+        Ok(Some(secret))
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct StaticSecrets {
+    entries: Arc<RwLock<HashMap<String, String>>>,
+}
+
+impl StaticSecrets {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&self, name: &str, secret: &str) {
+        if let Ok(mut w) = self.entries.write() {
+            w.insert(name.to_string(), secret.to_string());
+        }
+    }
+}
+
+#[async_trait]
+impl SecretSource for StaticSecrets {
+    async fn lookup(&self, name: &str, host: Option<&str>) -> Result<Option<Secret>> {
+        let r = self.entries.read().ok();
+        let found = r.and_then(|r| {
+            r.get(name)
+                .or_else(|| host.and_then(|h| r.get(h)))
+                .map(Secret::new)
+        });
+        info!(
+            name = name,
+            found = found.is_some(),
+            "static_secrets_lookup ok"
+        );
+        Ok(found)
+    }
+}
+
+#[derive(Clone)]
+pub struct Secrets {
+    detect: bool,
+    prompt: Option<Regex>,
+    timeout: Duration,
+    source: Option<Arc<dyn SecretSource>>,
+}
+
+impl Default for Secrets {
+    fn default() -> Self {
+        Self {
+            detect: true,
+            prompt: Regex::new(DEFAULT_PROMPT_PATTERN).ok(),
+            timeout: DEFAULT_TIMEOUT,
+            source: None,
+        }
+    }
+}
+
+impl Secrets {
+    pub fn from_config(section: Option<&SecretsSection>) -> Result<Self> {
+        debug!(present = section.is_some(), "secrets_from_config start");
+        let mut secrets = Self::default();
+        let Some(section) = section else {
+            info!("secrets_from_config default");
+            return Ok(secrets);
+        };
+        if let Some(pattern) = &section.prompt {
+            let re =
+                Regex::new(pattern).map_err(|source| RuntimeError::SecretsPattern {
+                    pattern: pattern.clone(),
+                    source,
+                })?;
+            secrets.prompt = Some(re);
+        }
+        if let Some(timeout) = section
+            .timeout
+            .and_then(|s| Duration::try_from_secs_f64(s).ok())
+            .filter(|t| !t.is_zero())
+        {
+            secrets.timeout = timeout;
+        }
+        secrets.detect = section.detect.unwrap_or(true);
+        if let Some(argv) = section.command.clone().filter(|a| !a.is_empty()) {
+            secrets.source = Some(Arc::new(CommandSource::new(argv)));
+        }
+        info!(
+            detect = secrets.detect,
+            source = secrets.source.is_some(),
+            "secrets_from_config ok"
+        );
+        Ok(secrets)
+    }
+
+    pub fn set_source(&mut self, source: Arc<dyn SecretSource>) {
+        self.source = Some(source);
+    }
+
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    pub fn watches(&self) -> bool {
+        self.detect && self.prompt.is_some()
+    }
+
+    pub fn detects(&self, spec: &ShellSpec) -> bool {
+        self.detect && spec.host().is_some() && spec.on_connect().is_none()
+    }
+
+    pub fn prompt_in<'a>(&self, output: &'a str) -> Option<&'a str> {
+        if output.ends_with(['\r', '\n']) {
+            return None;
+        }
+        let last = output.rsplit(['\r', '\n']).next()?.trim_start();
+        self.prompt
+            .as_ref()
+            .is_some_and(|re| re.is_match(last))
+            .then_some(last)
+    }
+
+    pub async fn lookup(&self, name: &str, host: Option<&str>) -> Option<Secret> {
+        let source = self.source.as_ref()?;
+        match source.lookup(name, host).await {
+            Ok(secret) => secret,
+            Err(e) => {
+                warn!(name = name, ?e, "secrets_lookup failed");
+                None
+            }
+        }
+    }
+}
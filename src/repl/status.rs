@@ -0,0 +1,191 @@
+use std::{
+    collections::{BTreeSet, HashMap, VecDeque},
+    sync::{Arc, RwLock},
+    time::{Duration, Instant},
+};
+
+use tracing::{debug, info};
+
+const MAX_PENDING: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LastCommand {
+    pub exit_status: Option<i32>,
+    pub duration: Option<Duration>,
+}
+
+// A line psh sent for the user, with the audited text when the exit status
+// reported by the prompt hook should be written to the audit log too.
+struct Sent {
+    at: Instant,
+    audited: Option<String>,
+}
+
+#[derive(Default)]
+struct Inner {
+    running: BTreeSet<String>,
+    disconnected: BTreeSet<String>,
+    prompting: BTreeSet<String>,
+    pending: HashMap<String, VecDeque<Option<Sent>>>,
+    last: HashMap<String, LastCommand>,
+    cwd: HashMap<String, String>,
+}
+
+#[derive(Clone, Default)]
+pub struct SessionStatus {
+    inner: Arc<RwLock<Inner>>,
+}
+
+impl SessionStatus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn started(&self, name: &str) {
+        debug!(name = name, "session_status_started start");
+        if let Ok(mut w) = self.inner.write() {
+            w.disconnected.remove(name);
+            w.running.insert(name.to_string());
+        }
+        info!(name = name, "session_status_started ok");
+    }
+
+    pub fn stopped(&self, name: &str) {
+        if let Ok(mut w) = self.inner.write() {
+            w.running.remove(name);
+            w.pending.remove(name);
+            w.prompting.remove(name);
+            w.cwd.remove(name);
+        }
+    }
+
+    pub fn disconnected(&self, name: &str) {
+        debug!(name = name, "session_status_disconnected start");
+        if let Ok(mut w) = self.inner.write() {
+            w.running.remove(name);
+            w.pending.remove(name);
+            w.prompting.remove(name);
+            w.cwd.remove(name);
+            w.disconnected.insert(name.to_string());
+        }
+        info!(name = name, "session_status_disconnected ok");
+    }
+
+    pub fn sent(&self, name: &str) {
+        let at = Instant::now();
+        self.push_pending(name, Some(Sent { at, audited: None }));
+    }
+
+    pub fn sent_audited(&self, name: &str, line: &str) {
+        let at = Instant::now();
+        let audited = Some(line.to_string());
+        self.push_pending(name, Some(Sent { at, audited }));
+    }
+
+    // Lines psh sends on its own behalf; their prompt marks must not replace the
+    // user's last command.
+    pub fn sent_internal(&self, name: &str) {
+        self.push_pending(name, None);
+    }
+
+    fn push_pending(&self, name: &str, sent: Option<Sent>) {
+        if let Ok(mut w) = self.inner.write() {
+            let queue = w.pending.entry(name.to_string()).or_default();
+            if queue.len() >= MAX_PENDING {
+                queue.pop_front();
+            }
+            queue.push_back(sent);
+        }
+    }
+
+    // Returns the audited line the status belongs to, if there is one.
+    pub fn finished(&self, name: &str, exit_status: Option<i32>) -> Option<String> {
+        let mut w = self.inner.write().ok()?;
+        let (duration, audited) =
+            match w.pending.get_mut(name).and_then(VecDeque::pop_front) {
+                Some(Some(sent)) => (Some(sent.at.elapsed()), sent.audited),
+                Some(None) => return None,
+                None => (None, None),
+            };
+        w.last.insert(
+            name.to_string(),
+            LastCommand {
+                exit_status,
+                duration,
+            },
+        );
+        audited
+    }
+
+    pub fn record(&self, name: &str, exit_status: Option<i32>, duration: Duration) {
+        if let Ok(mut w) = self.inner.write() {
+            w.last.insert(
+                name.to_string(),
+                LastCommand {
+                    exit_status,
+                    duration: Some(duration),
+                },
+            );
+        }
+    }
+
+    pub fn set_prompting(&self, name: &str, prompting: bool) {
+        if let Ok(mut w) = self.inner.write() {
+            match prompting {
+                true => w.prompting.insert(name.to_string()),
+                false => w.prompting.remove(name),
+            };
+        }
+    }
+
+    pub fn is_prompting(&self, name: &str) -> bool {
+        self.inner
+            .read()
+            .map(|r| r.prompting.contains(name))
+            .unwrap_or(false)
+    }
+
+    // The next line for a session that shows a password prompt is the answer;
+    // taking the flag makes sure only that one line is treated as a secret.
+    pub fn take_prompting(&self, name: &str) -> bool {
+        self.inner
+            .write()
+            .map(|mut w| w.prompting.remove(name))
+            .unwrap_or(false)
+    }
+
+    pub fn set_cwd(&self, name: &str, path: String) {
+        debug!(name = name, path = %path, "session_status_set_cwd");
+        if let Ok(mut w) = self.inner.write() {
+            w.cwd.insert(name.to_string(), path);
+        }
+    }
+
+    pub fn cwd(&self, name: &str) -> Option<String> {
+        self.inner
+            .read()
+            .ok()
+            .and_then(|r| r.cwd.get(name).cloned())
+    }
+
+    pub fn last(&self, name: &str) -> Option<LastCommand> {
+        self.inner
+            .read()
+            .ok()
+            .and_then(|r| r.last.get(name).copied())
+    }
+
+    pub fn is_disconnected(&self, name: &str) -> bool {
+        self.inner
+            .read()
+            .map(|r| r.disconnected.contains(name))
+            .unwrap_or(false)
+    }
+
+    pub fn counts(&self) -> (usize, usize) {
+        self.inner
+            .read()
+            .map(|r| (r.running.len(), r.disconnected.len()))
+            .unwrap_or_default()
+    }
+}
@@ -0,0 +1,266 @@
+use std::collections::BTreeMap;
+
+use tracing::{debug, info, warn};
+
+use crate::error::{ReplRouterError, Result};
+
+const VAR_SIGIL: char = '$';
+const ESCAPE: char = '\\';
+const SINGLE_QUOTE: char = '\'';
+const DOUBLE_QUOTE: char = '"';
+
+#[derive(Debug, Default, Clone)]
+pub struct VarTable {
+    entries: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Segment {
+    Text(String),
+    Capture { target: String, command: String },
+}
+
+pub fn is_valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+impl VarTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set(&mut self, name: &str, value: String) -> Result<()> {
+        debug!(name = name, "var_set start");
+        if !is_valid_name(name) {
+            warn!(name = name, "var_set invalid name");
+            return Err(ReplRouterError::VarInvalidName {
+                name: name.to_string(),
+            }
+            .into());
+        }
+        if self.entries.insert(name.to_string(), value).is_some() {
+            info!(name = name, "var_set replaced");
+        }
+        info!(name = name, count = self.entries.len(), "var_set ok");
+        Ok(())
+    }
+
+    pub fn remove(&mut self, name: &str) -> bool {
+        debug!(name = name, "var_remove start");
+        let removed = self.entries.remove(name).is_some();
+        info!(name = name, removed = removed, "var_remove ok");
+        removed
+    }
+
+    pub fn get(&self, name: &str) -> Option<String> {
+        self.entries.get(name).cloned()
+    }
+
+    pub fn list(&self) -> Vec<(String, String)> {
+        self.entries
+            .iter()
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect()
+    }
+}
+
+fn closing_paren(input: &str) -> Option<usize> {
+    let mut depth = 0usize;
+    let mut quote: Option<char> = None;
+    let mut escaped = false;
+    for (idx, ch) in input.char_indices() {
+        if escaped {
+            escaped = false;
+            continue;
+        }
+        match (quote, ch) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(SINGLE_QUOTE), _) => {}
+            (_, ESCAPE) => escaped = true,
+            (Some(_), _) => {}
+            (None, '\'' | '"') => quote = Some(ch),
+            (None, '(') => depth += 1,
+            (None, ')') if depth == 0 => return Some(idx),
+            (None, ')') => depth -= 1,
+            (None, _) => {}
+        }
+    }
+    None
+}
+
+fn capture_target<'a>(
+    inner: &'a str,
+    is_target: &impl Fn(&str) -> bool,
+) -> Option<(&'a str, &'a str)> {
+    let (head, rest) = inner.split_once(':')?;
+    let name = head.trim();
+    (!name.is_empty() && !name.contains(char::is_whitespace) && is_target(name))
+        .then(|| (name, rest.trim()))
+}
+
+pub fn scan(
+    input: &str,
+    vars: &VarTable,
+    is_target: impl Fn(&str) -> bool,
+) -> Vec<Segment> {
+    debug!(input = input, "var_scan start");
+    let mut segments: Vec<Segment> = Vec::new();
+    let mut text = String::with_capacity(input.len());
+    let mut in_single = false;
+    let mut in_double = false;
+    let mut idx = 0usize;
+
+    while let Some(ch) = input[idx..].chars().next() {
+        let next = idx + ch.len_utf8();
+        if in_single {
+            in_single = ch != SINGLE_QUOTE;
+            text.push(ch);
+            idx = next;
+            continue;
+        }
+        match ch {
+            DOUBLE_QUOTE => {
+                in_double = !in_double;
+                text.push(ch);
+                idx = next;
+            }
+            SINGLE_QUOTE if !in_double => {
+                in_single = true;
+                text.push(ch);
+                idx = next;
+            }
+            ESCAPE => {
+                let escaped = input[next..].chars().next().map_or(0, char::len_utf8);
+                text.push_str(&input[idx..next + escaped]);
+                idx = next + escaped;
+            }
+            VAR_SIGIL => {
+                let rest = &input[next..];
+                if let Some(body) = rest.strip_prefix('(')
+                    && let Some(end) = closing_paren(body)
+                    && let Some((target, command)) =
+                        capture_target(&body[..end], &is_target)
+                {
+                    if !text.is_empty() {
+                        segments.push(Segment::Text(std::mem::take(&mut text)));
+                    }
+                    segments.push(Segment::Capture {
+                        target: target.to_string(),
+                        command: command.to_string(),
+                    });
+                    idx = next + 1 + end + 1;
+                    continue;
+                }
+                let (name, consumed) = match rest.strip_prefix('{') {
+                    Some(braced) => match braced.find('}') {
+                        Some(end) => (&braced[..end], end + 2),
+                        None => ("", 0),
+                    },
+                    None => {
+                        let end = rest
+                            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                            .unwrap_or(rest.len());
+                        (&rest[..end], end)
+                    }
+                };
+                match vars.get(name).filter(|_| is_valid_name(name)) {
+                    Some(value) => {
+                        text.push_str(&value);
+                        idx = next + consumed;
+                    }
+                    None => {
+                        text.push(ch);
+                        idx = next;
+                    }
+                }
+            }
+            _ => {
+                text.push(ch);
+                idx = next;
+            }
+        }
+    }
+    if !text.is_empty() || segments.is_empty() {
+        segments.push(Segment::Text(text));
+    }
+    info!(segments = segments.len(), "var_scan ok");
+    segments
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table() -> VarTable {
+        let mut vars = VarTable::new();
+        vars.set("HOST", "web1".to_string()).expect("set");
+        vars
+    }
+
+    fn segments(input: &str) -> Vec<Segment> {
+        scan(input, &table(), |name| name == "web")
+    }
+
+    fn text(input: &str) -> String {
+        match segments(input).as_slice() {
+            [Segment::Text(text)] => text.clone(),
+            other => panic!("expected plain text for {input:?}, got {other:?}"),
+        }
+    }
+
+    fn capture(target: &str, command: &str) -> Segment {
+        Segment::Capture {
+            target: target.to_string(),
+            command: command.to_string(),
+        }
+    }
+
+    #[test]
+    fn expands_known_vars_and_keeps_unknown_ones() {
+        assert_eq!(text("ping $HOST"), "ping web1");
+        assert_eq!(text("ping ${HOST}.lan"), "ping web1.lan");
+        assert_eq!(text(r#"echo "$HOST""#), r#"echo "web1""#);
+        assert_eq!(text("echo $NOPE ${NOPE} $"), "echo $NOPE ${NOPE} $");
+        assert_eq!(text(""), "");
+    }
+
+    #[test]
+    fn single_quotes_and_escapes_suppress_expansion() {
+        assert_eq!(
+            text("echo '$HOST $(web: hostname)'"),
+            "echo '$HOST $(web: hostname)'"
+        );
+        assert_eq!(text(r"echo \$HOST"), r"echo \$HOST");
+        assert_eq!(text(r"echo \$(web: hostname)"), r"echo \$(web: hostname)");
+        assert_eq!(text(r#"echo "it's $HOST""#), r#"echo "it's web1""#);
+    }
+
+    #[test]
+    fn captures_split_out_of_the_line() {
+        assert_eq!(
+            segments("ssh $(web: hostname) -p 22"),
+            [
+                Segment::Text("ssh ".to_string()),
+                capture("web", "hostname"),
+                Segment::Text(" -p 22".to_string()),
+            ]
+        );
+        assert_eq!(segments("$( web :  uptime )"), [capture("web", "uptime")]);
+        assert_eq!(segments("$(web: echo $(x))"), [capture("web", "echo $(x)")]);
+        assert_eq!(
+            segments("$(web: echo ')' \")\")"),
+            [capture("web", "echo ')' \")\"")]
+        );
+    }
+
+    #[test]
+    fn other_command_substitutions_stay_as_text() {
+        assert_eq!(text("echo $(foo: x)"), "echo $(foo: x)");
+        assert_eq!(text("echo $(date)"), "echo $(date)");
+        assert_eq!(text("echo $(web: unclosed"), "echo $(web: unclosed");
+    }
+}
@@ -0,0 +1,197 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    str::FromStr,
+    sync::{Arc, RwLock},
+};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::{Notify, broadcast};
+use tracing::{debug, info};
+
+use crate::shell::ShellEvent;
+
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum ShareMode {
+    #[default]
+    Read,
+    Write,
+}
+
+impl fmt::Display for ShareMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShareMode::Read => f.write_str("read"),
+            ShareMode::Write => f.write_str("write"),
+        }
+    }
+}
+
+impl FromStr for ShareMode {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "read" => Ok(ShareMode::Read),
+            "write" => Ok(ShareMode::Write),
+            _ => Err(()),
+        }
+    }
+}
+
+pub struct Joined {
+    pub id: u64,
+    pub mode: ShareMode,
+    pub screen: String,
+    pub events: broadcast::Receiver<ShellEvent>,
+    pub kicked: Arc<Notify>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Viewer {
+    pub id: u64,
+    pub session: String,
+    pub mode: ShareMode,
+    kicked: Arc<Notify>,
+}
+
+// What a viewer typed since its last Enter. Escapes and tabs (history, cursor
+// keys, completion) edit the line in ways that only the session's screen shows.
+// A hidden line was held back from the session because it may be a password.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TypedLine {
+    pub text: String,
+    pub edited: bool,
+    pub hidden: bool,
+}
+
+#[derive(Default)]
+struct Inner {
+    shares: HashMap<String, ShareMode>,
+    viewers: BTreeMap<u64, Viewer>,
+    lines: HashMap<u64, TypedLine>,
+    next_id: u64,
+}
+
+#[derive(Clone, Default)]
+pub struct ViewerSet {
+    inner: Arc<RwLock<Inner>>,
+}
+
+impl ViewerSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn share(&self, name: &str, mode: ShareMode) {
+        debug!(name = name, %mode, "viewer_set_share start");
+        if let Ok(mut w) = self.inner.write() {
+            w.shares.insert(name.to_string(), mode);
+        }
+        info!(name = name, %mode, "viewer_set_share ok");
+    }
+
+    pub fn unshare(&self, name: &str) -> usize {
+        debug!(name = name, "viewer_set_unshare start");
+        let kicked = self
+            .inner
+            .write()
+            .map(|mut w| {
+                w.shares.remove(name);
+                let ids: Vec<u64> = w
+                    .viewers
+                    .values()
+                    .filter(|v| v.session == name)
+                    .map(|v| v.id)
+                    .collect();
+                for id in &ids {
+                    w.lines.remove(id);
+                    if let Some(v) = w.viewers.remove(id) {
+                        v.kicked.notify_one();
+                    }
+                }
+                ids.len()
+            })
+            .unwrap_or(0);
+        info!(name = name, kicked = kicked, "viewer_set_unshare ok");
+        kicked
+    }
+
+    pub fn share_mode(&self, name: &str) -> Option<ShareMode> {
+        self.inner
+            .read()
+            .ok()
+            .and_then(|r| r.shares.get(name).copied())
+    }
+
+    pub fn join(&self, name: &str, mode: ShareMode) -> Option<(u64, Arc<Notify>)> {
+        let mut w = self.inner.write().ok()?;
+        w.next_id += 1;
+        let id = w.next_id;
+        let kicked = Arc::new(Notify::new());
+        w.viewers.insert(
+            id,
+            Viewer {
+                id,
+                session: name.to_string(),
+                mode,
+                kicked: kicked.clone(),
+            },
+        );
+        info!(name = name, id = id, %mode, "viewer_set_join ok");
+        Some((id, kicked))
+    }
+
+    pub fn leave(&self, id: u64) -> bool {
+        let removed = self
+            .inner
+            .write()
+            .map(|mut w| {
+                w.lines.remove(&id);
+                w.viewers.remove(&id).is_some()
+            })
+            .unwrap_or(false);
+        info!(id = id, removed = removed, "viewer_set_leave ok");
+        removed
+    }
+
+    pub fn feed(&self, id: u64, byte: u8, hide: bool) -> Option<TypedLine> {
+        let mut w = self.inner.write().ok()?;
+        let line = w.lines.entry(id).or_default();
+        line.hidden |= hide;
+        match byte {
+            b'\r' | b'\n' => return Some(std::mem::take(line)),
+            0x08 | 0x7f => {
+                line.text.pop();
+            }
+            0x03 | 0x15 => *line = TypedLine::default(),
+            b'\t' | 0x1b => line.edited = true,
+            b if b >= 0x20 => line.text.push(char::from(b)),
+            _ => {}
+        }
+        None
+    }
+
+    pub fn get(&self, id: u64) -> Option<Viewer> {
+        self.inner
+            .read()
+            .ok()
+            .and_then(|r| r.viewers.get(&id).cloned())
+    }
+
+    pub fn viewers(&self, name: &str) -> Vec<Viewer> {
+        self.inner
+            .read()
+            .map(|r| {
+                r.viewers
+                    .values()
+                    .filter(|v| v.session == name)
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
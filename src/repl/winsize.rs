@@ -0,0 +1,35 @@
+use std::sync::{Arc, RwLock};
+
+use tracing::{debug, info};
+
+#[derive(Clone)]
+pub struct TermSize {
+    size: Arc<RwLock<(u16, u16)>>,
+}
+
+impl TermSize {
+    pub fn new(cols: u16, rows: u16) -> Self {
+        Self {
+            size: Arc::new(RwLock::new((cols, rows))),
+        }
+    }
+
+    pub fn get(&self) -> (u16, u16) {
+        self.size.read().map(|r| *r).unwrap_or((0, 0))
+    }
+
+    pub fn set(&self, cols: u16, rows: u16) -> bool {
+        debug!(cols, rows, "term_size_set start");
+        let changed = self
+            .size
+            .write()
+            .map(|mut w| {
+                let changed = *w != (cols, rows);
+                *w = (cols, rows);
+                changed
+            })
+            .unwrap_or(false);
+        info!(changed = changed, "term_size_set ok");
+        changed
+    }
+}
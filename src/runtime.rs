@@ -1,6 +1,8 @@
+pub mod audit;
 pub mod bootstrap;
 pub mod config;
 pub mod logging;
 
+pub use audit::AuditLog;
 pub use bootstrap::bootstrap;
-pub use config::ReplSettings;
+pub use config::{RecordingSettings, ReplSettings};
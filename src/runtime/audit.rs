@@ -0,0 +1,163 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use regex::Regex;
+use serde::Serialize;
+use tracing::{debug, info, warn};
+
+use crate::{
+    error::{Result, RuntimeError},
+    shell::ShellSpec,
+};
+
+const REDACTED: &str = "[REDACTED]";
+const SECS_PER_DAY: u64 = 86_400;
+const PROCESS_KIND: &str = "process";
+
+#[derive(Debug, Serialize)]
+pub struct AuditRecord<'a> {
+    pub timestamp: String,
+    pub user: &'a str,
+    pub session: &'a str,
+    pub kind: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub host: Option<&'a str>,
+    pub line: String,
+    pub exit_status: Option<i32>,
+}
+
+#[derive(Clone)]
+pub struct AuditLog {
+    path: PathBuf,
+    user: String,
+    redactions: Arc<Vec<Regex>>,
+    file: Arc<Mutex<File>>,
+}
+
+impl AuditLog {
+    pub fn open(path: &Path, patterns: &[String]) -> Result<Self> {
+        debug!(path = %path.display(), patterns = patterns.len(), "audit_open start");
+        let io_err = |source| RuntimeError::AuditOpen {
+            path: path.display().to_string(),
+            source,
+        };
+        if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            fs::create_dir_all(dir).map_err(io_err)?;
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(io_err)?;
+        let redactions = patterns
+            .iter()
+            .map(|p| {
+                Regex::new(p).map_err(|source| RuntimeError::AuditPattern {
+                    pattern: p.clone(),
+                    source,
+                })
+            })
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        let user = users::get_current_username()
+            .map(|u| u.to_string_lossy().to_string())
+            .unwrap_or_else(|| users::get_current_uid().to_string());
+        info!(path = %path.display(), user = %user, "audit_open ok");
+        Ok(Self {
+            path: path.to_path_buf(),
+            user,
+            redactions: Arc::new(redactions),
+            file: Arc::new(Mutex::new(file)),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn redact(&self, line: &str) -> String {
+        let mut out = line.to_string();
+        for re in self.redactions.iter() {
+            out = if re.captures_len() > 1 {
+                re.replace_all(&out, |caps: &regex::Captures| {
+                    let base = caps.get(0).map_or(0, |m| m.start());
+                    let mut text = caps[0].to_string();
+                    let mut groups: Vec<_> = caps.iter().skip(1).flatten().collect();
+                    groups.sort_by_key(|g| std::cmp::Reverse(g.start()));
+                    let mut limit = usize::MAX;
+                    for g in groups.into_iter().filter(|g| !g.is_empty()) {
+                        if g.end() > limit {
+                            continue;
+                        }
+                        text.replace_range(g.start() - base..g.end() - base, REDACTED);
+                        limit = g.start();
+                    }
+                    text
+                })
+                .into_owned()
+            } else {
+                re.replace_all(&out, REDACTED).into_owned()
+            };
+        }
+        out
+    }
+
+    pub fn record(
+        &self,
+        session: &str,
+        spec: Option<&ShellSpec>,
+        line: &str,
+        exit_status: Option<i32>,
+    ) {
+        debug!(session = session, "audit_record start");
+        let record = AuditRecord {
+            timestamp: utc_timestamp(SystemTime::now()),
+            user: &self.user,
+            session,
+            kind: spec.map_or(PROCESS_KIND, ShellSpec::kind_name),
+            host: spec.and_then(ShellSpec::host),
+            line: self.redact(line),
+            exit_status,
+        };
+        let res = serde_json::to_string(&record)
+            .map_err(std::io::Error::from)
+            .and_then(|json| match self.file.lock() {
+                Ok(mut f) => writeln!(f, "{json}").and_then(|()| f.flush()),
+                Err(e) => Err(std::io::Error::other(e.to_string())),
+            });
+        match res {
+            Ok(()) => info!(session = session, "audit_record ok"),
+            Err(e) => {
+                warn!(path = %self.path.display(), ?e, "audit_record write failed")
+            }
+        }
+    }
+}
+
+fn utc_timestamp(now: SystemTime) -> String {
+    let since = now.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = since.as_secs();
+    let (days, rem) = (secs / SECS_PER_DAY, secs % SECS_PER_DAY);
+
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}.{:03}Z",
+        rem / 3_600,
+        rem % 3_600 / 60,
+        rem % 60,
+        since.subsec_millis()
+    )
+}
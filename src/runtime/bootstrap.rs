@@ -1,11 +1,14 @@
-use tracing::{debug, info, warn};
+use std::path::PathBuf;
+
+use tracing::{debug, error, info, warn};
 
 use crate::{
-    builtins::BuiltinContext,
+    builtins::{BuiltinContext, BuiltinSet},
     error::Result,
     registry::{self, Registry},
-    repl::Router,
+    repl::{Policy, Router, Secrets},
     runtime::{
+        AuditLog,
         config::{self, PshConfig, ReplSettings, ShellsSection},
         logging::{LogControl, init_logging_early, reconfigure_logging_path},
     },
@@ -17,19 +20,35 @@ const DEFAULT_SHELL_PATH: &str = "/usr/bin/bash";
 
 pub struct AppParts {
     pub cfg: PshConfig,
+    pub control_socket: Option<PathBuf>,
     pub router: Router,
     pub default_mode: String,
     pub log_control: LogControl,
     pub repl_settings: ReplSettings,
 }
 
-fn build_base_registry() -> Registry {
+fn build_base_registry(builtins: &BuiltinSet) -> Registry {
     debug!("build_base_registry start");
-    let r = Registry::with_builtins();
+    let r = Registry::with_builtins(builtins);
     info!("build_base_registry ok");
     r
 }
 
+fn open_audit_log(cfg: &PshConfig) -> Option<AuditLog> {
+    debug!("open_audit_log start");
+    let settings = config::audit_settings_from_config(cfg)?;
+    match AuditLog::open(&settings.file, &settings.redact) {
+        Ok(audit) => {
+            info!(path = %audit.path().display(), "open_audit_log ok");
+            Some(audit)
+        }
+        Err(e) => {
+            warn!(?e, "open_audit_log failed");
+            None
+        }
+    }
+}
+
 fn apply_shells_from_config(cfg: &PshConfig, router: &mut Router) {
     debug!("apply_shells_from_config start");
     if let Some(ShellsSection { catalog, .. }) = &cfg.shells
@@ -42,6 +61,89 @@ fn apply_shells_from_config(cfg: &PshConfig, router: &mut Router) {
     }
 }
 
+fn apply_aliases_from_config(cfg: &PshConfig, router: &mut Router) {
+    debug!("apply_aliases_from_config start");
+    if let Some(aliases) = &cfg.aliases {
+        for (name, body) in aliases {
+            match router.set_alias(name, body.lines()) {
+                Ok(()) => info!(name = %name, "apply_aliases_from_config entry"),
+                Err(e) => {
+                    warn!(name = %name, ?e, "apply_aliases_from_config entry failed")
+                }
+            }
+        }
+    }
+}
+
+fn apply_vars_from_config(cfg: &PshConfig, router: &mut Router) {
+    debug!("apply_vars_from_config start");
+    if let Some(vars) = &cfg.vars {
+        for (name, value) in vars {
+            match router.set_var(name, value.clone()) {
+                Ok(()) => info!(name = %name, "apply_vars_from_config entry"),
+                Err(e) => {
+                    warn!(name = %name, ?e, "apply_vars_from_config entry failed")
+                }
+            }
+        }
+    }
+}
+
+pub(crate) fn apply_config(
+    cfg: &PshConfig,
+    router: &mut Router,
+) -> Result<ReplSettings> {
+    debug!("apply_config start");
+    let policy = Policy::from_config(cfg.policy.as_ref()).inspect_err(|e| {
+        error!(
+            ?e,
+            "policy_from_config failed; refusing to start without guardrails"
+        )
+    })?;
+    router.set_policy(policy);
+    router.set_recording_settings(config::recording_settings_from_config(cfg));
+    router.set_audit_log(open_audit_log(cfg));
+    match Secrets::from_config(cfg.secrets.as_ref()) {
+        Ok(secrets) => router.set_secrets(secrets),
+        Err(e) => warn!(?e, "secrets_from_config failed; using defaults"),
+    }
+    apply_shells_from_config(cfg, router);
+    apply_aliases_from_config(cfg, router);
+    apply_vars_from_config(cfg, router);
+    let repl_settings = config::repl_settings_from_config(cfg);
+    router.set_repl_settings(repl_settings.clone());
+    info!("apply_config ok");
+    Ok(repl_settings)
+}
+
+pub(crate) fn choose_default_mode(cfg: &PshConfig, router: &mut Router) -> String {
+    debug!("choose_default_mode start");
+    let default_mode = cfg
+        .shells
+        .as_ref()
+        .and_then(|s| s.default_shell.clone())
+        .filter(|name| router.set_default_mode(name))
+        .or_else(|| {
+            config::login_shell_program_name().filter(|n| router.set_default_mode(n))
+        })
+        .unwrap_or_else(|| {
+            if router.set_default_mode(DEFAULT_SHELL_NAME) {
+                DEFAULT_SHELL_NAME.to_string()
+            } else {
+                warn!("fallback default mode set failed; using literal name");
+                DEFAULT_SHELL_NAME.to_string()
+            }
+        });
+    info!(default = %default_mode, "default mode chosen");
+    router.set_current_mode(&default_mode);
+    default_mode
+}
+
+pub(crate) async fn start_shells(router: &mut Router) {
+    eager_start_registered_shells(router).await;
+    ensure_fallback_bash(router).await;
+}
+
 async fn eager_start_registered_shells(router: &mut Router) {
     debug!("eager_start_registered_shells start");
     for (name, entry) in router.list_entries() {
@@ -64,6 +166,9 @@ async fn ensure_fallback_bash(router: &mut Router) {
             DEFAULT_SHELL_NAME.to_string(),
             registry::Entry::Shell(ShellSpec::Local {
                 program: DEFAULT_SHELL_PATH.to_string(),
+                tags: Vec::new(),
+                readonly: false,
+                on_connect: None,
             }),
         );
         info!("fallback bash registered");
@@ -93,38 +198,19 @@ pub async fn bootstrap(cols: u16, rows: u16, verbosity: u8) -> Result<AppParts>
         .map(|s| s.into());
     reconfigure_logging_path(&mut log_control, log_path);
 
-    let registry = build_base_registry();
-    let mut router = Router::new(registry, cols, rows);
+    let builtins = BuiltinSet::with_defaults();
+    let registry = build_base_registry(&builtins);
+    let mut router = Router::new(registry, builtins, cols, rows);
     info!("router initialized");
 
-    apply_shells_from_config(&cfg, &mut router);
-    eager_start_registered_shells(&mut router).await;
-    ensure_fallback_bash(&mut router).await;
-
-    let repl_settings = config::repl_settings_from_config(&cfg);
-
-    let default_mode = cfg
-        .shells
-        .as_ref()
-        .and_then(|s| s.default_shell.clone())
-        .filter(|name| router.set_default_mode(name))
-        .or_else(|| {
-            config::login_shell_program_name().filter(|n| router.set_default_mode(n))
-        })
-        .unwrap_or_else(|| {
-            if router.set_default_mode(DEFAULT_SHELL_NAME) {
-                DEFAULT_SHELL_NAME.to_string()
-            } else {
-                warn!("fallback default mode set failed; using literal name");
-                DEFAULT_SHELL_NAME.to_string()
-            }
-        });
-    info!(default = %default_mode, "default mode chosen");
-
-    router.set_current_mode(&default_mode);
+    let repl_settings = apply_config(&cfg, &mut router)?;
+    start_shells(&mut router).await;
+    router.watch_term_size();
+    let default_mode = choose_default_mode(&cfg, &mut router);
 
     info!("bootstrap ok");
     Ok(AppParts {
+        control_socket: config::control_socket_from_config(&cfg),
         cfg,
         router,
         default_mode,
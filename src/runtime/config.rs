@@ -2,6 +2,7 @@ use std::{
     collections::HashMap,
     env, fs,
     path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
 };
 
 use directories::BaseDirs;
@@ -14,6 +15,17 @@ use users::{self, os::unix::UserExt};
 use crate::shell::ShellSpec;
 
 const MAX_FUNCTION_KEY: u8 = 24;
+const RECORD_ALL_SHELLS: &str = "*";
+const RECORDING_EXTENSION: &str = "cast";
+const DEFAULT_AUDIT_FILE: &str = "logs/audit.log";
+const DEFAULT_CONTROL_SOCKET: &str = "control.sock";
+const DEFAULT_ATTACH_SOCKET: &str = "attach.sock";
+const DEFAULT_AUDIT_REDACTIONS: &[&str] =
+    &[r"(?i)(?:password|passwd|secret|token|api[_-]?key)\s*[=:]\s*(\S+)"];
+const DEFAULT_PROMPT: &str = "psh> {mode}: ";
+const DEFAULT_RIGHT_PROMPT: &str = "{host} {cwd} {status} {duration} {sessions}";
+const DEFAULT_VI_INSERT_INDICATOR: &str = "";
+const DEFAULT_VI_NORMAL_INDICATOR: &str = "[N] ";
 const DEFAULT_MENU_KEY: (KeyCode, KeyModifiers) =
     (KeyCode::Char('g'), KeyModifiers::CONTROL);
 
@@ -23,11 +35,68 @@ pub enum EditMode {
     Vi,
 }
 
+impl EditMode {
+    pub fn name(&self) -> &'static str {
+        match self {
+            EditMode::Emacs => "emacs",
+            EditMode::Vi => "vi",
+        }
+    }
+}
+
 #[derive(Debug, Deserialize, Default, Clone)]
 pub struct PshConfig {
     pub logging: Option<LoggingSection>,
     pub shells: Option<ShellsSection>,
     pub repl: Option<ReplSection>,
+    pub aliases: Option<HashMap<String, AliasBody>>,
+    pub vars: Option<HashMap<String, String>>,
+    pub recording: Option<RecordingSection>,
+    pub audit: Option<AuditSection>,
+    pub policy: Option<PolicySection>,
+    pub control: Option<ControlSection>,
+    pub secrets: Option<SecretsSection>,
+}
+
+#[derive(Debug, Deserialize, Default, Clone)]
+pub struct PolicySection {
+    pub max_fanout: Option<usize>,
+    pub rules: Option<Vec<PolicyRuleSection>>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct PolicyRuleSection {
+    pub name: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub patterns: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, Default, Clone)]
+pub struct ControlSection {
+    pub enabled: Option<bool>,
+    pub socket: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Default, Clone)]
+pub struct SecretsSection {
+    pub detect: Option<bool>,
+    pub prompt: Option<String>,
+    pub command: Option<Vec<String>>,
+    pub timeout: Option<f64>,
+}
+
+#[derive(Debug, Deserialize, Default, Clone)]
+pub struct AuditSection {
+    pub enabled: Option<bool>,
+    pub file: Option<String>,
+    pub redact: Option<Vec<String>>,
+}
+
+#[derive(Debug, Deserialize, Default, Clone)]
+pub struct RecordingSection {
+    pub dir: Option<String>,
+    pub shells: Option<Vec<String>>,
 }
 
 #[derive(Debug, Deserialize, Default, Clone)]
@@ -42,11 +111,32 @@ pub struct ShellsSection {
     pub catalog: Option<HashMap<String, ShellSpec>>,
 }
 
+#[derive(Debug, Deserialize, Clone)]
+#[serde(untagged)]
+pub enum AliasBody {
+    Line(String),
+    Lines(Vec<String>),
+}
+
+impl AliasBody {
+    pub fn lines(&self) -> Vec<String> {
+        match self {
+            AliasBody::Line(s) => s.lines().map(str::to_string).collect(),
+            AliasBody::Lines(v) => v.clone(),
+        }
+    }
+}
+
 #[derive(Debug, Deserialize, Default, Clone)]
 pub struct ReplSection {
     pub menu_key: Option<String>,
     pub colors: Option<ReplColors>,
     pub edit_mode: Option<String>,
+    pub prompt: Option<String>,
+    pub right_prompt: Option<String>,
+    pub vi_insert_indicator: Option<String>,
+    pub vi_normal_indicator: Option<String>,
+    pub resize_all: Option<bool>,
 }
 
 #[derive(Debug, Deserialize, Default, Clone)]
@@ -56,6 +146,7 @@ pub struct ReplColors {
     pub local: Option<String>,
     pub remote: Option<String>,
     pub unknown: Option<String>,
+    pub disconnected: Option<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -67,6 +158,12 @@ pub struct ReplSettings {
     pub color_local: Color,
     pub color_remote: Color,
     pub color_unknown: Color,
+    pub color_disconnected: Color,
+    pub prompt: String,
+    pub right_prompt: String,
+    pub vi_insert_indicator: String,
+    pub vi_normal_indicator: String,
+    pub resize_all: bool,
 }
 
 fn parse_color(name: &str) -> Option<Color> {
@@ -167,6 +264,29 @@ fn parse_menu_key(s: &str) -> Option<(KeyCode, KeyModifiers)> {
     code.map(|c| (c, mods))
 }
 
+pub fn describe_key((code, mods): (KeyCode, KeyModifiers)) -> String {
+    let mut parts: Vec<String> = Vec::new();
+    for (flag, label) in [
+        (KeyModifiers::CONTROL, "Ctrl"),
+        (KeyModifiers::ALT, "Alt"),
+        (KeyModifiers::SHIFT, "Shift"),
+        (KeyModifiers::SUPER, "Super"),
+        (KeyModifiers::META, "Meta"),
+    ] {
+        if mods.contains(flag) {
+            parts.push(label.to_string());
+        }
+    }
+    let key = match code {
+        KeyCode::Char(' ') => "Space".to_string(),
+        KeyCode::Char(c) => c.to_string(),
+        KeyCode::F(n) => format!("F{n}"),
+        other => format!("{other:?}"),
+    };
+    parts.push(key);
+    parts.join("+")
+}
+
 fn parse_edit_mode(s: &str) -> Option<EditMode> {
     let t = s.trim().to_ascii_lowercase();
     match t.as_str() {
@@ -198,6 +318,7 @@ pub fn repl_settings_from_config(cfg: &PshConfig) -> ReplSettings {
         local: Some("Green".into()),
         remote: Some("Blue".into()),
         unknown: Some("Red".into()),
+        disconnected: Some("DarkGray".into()),
     };
     let colors = repl.colors.unwrap_or(defaults);
 
@@ -226,6 +347,11 @@ pub fn repl_settings_from_config(cfg: &PshConfig) -> ReplSettings {
         .as_deref()
         .and_then(parse_color)
         .unwrap_or(Color::Red);
+    let color_disconnected = colors
+        .disconnected
+        .as_deref()
+        .and_then(parse_color)
+        .unwrap_or(Color::DarkGray);
 
     info!("repl_settings_from_config ok");
     ReplSettings {
@@ -236,6 +362,120 @@ pub fn repl_settings_from_config(cfg: &PshConfig) -> ReplSettings {
         color_local,
         color_remote,
         color_unknown,
+        color_disconnected,
+        prompt: repl.prompt.unwrap_or_else(|| DEFAULT_PROMPT.to_string()),
+        right_prompt: repl
+            .right_prompt
+            .unwrap_or_else(|| DEFAULT_RIGHT_PROMPT.to_string()),
+        vi_insert_indicator: repl
+            .vi_insert_indicator
+            .unwrap_or_else(|| DEFAULT_VI_INSERT_INDICATOR.to_string()),
+        vi_normal_indicator: repl
+            .vi_normal_indicator
+            .unwrap_or_else(|| DEFAULT_VI_NORMAL_INDICATOR.to_string()),
+        resize_all: repl.resize_all.unwrap_or(false),
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RecordingSettings {
+    pub dir: PathBuf,
+    pub shells: Vec<String>,
+}
+
+impl Default for RecordingSettings {
+    fn default() -> Self {
+        Self {
+            dir: psh_home().join("recordings"),
+            shells: Vec::new(),
+        }
+    }
+}
+
+impl RecordingSettings {
+    pub fn records(&self, name: &str) -> bool {
+        self.shells
+            .iter()
+            .any(|s| s == name || s == RECORD_ALL_SHELLS)
+    }
+
+    pub fn path_for(&self, name: &str) -> PathBuf {
+        let stamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        self.dir
+            .join(format!("{name}-{stamp}.{RECORDING_EXTENSION}"))
+    }
+}
+
+pub fn recording_settings_from_config(cfg: &PshConfig) -> RecordingSettings {
+    debug!("recording_settings_from_config start");
+    let section = cfg.recording.clone().unwrap_or_default();
+    let mut settings = RecordingSettings::default();
+    if let Some(dir) = section.dir {
+        settings.dir = PathBuf::from(dir);
+    }
+    settings.shells = section.shells.unwrap_or_default();
+    info!(
+        dir = %settings.dir.display(),
+        shells = settings.shells.len(),
+        "recording_settings_from_config ok"
+    );
+    settings
+}
+
+#[derive(Debug, Clone)]
+pub struct AuditSettings {
+    pub file: PathBuf,
+    pub redact: Vec<String>,
+}
+
+pub fn audit_settings_from_config(cfg: &PshConfig) -> Option<AuditSettings> {
+    debug!("audit_settings_from_config start");
+    let section = cfg.audit.clone().unwrap_or_default();
+    if section.enabled == Some(false) {
+        info!("audit_settings_from_config disabled");
+        return None;
+    }
+    let settings = AuditSettings {
+        file: PathBuf::from(section.file.as_deref().unwrap_or(DEFAULT_AUDIT_FILE)),
+        redact: section.redact.unwrap_or_else(|| {
+            DEFAULT_AUDIT_REDACTIONS
+                .iter()
+                .map(|p| p.to_string())
+                .collect()
+        }),
+    };
+    info!(file = %settings.file.display(), "audit_settings_from_config ok");
+    Some(settings)
+}
+
+pub fn control_socket_from_config(cfg: &PshConfig) -> Option<PathBuf> {
+    debug!("control_socket_from_config start");
+    let section = cfg.control.clone().unwrap_or_default();
+    if section.enabled == Some(false) {
+        info!("control_socket_from_config disabled");
+        return None;
+    }
+    let path = section
+        .socket
+        .map(PathBuf::from)
+        .unwrap_or_else(|| psh_home().join(DEFAULT_CONTROL_SOCKET));
+    info!(path = %path.display(), "control_socket_from_config ok");
+    Some(path)
+}
+
+pub fn default_attach_socket() -> PathBuf {
+    psh_home().join(DEFAULT_ATTACH_SOCKET)
+}
+
+pub fn expect_script_path(path: &str) -> PathBuf {
+    let path = Path::new(path);
+    if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        psh_home().join(path)
     }
 }
 
@@ -281,16 +521,21 @@ pub fn load_config() -> (PshConfig, PathBuf) {
     }
 }
 
-fn config_path() -> PathBuf {
-    debug!("config_path start");
-    let path = if let Ok(p) = env::var("PSH_CONFIG") {
-        PathBuf::from(p)
-    } else if let Some(base) = BaseDirs::new() {
-        base.home_dir().join(".psh").join("config.toml")
+fn psh_home() -> PathBuf {
+    if let Some(base) = BaseDirs::new() {
+        base.home_dir().join(".psh")
     } else if let Ok(home) = env::var("HOME") {
-        Path::new(&home).join(".psh").join("config.toml")
+        Path::new(&home).join(".psh")
     } else {
-        PathBuf::from(".psh/config.toml")
+        PathBuf::from(".psh")
+    }
+}
+
+fn config_path() -> PathBuf {
+    debug!("config_path start");
+    let path = match env::var("PSH_CONFIG") {
+        Ok(p) => PathBuf::from(p),
+        Err(_) => psh_home().join("config.toml"),
     };
     info!(path = %path.display(), "config_path ok");
     path
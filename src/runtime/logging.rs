@@ -35,10 +35,13 @@ pub fn init_logging_early(verbosity: u8) -> LogControl {
     let fmt_layer = fmt::layer().with_writer(writer).with_ansi(false).boxed();
     let (fmt_layer, fmt_handle) = reload::Layer::new(fmt_layer);
 
-    tracing_subscriber::registry()
+    if let Err(e) = tracing_subscriber::registry()
         .with(fmt_layer)
         .with(filter)
-        .init();
+        .try_init()
+    {
+        warn!(?e, "init_logging_early subscriber already set");
+    }
 
     info!("init_logging_early ok");
 
@@ -1,27 +1,89 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
 use async_trait::async_trait;
 use tokio::sync::broadcast;
+use tracing::{debug, info};
 
-use crate::error::Result;
+use crate::error::{Result, ShellError, SyncError};
 
 pub mod cmd;
 pub mod event;
+pub mod expect;
 pub mod factory;
+pub mod integration;
 pub mod pty;
+pub mod record;
+pub mod screen;
+pub mod secret;
 pub mod spec;
+pub mod vt;
 
 #[cfg(feature = "mock-shell")]
 pub mod mock;
 
 pub use cmd::ShellCmd;
 pub use event::ShellEvent;
+pub use factory::{PtyFactory, ShellFactory};
 pub use pty::PtyShell;
+pub use screen::Screen;
+pub use secret::Secret;
 pub use spec::ShellSpec;
 
 #[async_trait]
 pub trait Shell: Send + Sync {
+    fn name(&self) -> &str;
     async fn send_line(&self, line: String) -> Result<()>;
     async fn send_bytes(&self, bytes: Vec<u8>) -> Result<()>;
     async fn resize(&self, cols: u16, rows: u16) -> Result<()>;
     async fn shutdown(&self) -> Result<()>;
     fn subscribe(&self) -> broadcast::Receiver<ShellEvent>;
+    fn size(&self) -> (u16, u16);
+
+    async fn send_secret(&self, secret: &Secret) -> Result<()> {
+        let mut bytes = secret.expose().as_bytes().to_vec();
+        bytes.push(b'\n');
+        self.send_bytes(bytes).await
+    }
+
+    fn screen(&self) -> Option<Arc<Mutex<Screen>>> {
+        None
+    }
+
+    fn render_screen(&self) -> Result<String> {
+        debug!(shell = %self.name(), "render_screen start");
+        let Some(screen) = self.screen() else {
+            return Err(unsupported(self.name(), "a screen"));
+        };
+        let screen = screen.lock().map_err(|e| {
+            ShellError::from(SyncError::MutexPoison {
+                context: format!("screen lock poisoned: {e}"),
+            })
+        })?;
+        let out = screen.render();
+        info!(shell = %self.name(), len = out.len(), "render_screen ok");
+        Ok(out)
+    }
+
+    fn start_recording(&self, _path: &Path) -> Result<PathBuf> {
+        Err(unsupported(self.name(), "recording"))
+    }
+
+    fn stop_recording(&self) -> Option<PathBuf> {
+        None
+    }
+
+    fn recording_path(&self) -> Option<PathBuf> {
+        None
+    }
+}
+
+fn unsupported(name: &str, what: &'static str) -> crate::PshError {
+    ShellError::Unsupported {
+        name: name.to_string(),
+        what,
+    }
+    .into()
 }
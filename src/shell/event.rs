@@ -1,5 +1,11 @@
+use std::sync::Arc;
+
+use crate::shell::vt::TermEvent;
+
 #[derive(Clone, Debug)]
 pub enum ShellEvent {
+    Raw(Arc<[u8]>),
     Output(String),
+    Term(TermEvent),
     Exited(String),
 }
@@ -0,0 +1,289 @@
+use std::{fs, future::Future, path::Path, pin::Pin, time::Duration};
+
+use async_trait::async_trait;
+use regex::Regex;
+use tokio::{
+    sync::broadcast::{self, error::RecvError},
+    time::{self, Instant},
+};
+use tracing::{debug, info, warn};
+
+use crate::{
+    error::{ExpectError, Result},
+    shell::{Shell, ShellEvent},
+};
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+const MAX_BUFFER: usize = 64 * 1024;
+const COMMENT: char = '#';
+
+#[async_trait]
+pub trait LineSink: Send + Sync {
+    async fn send(&self, line: String) -> Result<()>;
+}
+
+#[derive(Debug, Clone)]
+enum Step {
+    Send(String),
+    Expect {
+        pattern: Regex,
+        timeout: Duration,
+    },
+    If {
+        pattern: Regex,
+        timeout: Duration,
+        then: Vec<Step>,
+        otherwise: Vec<Step>,
+    },
+    Sleep(Duration),
+    Fail(String),
+}
+
+struct Block {
+    line: usize,
+    pattern: Regex,
+    timeout: Duration,
+    then: Vec<Step>,
+    otherwise: Option<Vec<Step>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Script {
+    steps: Vec<Step>,
+}
+
+fn parse_error(line: usize, reason: impl Into<String>) -> ExpectError {
+    ExpectError::Parse {
+        line,
+        reason: reason.into(),
+    }
+}
+
+fn parse_secs(line: usize, text: &str) -> std::result::Result<Duration, ExpectError> {
+    text.parse::<f64>()
+        .ok()
+        .and_then(|s| Duration::try_from_secs_f64(s).ok())
+        .ok_or_else(|| parse_error(line, format!("invalid seconds `{text}`")))
+}
+
+fn parse_expect(
+    line: usize,
+    rest: &str,
+) -> std::result::Result<(Regex, Duration), ExpectError> {
+    let body = rest
+        .strip_prefix('/')
+        .ok_or_else(|| parse_error(line, "expected /regex/ after `expect`"))?;
+    let end = body
+        .rfind('/')
+        .ok_or_else(|| parse_error(line, "unterminated /regex/"))?;
+    let pattern = Regex::new(&body[..end])
+        .map_err(|e| parse_error(line, format!("invalid regex: {e}")))?;
+    let timeout = match body[end + 1..].trim() {
+        "" => DEFAULT_TIMEOUT,
+        secs => parse_secs(line, secs)?,
+    };
+    Ok((pattern, timeout))
+}
+
+impl Script {
+    pub fn parse(source: &str) -> Result<Self> {
+        debug!(len = source.len(), "expect_script_parse start");
+        let mut root: Vec<Step> = Vec::new();
+        let mut blocks: Vec<Block> = Vec::new();
+        for (idx, raw) in source.lines().enumerate() {
+            let line = idx + 1;
+            let text = raw.trim();
+            if text.is_empty() || text.starts_with(COMMENT) {
+                continue;
+            }
+            let (word, rest) = text.split_once(' ').unwrap_or((text, ""));
+            let rest = rest.trim_start();
+            let step = match word {
+                "send" => {
+                    Step::Send(raw.trim_start()[word.len()..].trim_start().to_string())
+                }
+                "expect" => {
+                    let (pattern, timeout) = parse_expect(line, rest)?;
+                    Step::Expect { pattern, timeout }
+                }
+                "sleep" => Step::Sleep(parse_secs(line, rest)?),
+                "fail" => Step::Fail(rest.to_string()),
+                "if" => {
+                    let cond = rest.strip_prefix("expect").ok_or_else(|| {
+                        parse_error(line, "expected `if expect /regex/`")
+                    })?;
+                    let (pattern, timeout) = parse_expect(line, cond.trim_start())?;
+                    blocks.push(Block {
+                        line,
+                        pattern,
+                        timeout,
+                        then: Vec::new(),
+                        otherwise: None,
+                    });
+                    continue;
+                }
+                "else" => {
+                    match blocks.last_mut() {
+                        Some(b) if b.otherwise.is_none() => {
+                            b.otherwise = Some(Vec::new())
+                        }
+                        Some(_) => {
+                            return Err(parse_error(line, "second `else`").into());
+                        }
+                        None => {
+                            return Err(parse_error(line, "`else` without `if`").into());
+                        }
+                    }
+                    continue;
+                }
+                "end" => {
+                    let b = blocks
+                        .pop()
+                        .ok_or_else(|| parse_error(line, "`end` without `if`"))?;
+                    Step::If {
+                        pattern: b.pattern,
+                        timeout: b.timeout,
+                        then: b.then,
+                        otherwise: b.otherwise.unwrap_or_default(),
+                    }
+                }
+                other => {
+                    return Err(parse_error(
+                        line,
+                        format!("unknown command `{other}`"),
+                    )
+                    .into());
+                }
+            };
+            let target = match blocks.last_mut() {
+                Some(Block {
+                    otherwise: Some(steps),
+                    ..
+                }) => steps,
+                Some(b) => &mut b.then,
+                None => &mut root,
+            };
+            target.push(step);
+        }
+        if let Some(b) = blocks.last() {
+            return Err(parse_error(b.line, "`if` without `end`").into());
+        }
+        info!(steps = root.len(), "expect_script_parse ok");
+        Ok(Self { steps: root })
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        debug!(path = %path.display(), "expect_script_load start");
+        let source = fs::read_to_string(path).map_err(|source| ExpectError::Read {
+            path: path.display().to_string(),
+            source,
+        })?;
+        let script = Self::parse(&source)?;
+        info!(path = %path.display(), "expect_script_load ok");
+        Ok(script)
+    }
+
+    pub async fn run(
+        &self,
+        shell: &dyn Shell,
+        sink: &dyn LineSink,
+        seed: Option<String>,
+    ) -> Result<()> {
+        debug!(shell = %shell.name(), "expect_script_run start");
+        let mut runner = Runner {
+            events: shell.subscribe(),
+            shell,
+            sink,
+            buffer: seed.unwrap_or_default(),
+        };
+        runner.run(&self.steps).await?;
+        info!(shell = %shell.name(), "expect_script_run ok");
+        Ok(())
+    }
+}
+
+struct Runner<'a> {
+    shell: &'a dyn Shell,
+    sink: &'a dyn LineSink,
+    events: broadcast::Receiver<ShellEvent>,
+    buffer: String,
+}
+
+impl<'a> Runner<'a> {
+    fn run<'s>(
+        &'s mut self,
+        steps: &'s [Step],
+    ) -> Pin<Box<dyn Future<Output = Result<()>> + Send + 's>>
+    where
+        'a: 's,
+    {
+        Box::pin(async move {
+            for step in steps {
+                match step {
+                    Step::Send(line) => self.sink.send(line.clone()).await?,
+                    Step::Expect { pattern, timeout } => {
+                        if !self.wait_for(pattern, *timeout).await? {
+                            warn!(shell = %self.shell.name(), %pattern, "expect timed out");
+                            return Err(ExpectError::Timeout {
+                                pattern: pattern.to_string(),
+                                secs: timeout.as_secs_f64(),
+                            }
+                            .into());
+                        }
+                    }
+                    Step::If {
+                        pattern,
+                        timeout,
+                        then,
+                        otherwise,
+                    } => match self.wait_for(pattern, *timeout).await? {
+                        true => self.run(then).await?,
+                        false => self.run(otherwise).await?,
+                    },
+                    Step::Sleep(delay) => time::sleep(*delay).await,
+                    Step::Fail(message) => {
+                        return Err(ExpectError::Failed {
+                            message: message.clone(),
+                        }
+                        .into());
+                    }
+                }
+            }
+            Ok(())
+        })
+    }
+
+    async fn wait_for(&mut self, pattern: &Regex, timeout: Duration) -> Result<bool> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if let Some(m) = pattern.find(&self.buffer) {
+                debug!(shell = %self.shell.name(), %pattern, "expect matched");
+                self.buffer.drain(..m.end());
+                return Ok(true);
+            }
+            match time::timeout_at(deadline, self.events.recv()).await {
+                Err(_) => return Ok(false),
+                Ok(Ok(ShellEvent::Output(text))) => {
+                    self.buffer.push_str(&text);
+                    if self.buffer.len() > MAX_BUFFER {
+                        let mut cut = self.buffer.len() - MAX_BUFFER;
+                        while !self.buffer.is_char_boundary(cut) {
+                            cut += 1;
+                        }
+                        self.buffer.drain(..cut);
+                    }
+                }
+                Ok(Ok(ShellEvent::Exited(_))) | Ok(Err(RecvError::Closed)) => {
+                    return Err(ExpectError::Closed {
+                        name: self.shell.name().to_string(),
+                    }
+                    .into());
+                }
+                Ok(Ok(_)) => {}
+                Ok(Err(RecvError::Lagged(n))) => {
+                    warn!(shell = %self.shell.name(), skipped = n, "expect output lagged");
+                }
+            }
+        }
+    }
+}
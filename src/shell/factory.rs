@@ -1,17 +1,56 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use regex::Regex;
 use tracing::{debug, info, instrument};
 
 use crate::{
     error::Result,
-    shell::{PtyShell, ShellSpec, spec::RemoteBackend},
+    shell::{PtyShell, Shell, ShellSpec, spec::RemoteBackend},
 };
 
 const SSH_PROGRAM: &str = "ssh";
 const SSH_PTY_FLAG: &str = "-tt";
 const SSH_PORT_FLAG: &str = "-p";
+const SSH_JUMP_FLAG: &str = "-J";
 const TELNET_PROGRAM: &str = "telnet";
+const JUMP_FAILURES: &[&str] = &[
+    "stdio forwarding failed",
+    "Connection closed by UNKNOWN port 65535",
+    "kex_exchange_identification",
+];
+const SSH_HOST_ERROR: &str =
+    r"ssh: (?:connect to host (\S+) port \d+|Could not resolve hostname ([^:\s]+)):";
+
+#[async_trait]
+pub trait ShellFactory: Send + Sync {
+    async fn spawn(
+        &self,
+        name: &str,
+        spec: &ShellSpec,
+        cols: u16,
+        rows: u16,
+    ) -> Result<Arc<dyn Shell>>;
+}
+
+pub struct PtyFactory;
+
+#[async_trait]
+impl ShellFactory for PtyFactory {
+    #[instrument(skip(self), fields(name = %name, kind = ?spec, cols, rows))]
+    async fn spawn(
+        &self,
+        name: &str,
+        spec: &ShellSpec,
+        cols: u16,
+        rows: u16,
+    ) -> Result<Arc<dyn Shell>> {
+        let shell = spawn_pty(name, spec, cols, rows).await?;
+        Ok(Arc::new(shell))
+    }
+}
 
-#[instrument(fields(name = %name, kind = ?spec, cols, rows))]
-pub async fn spawn(
+async fn spawn_pty(
     name: &str,
     spec: &ShellSpec,
     cols: u16,
@@ -19,14 +58,22 @@ pub async fn spawn(
 ) -> Result<PtyShell> {
     debug!("shell_factory_spawn start");
     let shell = match spec {
-        ShellSpec::Local { program } => {
+        ShellSpec::Local { program, .. } => {
             PtyShell::spawn(name, program, &[], cols, rows).await
         }
-        ShellSpec::Remote { host, backend } => match backend {
-            RemoteBackend::Ssh { port, extra_args } => {
+        ShellSpec::Remote { host, backend, .. } => match backend {
+            RemoteBackend::Ssh {
+                port,
+                extra_args,
+                via,
+            } => {
                 let mut argv: Vec<String> = vec![SSH_PTY_FLAG.to_string()];
                 argv.push(SSH_PORT_FLAG.to_string());
                 argv.push(port.to_string());
+                if let Some(jumps) = via {
+                    argv.push(SSH_JUMP_FLAG.to_string());
+                    argv.push(jumps.clone());
+                }
                 argv.extend(extra_args.iter().cloned());
                 argv.push(host.clone());
                 let refs: Vec<&str> = argv.iter().map(|s| s.as_str()).collect();
@@ -44,3 +91,50 @@ pub async fn spawn(
     info!("shell_factory_spawn ok");
     Ok(shell)
 }
+
+pub fn bastion_failure(via: &str, output: &str) -> Option<String> {
+    let hops: Vec<&str> = via
+        .split(',')
+        .map(|hop| {
+            let hop = hop.rsplit('@').next().unwrap_or(hop);
+            match hop.strip_prefix('[') {
+                Some(bracketed) => bracketed.split(']').next().unwrap_or(bracketed),
+                None => hop.split(':').next().unwrap_or(hop),
+            }
+        })
+        .collect();
+    let host_error = Regex::new(SSH_HOST_ERROR).ok()?;
+    output
+        .lines()
+        .map(str::trim)
+        .find(|line| {
+            JUMP_FAILURES.iter().any(|f| line.contains(f))
+                || host_error.captures(line).is_some_and(|c| {
+                    c.get(1)
+                        .or_else(|| c.get(2))
+                        .is_some_and(|h| hops.contains(&h.as_str()))
+                })
+        })
+        .map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::bastion_failure;
+
+    #[test]
+    fn reports_jump_host_errors_only() {
+        let refused = "ssh: connect to host bastion port 22: Connection refused\r\n";
+        assert_eq!(
+            bastion_failure("ops@bastion:22", refused).as_deref(),
+            Some("ssh: connect to host bastion port 22: Connection refused")
+        );
+        let forwarding = "channel 0: open failed\r\nstdio forwarding failed\r\n";
+        assert!(bastion_failure("bastion:22", forwarding).is_some());
+        let target = "ssh: connect to host web port 22: Connection refused\r\n";
+        assert_eq!(bastion_failure("bastion:22", target), None);
+        assert_eq!(bastion_failure("bastion:22", "Welcome to web\r\n$ "), None);
+        let v6 = "ssh: connect to host fd00::1 port 22: No route to host\r\n";
+        assert!(bastion_failure("ops@[fd00::1]:22", v6).is_some());
+    }
+}
@@ -0,0 +1,76 @@
+use std::path::Path;
+
+use crate::shell::{ShellSpec, spec::RemoteBackend};
+
+const FILE_URL_SCHEME: &str = "file://";
+const HOOK_SHELLS: &[&str] = &["bash", "zsh"];
+
+// Leading space keeps the hook out of history when HISTCONTROL=ignorespace.
+pub const PROMPT_HOOK: &str = concat!(
+    r#" __psh_hook() { local s=$?; "#,
+    r#"printf '\033]133;D;%s\007\033]7;file://%s%s\007' "$s" "${HOSTNAME:-$HOST}" "$PWD"; "#,
+    r#"return $s; }; "#,
+    r#"if [ -n "$ZSH_VERSION" ]; then precmd_functions+=(__psh_hook); "#,
+    r#"else PROMPT_COMMAND="__psh_hook${PROMPT_COMMAND:+;$PROMPT_COMMAND}"; fi"#,
+);
+
+// The login shell of a remote is unknown, so the hook only runs under bash or zsh.
+const REMOTE_HOOK_GUARD: &str = r#" [ -n "$BASH_VERSION$ZSH_VERSION" ] && eval "#;
+
+pub fn parse_cwd(data: &str) -> Option<String> {
+    let rest = data.strip_prefix(FILE_URL_SCHEME)?;
+    let path = &rest[rest.find('/')?..];
+    percent_decode(path)
+}
+
+pub fn supports_prompt_hook(spec: &ShellSpec) -> bool {
+    match spec {
+        ShellSpec::Local { program, .. } => Path::new(program)
+            .file_name()
+            .and_then(|n| n.to_str())
+            .is_some_and(|n| HOOK_SHELLS.contains(&n)),
+        ShellSpec::Remote { backend, .. } => {
+            matches!(backend, RemoteBackend::Ssh { .. })
+        }
+    }
+}
+
+pub fn prompt_hook(spec: &ShellSpec) -> String {
+    match spec {
+        ShellSpec::Local { .. } => PROMPT_HOOK.to_string(),
+        ShellSpec::Remote { .. } => {
+            format!(
+                "{REMOTE_HOOK_GUARD}{}",
+                shell_quote(PROMPT_HOOK.trim_start())
+            )
+        }
+    }
+}
+
+pub fn cd_command(path: &str) -> String {
+    format!("cd -- {}", shell_quote(path))
+}
+
+fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
+
+fn percent_decode(s: &str) -> Option<String> {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' => {
+                let hex = s.get(i + 1..i + 3)?;
+                out.push(u8::from_str_radix(hex, 16).ok()?);
+                i += 3;
+            }
+            b => {
+                out.push(b);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8(out).ok()
+}
@@ -1,33 +1,137 @@
 #![cfg(feature = "mock-shell")]
 
-use std::sync::Arc;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
 use async_trait::async_trait;
 use tokio::{
     sync::{broadcast, mpsc},
-    task,
+    task, time,
 };
 use tracing::{debug, info, warn};
 
 use crate::{
-    error::{Result, ShellError},
-    shell::{Shell, ShellEvent},
+    error::{Result, ShellError, SyncError},
+    repl::pipe::{base64_encode, unwrap_capture},
+    shell::{
+        Secret, Shell, ShellEvent, ShellFactory, ShellSpec,
+        record::{Recorder, SharedRecorder, with_recorder},
+        vt::{PromptMark, TermEvent},
+    },
 };
 
 const SHELL_CMD_CHANNEL_CAP: usize = 64;
 const SHELL_EVENT_CHANNEL_CAP: usize = 1024;
+const DISCONNECT_REASON: &str = "mock_disconnect";
+
+#[derive(Debug, Clone)]
+pub enum MockStep {
+    Output(String),
+    Delay(Duration),
+    Status(i32),
+    Disconnect,
+}
+
+#[derive(Debug, Clone)]
+struct MockRule {
+    expect: String,
+    steps: Vec<MockStep>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MockScript {
+    echo: bool,
+    banner: Option<String>,
+    rules: Vec<MockRule>,
+}
+
+impl MockScript {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn echo(mut self, echo: bool) -> Self {
+        self.echo = echo;
+        self
+    }
+
+    pub fn banner(mut self, banner: impl Into<String>) -> Self {
+        self.banner = Some(banner.into());
+        self
+    }
+
+    pub fn expect(mut self, expect: impl Into<String>) -> Self {
+        self.rules.push(MockRule {
+            expect: expect.into(),
+            steps: Vec::new(),
+        });
+        self
+    }
+
+    pub fn respond(self, output: impl Into<String>) -> Self {
+        self.step(MockStep::Output(output.into()))
+    }
+
+    pub fn delay(self, delay: Duration) -> Self {
+        self.step(MockStep::Delay(delay))
+    }
+
+    pub fn status(self, code: i32) -> Self {
+        self.step(MockStep::Status(code))
+    }
+
+    pub fn disconnect(self) -> Self {
+        self.step(MockStep::Disconnect)
+    }
+
+    fn step(mut self, step: MockStep) -> Self {
+        match self.rules.last_mut() {
+            Some(rule) => rule.steps.push(step),
+            None => warn!(?step, "mock_script step without expect ignored"),
+        }
+        self
+    }
+
+    fn steps_for(&self, line: &str) -> &[MockStep] {
+        self.rules
+            .iter()
+            .find(|r| line.contains(&r.expect))
+            .map(|r| r.steps.as_slice())
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Default)]
+struct Transcript {
+    inputs: Vec<String>,
+    output: String,
+}
 
 pub struct MockShell {
     name: String,
     tx: mpsc::Sender<Vec<u8>>,
     events: broadcast::Sender<ShellEvent>,
+    size: Mutex<(u16, u16)>,
+    transcript: Arc<Mutex<Transcript>>,
+    recorder: SharedRecorder,
 }
 
 #[async_trait]
 impl Shell for MockShell {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
     async fn send_line(&self, line: String) -> Result<()> {
         debug!(shell = %self.name, %line, "mock_send_line start");
-        self.tx.send(line.into_bytes()).await.map_err(|e| {
+        with_recorder(&self.recorder, |r| r.input_line(&line));
+        let mut bytes = line.into_bytes();
+        bytes.push(b'\n');
+        self.tx.send(bytes).await.map_err(|e| {
             ShellError::from(SyncError::ChannelClosed {
                 context: format!("cmd_tx write_line: {e}"),
             })
@@ -38,17 +142,34 @@ impl Shell for MockShell {
 
     async fn send_bytes(&self, bytes: Vec<u8>) -> Result<()> {
         debug!(shell = %self.name, size = bytes.len(), "mock_send_bytes start");
+        with_recorder(&self.recorder, |r| r.input_bytes(&bytes));
         self.tx.send(bytes).await.map_err(|e| {
             ShellError::from(SyncError::ChannelClosed {
-                context: format!("cmd_tx write_line: {e}"),
+                context: format!("cmd_tx write_bytes: {e}"),
             })
         })?;
         info!(shell = %self.name, "mock_send_bytes ok");
         Ok(())
     }
 
-    async fn resize(&self, _cols: u16, _rows: u16) -> Result<()> {
+    async fn send_secret(&self, secret: &Secret) -> Result<()> {
+        debug!(shell = %self.name, "mock_send_secret start");
+        let mut bytes = secret.expose().as_bytes().to_vec();
+        bytes.push(b'\n');
+        self.tx.send(bytes).await.map_err(|e| {
+            ShellError::from(SyncError::ChannelClosed {
+                context: format!("cmd_tx write_secret: {e}"),
+            })
+        })?;
+        info!(shell = %self.name, "mock_send_secret ok");
+        Ok(())
+    }
+
+    async fn resize(&self, cols: u16, rows: u16) -> Result<()> {
         debug!(shell = %self.name, "mock_resize start");
+        if let Ok(mut size) = self.size.lock() {
+            *size = (cols, rows);
+        }
         info!(shell = %self.name, "mock_resize ok");
         Ok(())
     }
@@ -68,31 +189,203 @@ impl Shell for MockShell {
         info!(shell = %self.name, "mock_subscribe ok");
         rx
     }
+
+    fn size(&self) -> (u16, u16) {
+        self.size.lock().map(|s| *s).unwrap_or((0, 0))
+    }
+
+    fn render_screen(&self) -> Result<String> {
+        Ok(self.output())
+    }
+
+    fn start_recording(&self, path: &Path) -> Result<PathBuf> {
+        debug!(shell = %self.name, path = %path.display(), "mock_start_recording start");
+        let (cols, rows) = self.size();
+        let recorder = Recorder::create(path, &self.name, cols, rows)?;
+        if let Ok(mut guard) = self.recorder.lock() {
+            *guard = Some(recorder);
+        }
+        info!(shell = %self.name, "mock_start_recording ok");
+        Ok(path.to_path_buf())
+    }
+
+    fn stop_recording(&self) -> Option<PathBuf> {
+        self.recorder
+            .lock()
+            .ok()
+            .and_then(|mut g| g.take())
+            .map(|r| r.path().to_path_buf())
+    }
 }
 
 impl MockShell {
-    pub fn spawn(name: &str) -> Result<Arc<Self>> {
+    pub fn spawn(name: &str, script: MockScript) -> Result<Arc<Self>> {
         debug!(shell = name, "mock_spawn start");
         let (tx, mut rx) = mpsc::channel::<Vec<u8>>(SHELL_CMD_CHANNEL_CAP);
         let (ev_tx, _) = broadcast::channel::<ShellEvent>(SHELL_EVENT_CHANNEL_CAP);
-        let name_owned = name.to_string();
-        let ev_tx_cl = ev_tx.clone();
+        let transcript = Arc::new(Mutex::new(Transcript {
+            output: script.banner.clone().unwrap_or_default(),
+            ..Transcript::default()
+        }));
+        let recorder: SharedRecorder = Arc::new(Mutex::new(None));
+        let worker = Worker {
+            name: name.to_string(),
+            script,
+            events: ev_tx.clone(),
+            transcript: transcript.clone(),
+            recorder: recorder.clone(),
+        };
         task::spawn(async move {
-            info!(shell = %name_owned, "mock_worker start");
-            while let Some(bytes) = rx.recv().await {
-                let s = String::from_utf8_lossy(&bytes).to_string();
-                if let Err(e) = ev_tx_cl.send(ShellEvent::Output(s)) {
-                    warn!(shell = %name_owned, ?e, "mock_worker notify failed");
+            info!(shell = %worker.name, "mock_worker start");
+            let mut pending = String::new();
+            'recv: while let Some(bytes) = rx.recv().await {
+                pending.push_str(&String::from_utf8_lossy(&bytes));
+                while let Some(end) = pending.find(['\n', '\r']) {
+                    let line: String = pending.drain(..=end).collect();
+                    if !worker.run_line(line.trim_end()).await {
+                        break 'recv;
+                    }
                 }
             }
-            info!(shell = %name_owned, "mock_worker done");
+            info!(shell = %worker.name, "mock_worker done");
         });
         let s = Arc::new(Self {
             name: name.to_string(),
             tx,
             events: ev_tx,
+            size: Mutex::new((0, 0)),
+            transcript,
+            recorder,
         });
         info!(shell = name, "mock_spawn ok");
         Ok(s)
     }
+
+    pub fn inputs(&self) -> Vec<String> {
+        self.transcript
+            .lock()
+            .map(|t| t.inputs.clone())
+            .unwrap_or_default()
+    }
+
+    pub fn output(&self) -> String {
+        self.transcript
+            .lock()
+            .map(|t| t.output.clone())
+            .unwrap_or_default()
+    }
+}
+
+struct Worker {
+    name: String,
+    script: MockScript,
+    events: broadcast::Sender<ShellEvent>,
+    transcript: Arc<Mutex<Transcript>>,
+    recorder: SharedRecorder,
+}
+
+impl Worker {
+    async fn run_line(&self, line: &str) -> bool {
+        debug!(shell = %self.name, line = line, "mock_worker line");
+        if let Ok(mut t) = self.transcript.lock() {
+            t.inputs.push(line.to_string());
+        }
+        if self.script.echo {
+            self.output(format!("{line}\n"));
+        }
+        // Captures wrap the command in markers and base64, as a real shell
+        // running the wrapper would print them.
+        let Some((begin, command, end)) = unwrap_capture(line) else {
+            return self.run_steps(line, None).await;
+        };
+        self.output(format!("\n{begin}\n"));
+        let mut captured = String::new();
+        if !self.run_steps(command, Some(&mut captured)).await {
+            return false;
+        }
+        self.output(format!("{}\n\n{end}\n", base64_encode(captured.as_bytes())));
+        true
+    }
+
+    async fn run_steps(&self, line: &str, mut captured: Option<&mut String>) -> bool {
+        for step in self.script.steps_for(line) {
+            match step {
+                MockStep::Output(text) => match captured.as_deref_mut() {
+                    Some(captured) => captured.push_str(text),
+                    None => self.output(text.clone()),
+                },
+                MockStep::Delay(delay) => time::sleep(*delay).await,
+                MockStep::Status(code) => self.emit(ShellEvent::Term(
+                    TermEvent::Prompt(PromptMark::Finished(Some(*code))),
+                )),
+                MockStep::Disconnect => {
+                    info!(shell = %self.name, "mock_worker disconnect");
+                    self.emit(ShellEvent::Exited(DISCONNECT_REASON.to_string()));
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
+    fn output(&self, text: String) {
+        if let Ok(mut t) = self.transcript.lock() {
+            t.output.push_str(&text);
+        }
+        with_recorder(&self.recorder, |r| r.output(&text));
+        self.emit(ShellEvent::Raw(text.as_bytes().into()));
+        self.emit(ShellEvent::Output(text));
+    }
+
+    fn emit(&self, event: ShellEvent) {
+        if let Err(e) = self.events.send(event) {
+            debug!(shell = %self.name, ?e, "mock_worker no subscribers");
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct MockFactory {
+    scripts: Arc<Mutex<HashMap<String, MockScript>>>,
+    spawned: Arc<Mutex<HashMap<String, Arc<MockShell>>>>,
+}
+
+impl MockFactory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn script(&self, name: &str, script: MockScript) {
+        if let Ok(mut scripts) = self.scripts.lock() {
+            scripts.insert(name.to_string(), script);
+        }
+    }
+
+    pub fn shell(&self, name: &str) -> Option<Arc<MockShell>> {
+        self.spawned.lock().ok()?.get(name).cloned()
+    }
+}
+
+#[async_trait]
+impl ShellFactory for MockFactory {
+    async fn spawn(
+        &self,
+        name: &str,
+        _spec: &ShellSpec,
+        cols: u16,
+        rows: u16,
+    ) -> Result<Arc<dyn Shell>> {
+        let script = self
+            .scripts
+            .lock()
+            .ok()
+            .and_then(|s| s.get(name).cloned())
+            .unwrap_or_else(|| MockScript::new().echo(true));
+        let shell = MockShell::spawn(name, script)?;
+        shell.resize(cols, rows).await?;
+        if let Ok(mut spawned) = self.spawned.lock() {
+            spawned.insert(name.to_string(), shell.clone());
+        }
+        Ok(shell)
+    }
 }
@@ -1,5 +1,6 @@
 use std::{
     io::Write,
+    path::{Path, PathBuf},
     sync::{Arc, Mutex},
 };
 
@@ -13,7 +14,12 @@ use tracing::{debug, error, info, warn};
 
 use crate::{
     error::{Result, ShellError, SyncError},
-    shell::{Shell, ShellCmd, ShellEvent},
+    shell::{
+        Secret, Shell, ShellCmd, ShellEvent,
+        record::{Recorder, SharedRecorder, with_recorder},
+        screen::Screen,
+        vt::{TermEvent, Utf8Decoder, VtParser},
+    },
 };
 
 const SHELL_CMD_CHANNEL_CAP: usize = 64;
@@ -26,12 +32,20 @@ pub struct PtyShell {
     name: String,
     tx: mpsc::Sender<ShellCmd>,
     events: broadcast::Sender<ShellEvent>,
+    recorder: SharedRecorder,
+    screen: Arc<Mutex<Screen>>,
+    size: Mutex<(u16, u16)>,
 }
 
 #[async_trait]
 impl Shell for PtyShell {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
     async fn send_line(&self, line: String) -> Result<()> {
         debug!(shell = %self.name, %line, "send_line");
+        with_recorder(&self.recorder, |r| r.input_line(&line));
         self.tx.send(ShellCmd::WriteLine(line)).await.map_err(|e| {
             ShellError::from(SyncError::ChannelClosed {
                 context: format!("cmd_tx write_line: {e}"),
@@ -43,6 +57,7 @@ impl Shell for PtyShell {
 
     async fn send_bytes(&self, bytes: Vec<u8>) -> Result<()> {
         debug!(shell = %self.name, size = bytes.len(), "send_bytes");
+        with_recorder(&self.recorder, |r| r.input_bytes(&bytes));
         self.tx
             .send(ShellCmd::WriteBytes(bytes))
             .await
@@ -55,8 +70,31 @@ impl Shell for PtyShell {
         Ok(())
     }
 
+    async fn send_secret(&self, secret: &Secret) -> Result<()> {
+        debug!(shell = %self.name, "send_secret");
+        let mut bytes = secret.expose().as_bytes().to_vec();
+        bytes.extend_from_slice(NEWLINE);
+        self.tx
+            .send(ShellCmd::WriteBytes(bytes))
+            .await
+            .map_err(|e| {
+                ShellError::from(SyncError::ChannelClosed {
+                    context: format!("cmd_tx write_secret: {e}"),
+                })
+            })?;
+        info!(shell = %self.name, "send_secret ok");
+        Ok(())
+    }
+
     async fn resize(&self, cols: u16, rows: u16) -> Result<()> {
         debug!(shell = %self.name, cols, rows, "resize");
+        if let Ok(mut size) = self.size.lock() {
+            *size = (cols, rows);
+        }
+        if let Ok(mut screen) = self.screen.lock() {
+            screen.resize(cols, rows);
+        }
+        with_recorder(&self.recorder, |r| r.resize(cols, rows));
         self.tx
             .send(ShellCmd::Resize(cols, rows))
             .await
@@ -86,6 +124,53 @@ impl Shell for PtyShell {
         info!(shell = %self.name, "subscribe ok");
         rx
     }
+
+    fn start_recording(&self, path: &Path) -> Result<PathBuf> {
+        debug!(shell = %self.name, path = %path.display(), "start_recording start");
+        let (cols, rows) = *self.size.lock().map_err(|e| {
+            ShellError::from(SyncError::MutexPoison {
+                context: format!("size lock poisoned: {e}"),
+            })
+        })?;
+        let recorder = Recorder::create(path, &self.name, cols, rows)?;
+        let mut guard = self.recorder.lock().map_err(|e| {
+            ShellError::from(SyncError::MutexPoison {
+                context: format!("recorder lock poisoned: {e}"),
+            })
+        })?;
+        if let Some(prev) = guard.replace(recorder) {
+            warn!(shell = %self.name, path = %prev.path().display(), "start_recording replaced");
+        }
+        info!(shell = %self.name, path = %path.display(), "start_recording ok");
+        Ok(path.to_path_buf())
+    }
+
+    fn stop_recording(&self) -> Option<PathBuf> {
+        debug!(shell = %self.name, "stop_recording start");
+        let stopped = self
+            .recorder
+            .lock()
+            .ok()
+            .and_then(|mut g| g.take())
+            .map(|r| r.path().to_path_buf());
+        info!(shell = %self.name, stopped = stopped.is_some(), "stop_recording ok");
+        stopped
+    }
+
+    fn size(&self) -> (u16, u16) {
+        self.size.lock().map(|s| *s).unwrap_or((0, 0))
+    }
+
+    fn screen(&self) -> Option<Arc<Mutex<Screen>>> {
+        Some(self.screen.clone())
+    }
+
+    fn recording_path(&self) -> Option<PathBuf> {
+        self.recorder
+            .lock()
+            .ok()
+            .and_then(|g| g.as_ref().map(|r| r.path().to_path_buf()))
+    }
 }
 
 impl PtyShell {
@@ -120,12 +205,18 @@ impl PtyShell {
         let (tx, mut rx) = mpsc::channel::<ShellCmd>(SHELL_CMD_CHANNEL_CAP);
         let (ev_tx, _) = broadcast::channel::<ShellEvent>(SHELL_EVENT_CHANNEL_CAP);
 
+        let recorder: SharedRecorder = Arc::new(Mutex::new(None));
         let reader_name = name.to_string();
         let ev_tx_reader = ev_tx.clone();
+        let recorder_reader = recorder.clone();
+        let screen = Arc::new(Mutex::new(Screen::new(cols, rows)));
+        let screen_reader = screen.clone();
         task::spawn_blocking(move || {
             info!(shell = %reader_name, "reader started");
             let mut r = reader;
             let mut buf = [0u8; PTY_READ_BUF_SIZE];
+            let mut decoder = Utf8Decoder::new();
+            let mut parser = VtParser::new();
             loop {
                 match r.read(&mut buf) {
                     Ok(0) => {
@@ -138,11 +229,34 @@ impl PtyShell {
                         break;
                     }
                     Ok(n) => {
-                        let s = String::from_utf8_lossy(&buf[..n]).to_string();
+                        let raw = &buf[..n];
+                        let s = decoder.decode(raw);
                         info!(shell = %reader_name, bytes = n, "read chunk");
-                        if let Err(e) = ev_tx_reader.send(ShellEvent::Output(s)) {
+                        with_recorder(&recorder_reader, |rec| rec.output(&s));
+                        let mut marks: Vec<TermEvent> = Vec::new();
+                        match screen_reader.lock() {
+                            Ok(mut screen) => parser.feed(raw, |action| {
+                                screen.apply(&action);
+                                marks.extend(TermEvent::from_action(&action));
+                            }),
+                            Err(e) => {
+                                warn!(shell = %reader_name, ?e, "screen lock poisoned")
+                            }
+                        }
+                        if let Err(e) = ev_tx_reader.send(ShellEvent::Raw(raw.into())) {
+                            warn!(shell = %reader_name, ?e, "notify raw failed");
+                        }
+                        if !s.is_empty()
+                            && let Err(e) = ev_tx_reader.send(ShellEvent::Output(s))
+                        {
                             warn!(shell = %reader_name, ?e, "notify output failed");
                         }
+                        for mark in marks {
+                            debug!(shell = %reader_name, ?mark, "shell integration mark");
+                            if let Err(e) = ev_tx_reader.send(ShellEvent::Term(mark)) {
+                                warn!(shell = %reader_name, ?e, "notify mark failed");
+                            }
+                        }
                     }
                     Err(e) => {
                         let formatted_error = ShellError::Read(e.into());
@@ -334,6 +448,9 @@ impl PtyShell {
             name: name.to_string(),
             tx,
             events: ev_tx,
+            recorder,
+            screen,
+            size: Mutex::new((cols, rows)),
         })
     }
 }
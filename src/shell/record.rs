@@ -0,0 +1,188 @@
+use std::{
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info, warn};
+
+use crate::error::{Result, ShellError};
+
+pub const ASCIICAST_VERSION: u8 = 2;
+pub const EVENT_OUTPUT: &str = "o";
+pub const EVENT_INPUT: &str = "i";
+pub const EVENT_RESIZE: &str = "r";
+const INPUT_NEWLINE: &str = "\r";
+
+pub(crate) type SharedRecorder = Arc<Mutex<Option<Recorder>>>;
+
+pub(crate) fn with_recorder(recorder: &SharedRecorder, f: impl FnOnce(&mut Recorder)) {
+    match recorder.lock() {
+        Ok(mut guard) => {
+            if let Some(r) = guard.as_mut() {
+                f(r);
+            }
+        }
+        Err(e) => warn!(?e, "recorder lock poisoned"),
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CastHeader {
+    pub version: u8,
+    pub width: u16,
+    pub height: u16,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+}
+
+pub struct Recorder {
+    path: PathBuf,
+    started: Instant,
+    out: BufWriter<File>,
+}
+
+impl Recorder {
+    pub fn create(path: &Path, title: &str, cols: u16, rows: u16) -> Result<Self> {
+        debug!(path = %path.display(), cols, rows, "recorder_create start");
+        let io_err = |source| ShellError::Recording {
+            path: path.display().to_string(),
+            source,
+        };
+        if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            fs::create_dir_all(dir).map_err(io_err)?;
+        }
+        let file = File::create(path).map_err(io_err)?;
+        let mut out = BufWriter::new(file);
+        let header = CastHeader {
+            version: ASCIICAST_VERSION,
+            width: cols,
+            height: rows,
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .ok()
+                .map(|d| d.as_secs()),
+            title: Some(title.to_string()),
+        };
+        let line = serde_json::to_string(&header).map_err(|e| io_err(e.into()))?;
+        writeln!(out, "{line}").map_err(io_err)?;
+        out.flush().map_err(io_err)?;
+        info!(path = %path.display(), "recorder_create ok");
+        Ok(Self {
+            path: path.to_path_buf(),
+            started: Instant::now(),
+            out,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn event(&mut self, kind: &str, data: &str) {
+        let at = self.started.elapsed().as_secs_f64();
+        let res = serde_json::to_string(&(at, kind, data))
+            .map_err(std::io::Error::from)
+            .and_then(|line| writeln!(self.out, "{line}"))
+            .and_then(|()| self.out.flush());
+        if let Err(e) = res {
+            warn!(path = %self.path.display(), ?e, "recorder_event write failed");
+        }
+    }
+
+    pub fn output(&mut self, data: &str) {
+        self.event(EVENT_OUTPUT, data);
+    }
+
+    pub fn input_line(&mut self, line: &str) {
+        self.event(EVENT_INPUT, &format!("{line}{INPUT_NEWLINE}"));
+    }
+
+    pub fn input_bytes(&mut self, bytes: &[u8]) {
+        self.event(EVENT_INPUT, &String::from_utf8_lossy(bytes));
+    }
+
+    pub fn resize(&mut self, cols: u16, rows: u16) {
+        self.event(EVENT_RESIZE, &format!("{cols}x{rows}"));
+    }
+}
+
+pub struct ReplayOptions {
+    pub speed: f64,
+    pub idle_limit: Option<f64>,
+}
+
+impl Default for ReplayOptions {
+    fn default() -> Self {
+        Self {
+            speed: 1.0,
+            idle_limit: None,
+        }
+    }
+}
+
+pub async fn replay(
+    path: &Path,
+    options: &ReplayOptions,
+    mut emit: impl FnMut(&str) -> Result<()>,
+) -> Result<CastHeader> {
+    debug!(path = %path.display(), speed = options.speed, "recording_replay start");
+    let display = path.display().to_string();
+    let format_err = |line: usize, reason: String| ShellError::RecordingFormat {
+        path: display.clone(),
+        line,
+        reason,
+    };
+    let text = tokio::fs::read_to_string(path).await.map_err(|source| {
+        ShellError::Recording {
+            path: display.clone(),
+            source,
+        }
+    })?;
+    let mut lines = text
+        .lines()
+        .enumerate()
+        .filter(|(_, l)| !l.trim().is_empty());
+    let Some((_, first)) = lines.next() else {
+        return Err(format_err(1, "empty file".to_string()).into());
+    };
+    let header: CastHeader =
+        serde_json::from_str(first).map_err(|e| format_err(1, e.to_string()))?;
+    if header.version != ASCIICAST_VERSION {
+        warn!(
+            version = header.version,
+            "recording_replay unsupported version"
+        );
+        return Err(
+            format_err(1, format!("unsupported version {}", header.version)).into(),
+        );
+    }
+
+    let mut last = 0.0f64;
+    for (idx, line) in lines {
+        let (at, kind, data): (f64, String, String) = serde_json::from_str(line)
+            .map_err(|e| format_err(idx + 1, e.to_string()))?;
+        if kind != EVENT_OUTPUT {
+            continue;
+        }
+        let mut wait = (at - last).max(0.0);
+        if let Some(limit) = options.idle_limit {
+            wait = wait.min(limit);
+        }
+        last = at;
+        if wait > 0.0 {
+            match Duration::try_from_secs_f64(wait / options.speed) {
+                Ok(pause) => tokio::time::sleep(pause).await,
+                Err(e) => warn!(line = idx + 1, %e, "recording_replay wait skipped"),
+            }
+        }
+        emit(&data)?;
+    }
+    info!(path = %path.display(), "recording_replay ok");
+    Ok(header)
+}
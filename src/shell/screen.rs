@@ -0,0 +1,684 @@
+use std::fmt::Write;
+
+use crate::shell::vt::{Action, Csi};
+
+const TAB_WIDTH: usize = 8;
+const BS: u8 = 0x08;
+const HT: u8 = 0x09;
+const LF: u8 = 0x0a;
+const VT: u8 = 0x0b;
+const FF: u8 = 0x0c;
+const CR: u8 = 0x0d;
+
+const MODE_CURSOR_VISIBLE: u16 = 25;
+const MODE_AUTOWRAP: u16 = 7;
+const MODE_ALT_SCREEN: u16 = 47;
+const MODE_ALT_SCREEN_CLEAR: u16 = 1047;
+const MODE_ALT_SCREEN_SAVE: u16 = 1049;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CellColor {
+    #[default]
+    Default,
+    Indexed(u8),
+    Rgb(u8, u8, u8),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Pen {
+    pub fg: CellColor,
+    pub bg: CellColor,
+    pub bold: bool,
+    pub dim: bool,
+    pub italic: bool,
+    pub underline: bool,
+    pub inverse: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cell {
+    pub ch: char,
+    pub pen: Pen,
+}
+
+impl Default for Cell {
+    fn default() -> Self {
+        Self {
+            ch: ' ',
+            pen: Pen::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Cursor {
+    row: usize,
+    col: usize,
+    pen: Pen,
+}
+
+#[derive(Debug, Clone)]
+pub struct Screen {
+    cols: usize,
+    rows: usize,
+    grid: Vec<Vec<Cell>>,
+    primary: Option<Vec<Vec<Cell>>>,
+    cursor: Cursor,
+    saved: Cursor,
+    wrap_pending: bool,
+    autowrap: bool,
+    cursor_visible: bool,
+    scroll_top: usize,
+    scroll_bottom: usize,
+    title: String,
+}
+
+impl Screen {
+    pub fn new(cols: u16, rows: u16) -> Self {
+        let (cols, rows) = (usize::from(cols.max(1)), usize::from(rows.max(1)));
+        Self {
+            cols,
+            rows,
+            grid: vec![vec![Cell::default(); cols]; rows],
+            primary: None,
+            cursor: Cursor::default(),
+            saved: Cursor::default(),
+            wrap_pending: false,
+            autowrap: true,
+            cursor_visible: true,
+            scroll_top: 0,
+            scroll_bottom: rows - 1,
+            title: String::new(),
+        }
+    }
+
+    pub fn size(&self) -> (u16, u16) {
+        (self.cols as u16, self.rows as u16)
+    }
+
+    pub fn cursor(&self) -> (usize, usize) {
+        (self.cursor.row, self.cursor.col)
+    }
+
+    pub fn cursor_visible(&self) -> bool {
+        self.cursor_visible
+    }
+
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    pub fn is_alternate(&self) -> bool {
+        self.primary.is_some()
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = &[Cell]> {
+        self.grid.iter().map(Vec::as_slice)
+    }
+
+    pub fn resize(&mut self, cols: u16, rows: u16) {
+        let (cols, rows) = (usize::from(cols.max(1)), usize::from(rows.max(1)));
+        for grid in std::iter::once(&mut self.grid).chain(self.primary.as_mut()) {
+            if grid.len() > rows {
+                let excess = grid.len() - rows;
+                grid.drain(..excess);
+            }
+            grid.resize(rows, vec![Cell::default(); cols]);
+            for row in grid.iter_mut() {
+                row.resize(cols, Cell::default());
+            }
+        }
+        if self.rows > rows {
+            self.cursor.row = self.cursor.row.saturating_sub(self.rows - rows);
+        }
+        self.cols = cols;
+        self.rows = rows;
+        self.cursor.row = self.cursor.row.min(rows - 1);
+        self.cursor.col = self.cursor.col.min(cols - 1);
+        self.scroll_top = 0;
+        self.scroll_bottom = rows - 1;
+        self.wrap_pending = false;
+    }
+
+    pub fn apply(&mut self, action: &Action) {
+        match action {
+            Action::Print(ch) => self.print(*ch),
+            Action::Control(byte) => self.control(*byte),
+            Action::Esc {
+                intermediates,
+                byte,
+            } if intermediates.is_empty() => self.esc(*byte),
+            Action::Esc { .. } => {}
+            Action::Csi(csi) => self.csi(csi),
+            Action::Osc(body) => {
+                if let Some(title) =
+                    body.strip_prefix("0;").or_else(|| body.strip_prefix("2;"))
+                {
+                    self.title = title.to_string();
+                }
+            }
+        }
+    }
+
+    fn print(&mut self, ch: char) {
+        if self.wrap_pending {
+            self.cursor.col = 0;
+            self.line_feed();
+            self.wrap_pending = false;
+        }
+        let Cursor { row, col, pen } = self.cursor;
+        self.grid[row][col] = Cell { ch, pen };
+        if col + 1 < self.cols {
+            self.cursor.col += 1;
+        } else if self.autowrap {
+            self.wrap_pending = true;
+        }
+    }
+
+    fn control(&mut self, byte: u8) {
+        match byte {
+            BS => {
+                self.cursor.col = self.cursor.col.saturating_sub(1);
+                self.wrap_pending = false;
+            }
+            HT => {
+                let next = (self.cursor.col / TAB_WIDTH + 1) * TAB_WIDTH;
+                self.cursor.col = next.min(self.cols - 1);
+            }
+            LF | VT | FF => {
+                self.line_feed();
+                self.wrap_pending = false;
+            }
+            CR => {
+                self.cursor.col = 0;
+                self.wrap_pending = false;
+            }
+            _ => {}
+        }
+    }
+
+    fn esc(&mut self, byte: u8) {
+        match byte {
+            b'7' => self.saved = self.cursor,
+            b'8' => self.restore_cursor(),
+            b'D' => self.line_feed(),
+            b'E' => {
+                self.cursor.col = 0;
+                self.line_feed();
+            }
+            b'M' => self.reverse_index(),
+            b'c' => *self = Screen::new(self.cols as u16, self.rows as u16),
+            _ => {}
+        }
+        self.wrap_pending = false;
+    }
+
+    fn csi(&mut self, csi: &Csi) {
+        if let Some(b'?') = csi.private {
+            match csi.action {
+                'h' => self.set_private_modes(&csi.params, true),
+                'l' => self.set_private_modes(&csi.params, false),
+                _ => {}
+            }
+            return;
+        }
+        if csi.private.is_some() || !csi.intermediates.is_empty() {
+            return;
+        }
+        let n = usize::from(csi.param(0, 1));
+        let (row, col) = (self.cursor.row, self.cursor.col);
+        match csi.action {
+            'A' => self.goto(row.saturating_sub(n), col),
+            'B' | 'e' => self.goto(row + n, col),
+            'C' | 'a' => self.goto(row, col + n),
+            'D' => self.goto(row, col.saturating_sub(n)),
+            'E' => self.goto(row + n, 0),
+            'F' => self.goto(row.saturating_sub(n), 0),
+            'G' | '`' => self.goto(row, n - 1),
+            'd' => self.goto(n - 1, col),
+            'H' | 'f' => self.goto(n - 1, usize::from(csi.param(1, 1)) - 1),
+            'J' => self.erase_display(csi.params.first().copied().unwrap_or(0)),
+            'K' => self.erase_line(csi.params.first().copied().unwrap_or(0)),
+            'L' => self.insert_lines(n),
+            'M' => self.delete_lines(n),
+            '@' => self.insert_chars(n),
+            'P' => self.delete_chars(n),
+            'X' => {
+                let end = (col + n).min(self.cols);
+                self.clear_cells(row, col, end);
+            }
+            'S' => self.scroll_up(n),
+            'T' => self.scroll_down(n),
+            'm' => self.sgr(&csi.params),
+            'r' => {
+                let top = usize::from(csi.param(0, 1)) - 1;
+                let bottom = usize::from(csi.param(1, self.rows as u16)) - 1;
+                if top < bottom && bottom < self.rows {
+                    self.scroll_top = top;
+                    self.scroll_bottom = bottom;
+                    self.goto(0, 0);
+                }
+            }
+            's' => self.saved = self.cursor,
+            'u' => self.restore_cursor(),
+            _ => {}
+        }
+    }
+
+    fn set_private_modes(&mut self, params: &[u16], on: bool) {
+        for mode in params {
+            match *mode {
+                MODE_CURSOR_VISIBLE => self.cursor_visible = on,
+                MODE_AUTOWRAP => self.autowrap = on,
+                MODE_ALT_SCREEN | MODE_ALT_SCREEN_CLEAR | MODE_ALT_SCREEN_SAVE => {
+                    self.switch_alternate(on, *mode == MODE_ALT_SCREEN_SAVE)
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn switch_alternate(&mut self, on: bool, save_cursor: bool) {
+        match (on, self.primary.is_some()) {
+            (true, false) => {
+                if save_cursor {
+                    self.saved = self.cursor;
+                }
+                let blank = vec![vec![Cell::default(); self.cols]; self.rows];
+                self.primary = Some(std::mem::replace(&mut self.grid, blank));
+            }
+            (false, true) => {
+                if let Some(primary) = self.primary.take() {
+                    self.grid = primary;
+                }
+                if save_cursor {
+                    self.restore_cursor();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn restore_cursor(&mut self) {
+        self.cursor = self.saved;
+        self.cursor.row = self.cursor.row.min(self.rows - 1);
+        self.cursor.col = self.cursor.col.min(self.cols - 1);
+        self.wrap_pending = false;
+    }
+
+    fn goto(&mut self, row: usize, col: usize) {
+        self.cursor.row = row.min(self.rows - 1);
+        self.cursor.col = col.min(self.cols - 1);
+        self.wrap_pending = false;
+    }
+
+    fn line_feed(&mut self) {
+        if self.cursor.row == self.scroll_bottom {
+            self.scroll_up(1);
+        } else if self.cursor.row + 1 < self.rows {
+            self.cursor.row += 1;
+        }
+    }
+
+    fn reverse_index(&mut self) {
+        if self.cursor.row == self.scroll_top {
+            self.scroll_down(1);
+        } else {
+            self.cursor.row = self.cursor.row.saturating_sub(1);
+        }
+    }
+
+    fn blank_row(&self) -> Vec<Cell> {
+        let pen = Pen {
+            bg: self.cursor.pen.bg,
+            ..Pen::default()
+        };
+        vec![Cell { ch: ' ', pen }; self.cols]
+    }
+
+    fn scroll_up(&mut self, n: usize) {
+        let (top, bottom) = (self.scroll_top, self.scroll_bottom);
+        for _ in 0..n.min(bottom - top + 1) {
+            self.grid.remove(top);
+            self.grid.insert(bottom, self.blank_row());
+        }
+    }
+
+    fn scroll_down(&mut self, n: usize) {
+        let (top, bottom) = (self.scroll_top, self.scroll_bottom);
+        for _ in 0..n.min(bottom - top + 1) {
+            self.grid.remove(bottom);
+            self.grid.insert(top, self.blank_row());
+        }
+    }
+
+    fn insert_lines(&mut self, n: usize) {
+        let row = self.cursor.row;
+        if row < self.scroll_top || row > self.scroll_bottom {
+            return;
+        }
+        for _ in 0..n.min(self.scroll_bottom - row + 1) {
+            self.grid.remove(self.scroll_bottom);
+            self.grid.insert(row, self.blank_row());
+        }
+        self.cursor.col = 0;
+    }
+
+    fn delete_lines(&mut self, n: usize) {
+        let row = self.cursor.row;
+        if row < self.scroll_top || row > self.scroll_bottom {
+            return;
+        }
+        for _ in 0..n.min(self.scroll_bottom - row + 1) {
+            self.grid.remove(row);
+            self.grid.insert(self.scroll_bottom, self.blank_row());
+        }
+        self.cursor.col = 0;
+    }
+
+    fn insert_chars(&mut self, n: usize) {
+        let Cursor { row, col, .. } = self.cursor;
+        let blank = self.blank_row()[0];
+        let line = &mut self.grid[row];
+        for _ in 0..n.min(self.cols - col) {
+            line.pop();
+            line.insert(col, blank);
+        }
+    }
+
+    fn delete_chars(&mut self, n: usize) {
+        let Cursor { row, col, .. } = self.cursor;
+        let blank = self.blank_row()[0];
+        let line = &mut self.grid[row];
+        for _ in 0..n.min(self.cols - col) {
+            line.remove(col);
+            line.push(blank);
+        }
+    }
+
+    fn clear_cells(&mut self, row: usize, from: usize, to: usize) {
+        let blank = self.blank_row()[0];
+        for cell in &mut self.grid[row][from..to] {
+            *cell = blank;
+        }
+    }
+
+    fn erase_line(&mut self, mode: u16) {
+        let Cursor { row, col, .. } = self.cursor;
+        match mode {
+            0 => self.clear_cells(row, col, self.cols),
+            1 => self.clear_cells(row, 0, col + 1),
+            2 => self.clear_cells(row, 0, self.cols),
+            _ => {}
+        }
+    }
+
+    fn erase_display(&mut self, mode: u16) {
+        let row = self.cursor.row;
+        match mode {
+            0 => {
+                self.erase_line(0);
+                for r in row + 1..self.rows {
+                    self.clear_cells(r, 0, self.cols);
+                }
+            }
+            1 => {
+                self.erase_line(1);
+                for r in 0..row {
+                    self.clear_cells(r, 0, self.cols);
+                }
+            }
+            2 | 3 => {
+                for r in 0..self.rows {
+                    self.clear_cells(r, 0, self.cols);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn sgr(&mut self, params: &[u16]) {
+        let pen = &mut self.cursor.pen;
+        if params.is_empty() {
+            *pen = Pen::default();
+            return;
+        }
+        let mut it = params.iter().copied();
+        while let Some(p) = it.next() {
+            match p {
+                0 => *pen = Pen::default(),
+                1 => pen.bold = true,
+                2 => pen.dim = true,
+                3 => pen.italic = true,
+                4 => pen.underline = true,
+                7 => pen.inverse = true,
+                22 => {
+                    pen.bold = false;
+                    pen.dim = false;
+                }
+                23 => pen.italic = false,
+                24 => pen.underline = false,
+                27 => pen.inverse = false,
+                30..=37 => pen.fg = CellColor::Indexed((p - 30) as u8),
+                39 => pen.fg = CellColor::Default,
+                40..=47 => pen.bg = CellColor::Indexed((p - 40) as u8),
+                49 => pen.bg = CellColor::Default,
+                90..=97 => pen.fg = CellColor::Indexed((p - 90 + 8) as u8),
+                100..=107 => pen.bg = CellColor::Indexed((p - 100 + 8) as u8),
+                38 | 48 => {
+                    let color = match it.next() {
+                        Some(5) => it.next().map(|i| CellColor::Indexed(i as u8)),
+                        Some(2) => match (it.next(), it.next(), it.next()) {
+                            (Some(r), Some(g), Some(b)) => {
+                                Some(CellColor::Rgb(r as u8, g as u8, b as u8))
+                            }
+                            _ => None,
+                        },
+                        _ => None,
+                    };
+                    if let Some(color) = color {
+                        match p {
+                            38 => pen.fg = color,
+                            _ => pen.bg = color,
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    pub fn render_row(&self, row: usize, width: usize) -> String {
+        let mut out = String::from("\x1b[0m");
+        let mut current = Pen::default();
+        let cells = self.grid.get(row).map_or(&[][..], Vec::as_slice);
+        for idx in 0..width {
+            let cell = cells.get(idx).copied().unwrap_or_default();
+            if cell.pen != current {
+                out.push_str(&sgr_sequence(&cell.pen));
+                current = cell.pen;
+            }
+            out.push(cell.ch);
+        }
+        out.push_str("\x1b[0m");
+        out
+    }
+
+    pub fn render(&self) -> String {
+        let mut out = String::from("\x1b[?25l\x1b[0m\x1b[H\x1b[2J");
+        let mut current = Pen::default();
+        for (r, row) in self.grid.iter().enumerate() {
+            let end = row
+                .iter()
+                .rposition(|c| *c != Cell::default())
+                .map_or(0, |i| i + 1);
+            if end == 0 {
+                continue;
+            }
+            let _ = write!(out, "\x1b[{};1H", r + 1);
+            for cell in &row[..end] {
+                if cell.pen != current {
+                    out.push_str(&sgr_sequence(&cell.pen));
+                    current = cell.pen;
+                }
+                out.push(cell.ch);
+            }
+        }
+        out.push_str("\x1b[0m");
+        let _ = write!(out, "\x1b[{};{}H", self.cursor.row + 1, self.cursor.col + 1);
+        if self.cursor_visible {
+            out.push_str("\x1b[?25h");
+        }
+        out
+    }
+}
+
+fn sgr_sequence(pen: &Pen) -> String {
+    let mut codes: Vec<String> = vec!["0".to_string()];
+    for (on, code) in [
+        (pen.bold, "1"),
+        (pen.dim, "2"),
+        (pen.italic, "3"),
+        (pen.underline, "4"),
+        (pen.inverse, "7"),
+    ] {
+        if on {
+            codes.push(code.to_string());
+        }
+    }
+    for (color, base) in [(pen.fg, 38), (pen.bg, 48)] {
+        match color {
+            CellColor::Default => {}
+            CellColor::Indexed(i) => codes.push(format!("{base};5;{i}")),
+            CellColor::Rgb(r, g, b) => codes.push(format!("{base};2;{r};{g};{b}")),
+        }
+    }
+    format!("\x1b[{}m", codes.join(";"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shell::vt::VtParser;
+
+    fn feed(screen: &mut Screen, input: &str) {
+        let mut parser = VtParser::new();
+        parser.feed(input.as_bytes(), |action| screen.apply(&action));
+    }
+
+    fn screen(cols: u16, rows: u16, input: &str) -> Screen {
+        let mut screen = Screen::new(cols, rows);
+        feed(&mut screen, input);
+        screen
+    }
+
+    fn text(screen: &Screen) -> Vec<String> {
+        screen
+            .rows()
+            .map(|row| {
+                let line: String = row.iter().map(|c| c.ch).collect();
+                line.trim_end().to_string()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn wraps_at_the_last_column() {
+        let s = screen(5, 3, "abcdefg");
+        assert_eq!(text(&s), ["abcde", "fg", ""]);
+        assert_eq!(s.cursor(), (1, 2));
+
+        let s = screen(5, 3, "abcde\r\nx");
+        assert_eq!(text(&s), ["abcde", "x", ""]);
+
+        let s = screen(5, 3, "\x1b[?7labcdefg");
+        assert_eq!(text(&s), ["abcdg", "", ""]);
+    }
+
+    #[test]
+    fn scrolls_inside_the_scroll_region() {
+        let s = screen(4, 3, "1\r\n2\r\n3\r\n4");
+        assert_eq!(text(&s), ["2", "3", "4"]);
+
+        let mut s = screen(4, 4, "a\r\nb\r\nc\r\nd");
+        feed(&mut s, "\x1b[2;3r");
+        assert_eq!(s.cursor(), (0, 0));
+        feed(&mut s, "\x1b[3;1H\n");
+        assert_eq!(text(&s), ["a", "c", "", "d"]);
+        feed(&mut s, "\x1b[2;1H\x1bM");
+        assert_eq!(text(&s), ["a", "", "c", "d"]);
+        feed(&mut s, "\x1b[2S");
+        assert_eq!(text(&s), ["a", "", "", "d"]);
+    }
+
+    #[test]
+    fn alternate_screen_restores_the_primary() {
+        let mut s = screen(10, 2, "main");
+        feed(&mut s, "\x1b[?1049h");
+        assert!(s.is_alternate());
+        assert_eq!(text(&s), ["", ""]);
+        feed(&mut s, "\x1b[2;1Halt");
+        assert_eq!(text(&s), ["", "alt"]);
+        feed(&mut s, "\x1b[?1049l");
+        assert!(!s.is_alternate());
+        assert_eq!(text(&s), ["main", ""]);
+        assert_eq!(s.cursor(), (0, 4));
+    }
+
+    #[test]
+    fn erases_lines_and_display() {
+        let line = |seq: &str| {
+            text(&screen(6, 1, &format!("abcdef\x1b[1;3H{seq}")))[0].clone()
+        };
+        assert_eq!(line("\x1b[K"), "ab");
+        assert_eq!(line("\x1b[1K"), "   def");
+        assert_eq!(line("\x1b[2K"), "");
+        assert_eq!(line("\x1b[2X"), "ab  ef");
+
+        let display = |seq: &str| {
+            text(&screen(3, 3, &format!("aaa\r\nbbb\r\nccc\x1b[2;2H{seq}")))
+        };
+        assert_eq!(display("\x1b[J"), ["aaa", "b", ""]);
+        assert_eq!(display("\x1b[1J"), ["", "  b", "ccc"]);
+        assert_eq!(display("\x1b[2J"), ["", "", ""]);
+    }
+
+    #[test]
+    fn resize_keeps_the_bottom_rows_and_clamps_the_cursor() {
+        let mut s = screen(6, 4, "1\r\n2\r\n3\r\n4567");
+        s.resize(3, 2);
+        assert_eq!(s.size(), (3, 2));
+        assert_eq!(text(&s), ["3", "456"]);
+        assert_eq!(s.cursor(), (1, 2));
+
+        s.resize(5, 3);
+        assert_eq!(text(&s), ["3", "456", ""]);
+        feed(&mut s, "\r\n\r\nend");
+        assert_eq!(text(&s), ["456", "", "end"]);
+    }
+
+    #[test]
+    fn render_reproduces_the_screen() {
+        let s = screen(10, 3, "\x1b]2;top\x07plain \x1b[1;31mred\x1b[0m\r\n\r\n  x");
+        let out = s.render();
+        assert!(out.starts_with("\x1b[?25l\x1b[0m\x1b[H\x1b[2J"), "{out:?}");
+        assert!(
+            out.contains("\x1b[1;1Hplain \x1b[0;1;38;5;1mred"),
+            "{out:?}"
+        );
+        assert!(!out.contains("\x1b[2;1H"), "{out:?}");
+        assert!(out.ends_with("\x1b[3;4H\x1b[?25h"), "{out:?}");
+        assert_eq!(s.title(), "top");
+
+        let copy = screen(10, 3, &out);
+        assert_eq!(
+            copy.rows().collect::<Vec<_>>(),
+            s.rows().collect::<Vec<_>>()
+        );
+        assert_eq!(copy.cursor(), s.cursor());
+
+        let hidden = screen(4, 1, "\x1b[?25l");
+        assert!(!hidden.cursor_visible());
+        assert!(!hidden.render().contains("\x1b[?25h"));
+    }
+}
@@ -9,6 +9,8 @@ pub enum RemoteBackend {
         port: u16,
         #[serde(default)]
         extra_args: Vec<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        via: Option<String>,
     },
     Telnet {
         #[serde(default = "default_telnet_port")]
@@ -23,11 +25,23 @@ pub enum RemoteBackend {
 pub enum ShellSpec {
     Local {
         program: String,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        tags: Vec<String>,
+        #[serde(default, skip_serializing_if = "is_false")]
+        readonly: bool,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        on_connect: Option<String>,
     },
     Remote {
         host: String,
         #[serde(flatten)]
         backend: RemoteBackend,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        tags: Vec<String>,
+        #[serde(default, skip_serializing_if = "is_false")]
+        readonly: bool,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        on_connect: Option<String>,
     },
 }
 
@@ -44,6 +58,115 @@ impl ShellSpec {
         info!("shellspec_kind_name ok");
         k
     }
+
+    pub fn tags(&self) -> &[String] {
+        match self {
+            ShellSpec::Local { tags, .. } | ShellSpec::Remote { tags, .. } => tags,
+        }
+    }
+
+    pub fn is_readonly(&self) -> bool {
+        match self {
+            ShellSpec::Local { readonly, .. } | ShellSpec::Remote { readonly, .. } => {
+                *readonly
+            }
+        }
+    }
+
+    pub fn on_connect(&self) -> Option<&str> {
+        match self {
+            ShellSpec::Local { on_connect, .. }
+            | ShellSpec::Remote { on_connect, .. } => on_connect.as_deref(),
+        }
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags().iter().any(|t| t == tag)
+    }
+
+    pub fn host(&self) -> Option<&str> {
+        match self {
+            ShellSpec::Remote { host, .. } => Some(host),
+            ShellSpec::Local { .. } => None,
+        }
+    }
+
+    pub fn via(&self) -> Option<&str> {
+        match self {
+            ShellSpec::Remote {
+                backend: RemoteBackend::Ssh { via, .. },
+                ..
+            } => via.as_deref(),
+            _ => None,
+        }
+    }
+
+    pub fn jump_host(&self) -> Option<String> {
+        debug!("shellspec_jump_host start");
+        let j = match self {
+            ShellSpec::Remote {
+                host,
+                backend: RemoteBackend::Ssh { port, .. },
+                ..
+            } => {
+                // `-J` takes `[user@]host[:port]`, so IPv6 literals need brackets.
+                let (user, addr) = match host.rsplit_once('@') {
+                    Some((user, addr)) => (format!("{user}@"), addr),
+                    None => (String::new(), host.as_str()),
+                };
+                if addr.contains(':') && !addr.starts_with('[') {
+                    Some(format!("{user}[{addr}]:{port}"))
+                } else {
+                    Some(format!("{user}{addr}:{port}"))
+                }
+            }
+            _ => None,
+        };
+        info!(present = j.is_some(), "shellspec_jump_host ok");
+        j
+    }
+
+    pub fn extra_args(&self) -> &[String] {
+        match self {
+            ShellSpec::Remote {
+                backend:
+                    RemoteBackend::Ssh { extra_args, .. }
+                    | RemoteBackend::Telnet { extra_args, .. },
+                ..
+            } => extra_args,
+            ShellSpec::Local { .. } => &[],
+        }
+    }
+
+    pub fn with_via(&self, jumps: Option<String>) -> ShellSpec {
+        match self {
+            ShellSpec::Remote {
+                host,
+                backend:
+                    RemoteBackend::Ssh {
+                        port, extra_args, ..
+                    },
+                tags,
+                readonly,
+                on_connect,
+            } => ShellSpec::Remote {
+                host: host.clone(),
+                backend: RemoteBackend::Ssh {
+                    port: *port,
+                    extra_args: extra_args.clone(),
+                    via: jumps,
+                },
+                tags: tags.clone(),
+                readonly: *readonly,
+                on_connect: on_connect.clone(),
+            },
+            other => other.clone(),
+        }
+    }
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
 }
 
 fn default_ssh_port() -> u16 {
@@ -53,3 +176,36 @@ fn default_ssh_port() -> u16 {
 fn default_telnet_port() -> u16 {
     23
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ssh(host: &str) -> ShellSpec {
+        ShellSpec::Remote {
+            host: host.to_string(),
+            backend: RemoteBackend::Ssh {
+                port: 2222,
+                extra_args: Vec::new(),
+                via: None,
+            },
+            tags: Vec::new(),
+            readonly: false,
+            on_connect: None,
+        }
+    }
+
+    #[test]
+    fn jump_hosts_bracket_ipv6_literals() {
+        assert_eq!(ssh("bastion").jump_host().as_deref(), Some("bastion:2222"));
+        assert_eq!(
+            ssh("fd00::1").jump_host().as_deref(),
+            Some("[fd00::1]:2222")
+        );
+        assert_eq!(
+            ssh("ops@fd00::1").jump_host().as_deref(),
+            Some("ops@[fd00::1]:2222")
+        );
+        assert_eq!(ssh("[::1]").jump_host().as_deref(), Some("[::1]:2222"));
+    }
+}
@@ -0,0 +1,476 @@
+use tracing::debug;
+
+use crate::shell::integration;
+
+const ESC: u8 = 0x1b;
+const BEL: u8 = 0x07;
+const CAN: u8 = 0x18;
+const SUB: u8 = 0x1a;
+const DEL: u8 = 0x7f;
+const ST_FINAL: u8 = b'\\';
+const MAX_PARAMS: usize = 32;
+const MAX_INTERMEDIATES: usize = 2;
+const MAX_OSC_LEN: usize = 4096;
+
+const OSC_ICON_AND_TITLE: &str = "0";
+const OSC_TITLE: &str = "2";
+const OSC_CWD: &str = "7";
+const OSC_PROMPT: &str = "133";
+const DSR_ACTION: char = 'n';
+const DSR_CURSOR: u16 = 6;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Csi {
+    pub private: Option<u8>,
+    pub params: Vec<u16>,
+    pub intermediates: Vec<u8>,
+    pub action: char,
+}
+
+impl Csi {
+    pub fn param(&self, idx: usize, default: u16) -> u16 {
+        match self.params.get(idx) {
+            Some(0) | None => default,
+            Some(v) => *v,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Action {
+    Print(char),
+    Control(u8),
+    Esc { intermediates: Vec<u8>, byte: u8 },
+    Csi(Csi),
+    Osc(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PromptMark {
+    PromptStart,
+    CommandStart,
+    OutputStart,
+    Finished(Option<i32>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TermEvent {
+    Title(String),
+    Cwd(String),
+    Prompt(PromptMark),
+    Bell,
+    CursorQuery,
+}
+
+impl TermEvent {
+    pub fn from_action(action: &Action) -> Option<Self> {
+        match action {
+            Action::Control(BEL) => Some(TermEvent::Bell),
+            Action::Csi(csi)
+                if csi.action == DSR_ACTION
+                    && csi.private.is_none()
+                    && csi.param(0, 0) == DSR_CURSOR =>
+            {
+                Some(TermEvent::CursorQuery)
+            }
+            Action::Osc(body) => {
+                let (code, data) = body.split_once(';').unwrap_or((body, ""));
+                match code {
+                    OSC_ICON_AND_TITLE | OSC_TITLE => {
+                        Some(TermEvent::Title(data.to_string()))
+                    }
+                    OSC_CWD => integration::parse_cwd(data).map(TermEvent::Cwd),
+                    OSC_PROMPT => parse_prompt_mark(data).map(TermEvent::Prompt),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+fn parse_prompt_mark(data: &str) -> Option<PromptMark> {
+    let (kind, rest) = data.split_once(';').unwrap_or((data, ""));
+    match kind {
+        "A" => Some(PromptMark::PromptStart),
+        "B" => Some(PromptMark::CommandStart),
+        "C" => Some(PromptMark::OutputStart),
+        "D" => Some(PromptMark::Finished(rest.parse().ok())),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum State {
+    #[default]
+    Ground,
+    Escape,
+    Csi,
+    CsiIgnore,
+    Osc,
+    OscEscape,
+    Str,
+    StrEscape,
+}
+
+#[derive(Debug, Default)]
+pub struct VtParser {
+    state: State,
+    utf8: Vec<u8>,
+    utf8_len: usize,
+    private: Option<u8>,
+    params: Vec<u16>,
+    param: Option<u16>,
+    intermediates: Vec<u8>,
+    osc: Vec<u8>,
+}
+
+impl VtParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn feed(&mut self, bytes: &[u8], mut emit: impl FnMut(Action)) {
+        debug!(len = bytes.len(), "vt_parser_feed");
+        for &byte in bytes {
+            self.advance(byte, &mut emit);
+        }
+    }
+
+    fn advance(&mut self, byte: u8, emit: &mut impl FnMut(Action)) {
+        if self.utf8_len > 0 {
+            if byte & 0xc0 == 0x80 {
+                self.utf8.push(byte);
+                if self.utf8.len() == self.utf8_len {
+                    let ch = std::str::from_utf8(&self.utf8)
+                        .ok()
+                        .and_then(|s| s.chars().next())
+                        .unwrap_or(char::REPLACEMENT_CHARACTER);
+                    self.utf8.clear();
+                    self.utf8_len = 0;
+                    emit(Action::Print(ch));
+                }
+                return;
+            }
+            self.utf8.clear();
+            self.utf8_len = 0;
+            emit(Action::Print(char::REPLACEMENT_CHARACTER));
+        }
+
+        if matches!(byte, CAN | SUB) {
+            self.state = State::Ground;
+            return;
+        }
+        match self.state {
+            State::Ground => self.ground(byte, emit),
+            State::Escape => self.escape(byte, emit),
+            State::Csi | State::CsiIgnore => self.csi(byte, emit),
+            State::Osc => match byte {
+                BEL => self.dispatch_osc(emit),
+                ESC => self.state = State::OscEscape,
+                _ if self.osc.len() < MAX_OSC_LEN => self.osc.push(byte),
+                _ => {}
+            },
+            State::OscEscape => {
+                self.dispatch_osc(emit);
+                if byte != ST_FINAL {
+                    self.enter_escape();
+                    self.escape(byte, emit);
+                }
+            }
+            State::Str => {
+                if byte == ESC {
+                    self.state = State::StrEscape;
+                }
+            }
+            State::StrEscape => {
+                self.state = match byte {
+                    ST_FINAL => State::Ground,
+                    ESC => State::StrEscape,
+                    _ => State::Str,
+                };
+            }
+        }
+    }
+
+    fn ground(&mut self, byte: u8, emit: &mut impl FnMut(Action)) {
+        match byte {
+            ESC => self.enter_escape(),
+            0x00..=0x1f => emit(Action::Control(byte)),
+            DEL => {}
+            0x20..=0x7e => emit(Action::Print(byte as char)),
+            0xc2..=0xdf => self.start_utf8(byte, 2),
+            0xe0..=0xef => self.start_utf8(byte, 3),
+            0xf0..=0xf4 => self.start_utf8(byte, 4),
+            _ => emit(Action::Print(char::REPLACEMENT_CHARACTER)),
+        }
+    }
+
+    fn start_utf8(&mut self, byte: u8, len: usize) {
+        self.utf8.push(byte);
+        self.utf8_len = len;
+    }
+
+    fn enter_escape(&mut self) {
+        self.state = State::Escape;
+        self.intermediates.clear();
+    }
+
+    fn escape(&mut self, byte: u8, emit: &mut impl FnMut(Action)) {
+        match byte {
+            ESC => self.enter_escape(),
+            0x00..=0x1f => emit(Action::Control(byte)),
+            0x20..=0x2f => {
+                if self.intermediates.len() < MAX_INTERMEDIATES {
+                    self.intermediates.push(byte);
+                }
+            }
+            b'[' if self.intermediates.is_empty() => {
+                self.state = State::Csi;
+                self.private = None;
+                self.params.clear();
+                self.param = None;
+            }
+            b']' if self.intermediates.is_empty() => {
+                self.state = State::Osc;
+                self.osc.clear();
+            }
+            b'P' | b'X' | b'^' | b'_' if self.intermediates.is_empty() => {
+                self.state = State::Str;
+            }
+            0x30..=0x7e => {
+                self.state = State::Ground;
+                emit(Action::Esc {
+                    intermediates: std::mem::take(&mut self.intermediates),
+                    byte,
+                });
+            }
+            _ => self.state = State::Ground,
+        }
+    }
+
+    fn csi(&mut self, byte: u8, emit: &mut impl FnMut(Action)) {
+        match byte {
+            ESC => self.enter_escape(),
+            0x00..=0x1f => emit(Action::Control(byte)),
+            b'0'..=b'9' => {
+                let digit = u16::from(byte - b'0');
+                let value = self.param.unwrap_or(0);
+                self.param = Some(value.saturating_mul(10).saturating_add(digit));
+            }
+            b';' | b':' => {
+                let value = self.param.take().unwrap_or(0);
+                if self.params.len() < MAX_PARAMS {
+                    self.params.push(value);
+                }
+            }
+            b'<'..=b'?' => {
+                if self.params.is_empty()
+                    && self.param.is_none()
+                    && self.private.is_none()
+                {
+                    self.private = Some(byte);
+                } else {
+                    self.state = State::CsiIgnore;
+                }
+            }
+            0x20..=0x2f => {
+                if self.intermediates.len() < MAX_INTERMEDIATES {
+                    self.intermediates.push(byte);
+                } else {
+                    self.state = State::CsiIgnore;
+                }
+            }
+            0x40..=0x7e => {
+                let ignore = self.state == State::CsiIgnore;
+                self.state = State::Ground;
+                if ignore {
+                    return;
+                }
+                self.push_param();
+                emit(Action::Csi(Csi {
+                    private: self.private.take(),
+                    params: std::mem::take(&mut self.params),
+                    intermediates: std::mem::take(&mut self.intermediates),
+                    action: byte as char,
+                }));
+            }
+            _ => self.state = State::CsiIgnore,
+        }
+    }
+
+    fn push_param(&mut self) {
+        let value = self.param.take();
+        if (value.is_some() || !self.params.is_empty())
+            && self.params.len() < MAX_PARAMS
+        {
+            self.params.push(value.unwrap_or(0));
+        }
+    }
+
+    fn dispatch_osc(&mut self, emit: &mut impl FnMut(Action)) {
+        self.state = State::Ground;
+        let body = String::from_utf8_lossy(&self.osc).into_owned();
+        self.osc.clear();
+        emit(Action::Osc(body));
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Utf8Decoder {
+    pending: Vec<u8>,
+}
+
+impl Utf8Decoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn decode(&mut self, bytes: &[u8]) -> String {
+        self.pending.extend_from_slice(bytes);
+        let keep = incomplete_tail(&self.pending);
+        let split = self.pending.len() - keep;
+        let text = String::from_utf8_lossy(&self.pending[..split]).into_owned();
+        self.pending.drain(..split);
+        text
+    }
+}
+
+fn incomplete_tail(bytes: &[u8]) -> usize {
+    for back in 1..=bytes.len().min(3) {
+        let byte = bytes[bytes.len() - back];
+        if byte & 0xc0 == 0x80 {
+            continue;
+        }
+        let need = match byte {
+            0xc2..=0xdf => 2,
+            0xe0..=0xef => 3,
+            0xf0..=0xf4 => 4,
+            _ => return 0,
+        };
+        return if need > back { back } else { 0 };
+    }
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn actions(chunks: &[&[u8]]) -> Vec<Action> {
+        let mut parser = VtParser::new();
+        let mut out = Vec::new();
+        for chunk in chunks {
+            parser.feed(chunk, |a| out.push(a));
+        }
+        out
+    }
+
+    fn csi(input: &str) -> Csi {
+        match actions(&[input.as_bytes()]).as_slice() {
+            [Action::Csi(csi)] => csi.clone(),
+            other => panic!("expected one CSI for {input:?}, got {other:?}"),
+        }
+    }
+
+    fn printed(actions: &[Action]) -> String {
+        actions
+            .iter()
+            .filter_map(|a| match a {
+                Action::Print(c) => Some(*c),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn utf8_split_across_chunks() {
+        let text = "é€😀".as_bytes();
+        let chunks: Vec<&[u8]> = text.chunks(1).collect();
+        assert_eq!(printed(&actions(&chunks)), "é€😀");
+
+        let mut decoder = Utf8Decoder::new();
+        let mut decoded = String::new();
+        for chunk in text.chunks(3) {
+            decoded.push_str(&decoder.decode(chunk));
+        }
+        assert_eq!(decoded, "é€😀");
+        assert_eq!(decoder.decode(&text[..1]), "");
+        assert_eq!(decoder.decode(&text[1..2]), "é");
+    }
+
+    #[test]
+    fn broken_utf8_prints_replacement() {
+        let out = actions(&[b"\xc3a\xffb"]);
+        assert_eq!(printed(&out), "\u{fffd}a\u{fffd}b");
+    }
+
+    #[test]
+    fn csi_params() {
+        assert_eq!(csi("\x1b[H").params, Vec::<u16>::new());
+        assert_eq!(csi("\x1b[12;40H").params, [12, 40]);
+        assert_eq!(csi("\x1b[;5H").params, [0, 5]);
+        assert_eq!(csi("\x1b[3;H").params, [3, 0]);
+        assert_eq!(csi("\x1b[38:5:196m").params, [38, 5, 196]);
+        assert_eq!(csi("\x1b[99999m").params, [u16::MAX]);
+
+        let cup = csi("\x1b[;5H");
+        assert_eq!((cup.param(0, 1), cup.param(1, 1)), (1, 5));
+        assert_eq!(cup.action, 'H');
+
+        let private = csi("\x1b[?1049h");
+        assert_eq!(private.private, Some(b'?'));
+        assert_eq!(private.params, [1049]);
+        assert_eq!(csi("\x1b[2 q").intermediates, b" ");
+    }
+
+    #[test]
+    fn malformed_csi_is_ignored() {
+        let out = actions(&[b"\x1b[1?2hok"]);
+        assert_eq!(out, [Action::Print('o'), Action::Print('k')]);
+        let out = actions(&[b"\x1b[12", b"\x18x"]);
+        assert_eq!(out, [Action::Print('x')]);
+    }
+
+    #[test]
+    fn osc_terminates_on_bel_st_or_next_escape() {
+        let title = Action::Osc("2;hi".to_string());
+        assert_eq!(actions(&[b"\x1b]2;hi\x07"]), std::slice::from_ref(&title));
+        assert_eq!(
+            actions(&[b"\x1b]2;", b"hi\x1b", b"\\x"]),
+            [title.clone(), Action::Print('x')]
+        );
+        let out = actions(&[b"\x1b]2;hi\x1b[1m"]);
+        assert!(
+            matches!(out.as_slice(), [a, Action::Csi(c)] if *a == title && c.action == 'm')
+        );
+        assert_eq!(actions(&[b"\x1b]2;hi\x18x"]), [Action::Print('x')]);
+        assert_eq!(actions(&[b"\x1bPq#0;1\x1b\\x"]), [Action::Print('x')]);
+    }
+
+    #[test]
+    fn osc_bodies_become_term_events() {
+        let event = |body: &str| TermEvent::from_action(&Action::Osc(body.to_string()));
+        assert_eq!(
+            event("0;build"),
+            Some(TermEvent::Title("build".to_string()))
+        );
+        assert_eq!(
+            event("7;file://host/tmp/a%20b"),
+            Some(TermEvent::Cwd("/tmp/a b".to_string()))
+        );
+        assert_eq!(
+            event("133;D;2"),
+            Some(TermEvent::Prompt(PromptMark::Finished(Some(2))))
+        );
+        assert_eq!(
+            event("133;A"),
+            Some(TermEvent::Prompt(PromptMark::PromptStart))
+        );
+        assert_eq!(event("52;c;aGk="), None);
+        assert_eq!(
+            TermEvent::from_action(&Action::Csi(csi("\x1b[6n"))),
+            Some(TermEvent::CursorQuery)
+        );
+    }
+}
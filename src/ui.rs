@@ -1,16 +1,45 @@
-use std::io::{Write, stdout};
+#[cfg(feature = "mock-shell")]
+use std::cell::RefCell;
+use std::io::{IsTerminal, Write, stdout};
 
 use tracing::{debug, info};
 
 use crate::error::{Result, UiError};
 
+pub mod confirm;
 pub mod editor;
 pub mod prefix_menu;
+pub mod secret;
+pub mod tui;
 
 pub use editor::prompt::PshPrompt;
 
+#[cfg(feature = "mock-shell")]
+tokio::task_local! {
+    static CAPTURE: RefCell<String>;
+}
+
+#[cfg(feature = "mock-shell")]
+pub async fn capture_output<F: Future>(f: F) -> (F::Output, String) {
+    CAPTURE
+        .scope(RefCell::new(String::new()), async {
+            let out = f.await;
+            (out, CAPTURE.with(|c| c.take()))
+        })
+        .await
+}
+
+#[cfg(feature = "mock-shell")]
+fn captured(msg: &str) -> bool {
+    CAPTURE.try_with(|c| c.borrow_mut().push_str(msg)).is_ok()
+}
+
 pub fn ui_print(msg: &str) -> Result<()> {
     debug!(len = msg.len(), "ui_print start");
+    #[cfg(feature = "mock-shell")]
+    if captured(msg) {
+        return Ok(());
+    }
     stdout()
         .write_all(msg.as_bytes())
         .map_err(UiError::IoWrite)?;
@@ -20,6 +49,10 @@ pub fn ui_print(msg: &str) -> Result<()> {
 
 pub fn ui_println(msg: &str) -> Result<()> {
     debug!(len = msg.len(), "ui_print start");
+    #[cfg(feature = "mock-shell")]
+    if captured(&format!("{msg}\n")) {
+        return Ok(());
+    }
     let mut out = stdout();
     out.write_all(b"\r").map_err(UiError::IoWrite)?;
     out.write_all(msg.as_bytes()).map_err(UiError::IoWrite)?;
@@ -35,3 +68,14 @@ pub fn ui_flush() -> Result<()> {
     info!("ui_flush ok");
     Ok(())
 }
+
+pub fn query_term_size() -> Result<Option<(u16, u16)>> {
+    debug!("query_term_size start");
+    if !stdout().is_terminal() {
+        info!("query_term_size not_a_terminal");
+        return Ok(None);
+    }
+    let (cols, rows) = crossterm::terminal::size().map_err(UiError::ResizeRead)?;
+    info!(cols, rows, "query_term_size ok");
+    Ok((cols > 0 && rows > 0).then_some((cols, rows)))
+}
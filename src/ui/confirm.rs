@@ -0,0 +1,31 @@
+use std::io::stdin;
+
+use tokio::task;
+use tracing::{debug, info, warn};
+
+use crate::{
+    error::{Result, UiError},
+    repl::policy::Confirmer,
+    ui::{ui_flush, ui_print, ui_println},
+};
+
+pub struct StdinConfirmer;
+
+impl Confirmer for StdinConfirmer {
+    fn confirm(&self, reason: &str, expected: &str) -> Result<bool> {
+        debug!(expected = expected, "stdin_confirm start");
+        ui_println(&format!("confirmation required: {reason}"))?;
+        ui_print(&format!("\rtype `{expected}` to send it anyway: "))?;
+        ui_flush()?;
+        let mut answer = String::new();
+        task::block_in_place(|| stdin().read_line(&mut answer))
+            .map_err(UiError::IoRead)?;
+        let confirmed = answer.trim() == expected;
+        if confirmed {
+            info!(expected = expected, "stdin_confirm ok");
+        } else {
+            warn!(expected = expected, "stdin_confirm declined");
+        }
+        Ok(confirmed)
+    }
+}
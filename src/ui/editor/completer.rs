@@ -0,0 +1,95 @@
+use std::sync::{Arc, RwLock};
+
+use reedline::{Completer, Span, Suggestion};
+use tracing::{debug, info};
+
+#[derive(Debug, Clone)]
+pub struct CompletionEntry {
+    pub name: String,
+    pub hints: Vec<String>,
+    pub prefix: bool,
+}
+
+#[derive(Clone, Default)]
+pub struct CompletionIndex {
+    entries: Arc<RwLock<Vec<CompletionEntry>>>,
+}
+
+impl CompletionIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn replace(&self, entries: Vec<CompletionEntry>) {
+        debug!(count = entries.len(), "completion_index_replace start");
+        if let Ok(mut w) = self.entries.write() {
+            *w = entries;
+        }
+        info!("completion_index_replace ok");
+    }
+
+    fn snapshot(&self) -> Vec<CompletionEntry> {
+        self.entries.read().map(|g| g.clone()).unwrap_or_default()
+    }
+}
+
+pub struct PshCompleter {
+    index: CompletionIndex,
+}
+
+impl PshCompleter {
+    pub fn new(index: CompletionIndex) -> Self {
+        Self { index }
+    }
+}
+
+impl Completer for PshCompleter {
+    fn complete(&mut self, line: &str, pos: usize) -> Vec<Suggestion> {
+        debug!(len = line.len(), pos, "psh_complete start");
+        let head = &line[..pos.min(line.len())];
+        let entries = self.index.snapshot();
+
+        let suggestions: Vec<Suggestion> = match head.split_once(':') {
+            None => entries
+                .iter()
+                .filter(|e| e.name.starts_with(head.trim_start()))
+                .map(|e| Suggestion {
+                    value: if e.prefix {
+                        format!("{}: ", e.name)
+                    } else {
+                        e.name.clone()
+                    },
+                    description: (!e.prefix).then(|| "alias".to_string()),
+                    span: Span::new(head.len() - head.trim_start().len(), pos),
+                    append_whitespace: !e.prefix,
+                    ..Default::default()
+                })
+                .collect(),
+            Some((prefix, rest)) => {
+                let hints = entries
+                    .iter()
+                    .find(|e| e.prefix && e.name == prefix.trim())
+                    .map(|e| e.hints.clone())
+                    .unwrap_or_default();
+                let word = rest.trim_start();
+                if word.contains(char::is_whitespace) {
+                    Vec::new()
+                } else {
+                    let start = pos - word.len();
+                    hints
+                        .into_iter()
+                        .filter(|h| h.starts_with(word))
+                        .map(|h| Suggestion {
+                            value: if rest.is_empty() { format!(" {h}") } else { h },
+                            span: Span::new(start, pos),
+                            append_whitespace: true,
+                            ..Default::default()
+                        })
+                        .collect()
+                }
+            }
+        };
+        debug!(count = suggestions.len(), "psh_complete ok");
+        suggestions
+    }
+}
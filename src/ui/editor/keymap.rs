@@ -1,14 +1,39 @@
 use reedline::{
-    self, Reedline, ReedlineEvent, default_emacs_keybindings,
+    self, ColumnarMenu, KeyCode, KeyModifiers, Keybindings, MenuBuilder, Reedline,
+    ReedlineEvent, ReedlineMenu, default_emacs_keybindings,
     default_vi_insert_keybindings, default_vi_normal_keybindings,
 };
 use tracing::{debug, info};
 
-use crate::runtime::{ReplSettings, config};
+use crate::{
+    runtime::{ReplSettings, config},
+    ui::editor::completer::{CompletionIndex, PshCompleter},
+};
 
 pub const MENU_SENTINEL: &str = "__PSH_MENU__";
+const COMPLETION_MENU: &str = "completion_menu";
+
+fn add_completion_binding(kb: &mut Keybindings) {
+    kb.add_binding(
+        KeyModifiers::NONE,
+        KeyCode::Tab,
+        ReedlineEvent::UntilFound(vec![
+            ReedlineEvent::Menu(COMPLETION_MENU.to_string()),
+            ReedlineEvent::MenuNext,
+        ]),
+    );
+}
 
-pub fn make_reedline(settings: &ReplSettings) -> Reedline {
+fn with_completion(rl: Reedline, completions: CompletionIndex) -> Reedline {
+    let menu = ColumnarMenu::default().with_name(COMPLETION_MENU);
+    rl.with_completer(Box::new(PshCompleter::new(completions)))
+        .with_menu(ReedlineMenu::EngineCompleter(Box::new(menu)))
+}
+
+pub fn make_reedline(
+    settings: &ReplSettings,
+    completions: CompletionIndex,
+) -> Reedline {
     debug!("make_reedline start");
     match settings.edit_menu {
         config::EditMode::Emacs => {
@@ -18,8 +43,12 @@ pub fn make_reedline(settings: &ReplSettings) -> Reedline {
                 settings.menu_key.0,
                 ReedlineEvent::ExecuteHostCommand(MENU_SENTINEL.into()),
             );
+            add_completion_binding(&mut kb);
             let edit_mode = Box::new(reedline::Emacs::new(kb));
-            let rl = Reedline::create().with_edit_mode(edit_mode);
+            let rl = with_completion(
+                Reedline::create().with_edit_mode(edit_mode),
+                completions,
+            );
             info!("make_reedline emacs ok");
             rl
         }
@@ -36,8 +65,12 @@ pub fn make_reedline(settings: &ReplSettings) -> Reedline {
                 settings.menu_key.0,
                 ReedlineEvent::ExecuteHostCommand(MENU_SENTINEL.into()),
             );
+            add_completion_binding(&mut insert);
             let edit_mode = Box::new(reedline::Vi::new(insert, normal));
-            let rl = Reedline::create().with_edit_mode(edit_mode);
+            let rl = with_completion(
+                Reedline::create().with_edit_mode(edit_mode),
+                completions,
+            );
             info!("make_reedline emacs ok");
             rl
         }
@@ -1,27 +1,36 @@
-use std::borrow::Cow;
+use std::{borrow::Cow, env, time::Duration};
 
 use nu_ansi_term::{Color, Style};
-use reedline::{Prompt, PromptEditMode, PromptHistorySearch};
+use reedline::{Prompt, PromptEditMode, PromptHistorySearch, PromptViMode};
 use tracing::{debug, info};
 
 use crate::{
     registry::{self, Registry},
-    repl::ModeState,
+    repl::{LockState, ModeState, SessionStatus},
     runtime::ReplSettings,
     shell::ShellSpec,
 };
 
 const ANSI_RESET: &str = "\x1b[0m";
+const LOCKED_MARKER: &str = "[locked]";
+const HOME_MARKER: &str = "~";
 
 #[derive(Clone)]
 pub struct PshPrompt {
     mode: Option<ModeState>,
+    locks: Option<LockState>,
+    status: Option<SessionStatus>,
     registry: Option<Registry>,
+    template_left: String,
+    template_right: String,
+    vi_insert_indicator: String,
+    vi_normal_indicator: String,
     color_prompt: Color,
     color_builtin: Color,
     color_local: Color,
     color_remote: Color,
     color_unknown: Color,
+    color_disconnected: Color,
 }
 
 impl PshPrompt {
@@ -29,12 +38,19 @@ impl PshPrompt {
         debug!("psh_prompt_new start");
         let s = Self {
             mode: None,
+            locks: None,
+            status: None,
             registry: None,
+            template_left: settings.prompt.clone(),
+            template_right: settings.right_prompt.clone(),
+            vi_insert_indicator: settings.vi_insert_indicator.clone(),
+            vi_normal_indicator: settings.vi_normal_indicator.clone(),
             color_prompt: settings.color_prompt,
             color_builtin: settings.color_builtin,
             color_local: settings.color_local,
             color_remote: settings.color_remote,
             color_unknown: settings.color_unknown,
+            color_disconnected: settings.color_disconnected,
         };
         info!("psh_prompt_new ok");
         s
@@ -46,6 +62,27 @@ impl PshPrompt {
         info!("psh_prompt_set_mode_state ok");
     }
 
+    pub fn set_lock_state(&mut self, locks: LockState) {
+        debug!("psh_prompt_set_lock_state start");
+        self.locks = Some(locks);
+        info!("psh_prompt_set_lock_state ok");
+    }
+
+    pub fn set_session_status(&mut self, status: SessionStatus) {
+        debug!("psh_prompt_set_session_status start");
+        self.status = Some(status);
+        info!("psh_prompt_set_session_status ok");
+    }
+
+    fn is_locked(&self, name: &str) -> bool {
+        self.locks.as_ref().is_some_and(|l| l.is_locked(name))
+            || self
+                .registry
+                .as_ref()
+                .and_then(|r| r.get_shell_spec(name))
+                .is_some_and(|s| s.is_readonly())
+    }
+
     pub fn set_registry(&mut self, reg: Registry) {
         debug!("psh_prompt_set_registry start");
         self.registry = Some(reg);
@@ -53,6 +90,13 @@ impl PshPrompt {
     }
 
     fn color_for_mode(&self, name: &str) -> Color {
+        if self
+            .status
+            .as_ref()
+            .is_some_and(|s| s.is_disconnected(name))
+        {
+            return self.color_disconnected;
+        }
         match &self.registry {
             Some(reg) => match reg.get_entry(name) {
                 Some(registry::Entry::Builtin) => self.color_builtin,
@@ -67,41 +111,146 @@ impl PshPrompt {
             None => self.color_unknown,
         }
     }
+    fn placeholder(&self, key: &str, current: Option<&str>) -> Option<(String, Color)> {
+        let last = current.and_then(|n| self.status.as_ref()?.last(n));
+        let (running, disconnected) = self
+            .status
+            .as_ref()
+            .map(SessionStatus::counts)
+            .unwrap_or_default();
+        let value = match key {
+            "mode" => current.map(|name| {
+                let label = match self.is_locked(name) {
+                    true => format!("{name} {LOCKED_MARKER}"),
+                    false => name.to_string(),
+                };
+                (label, self.color_for_mode(name))
+            }),
+            "host" => current.and_then(|name| {
+                let spec = self.registry.as_ref()?.get_shell_spec(name)?;
+                Some((spec.host()?.to_string(), self.color_for_mode(name)))
+            }),
+            "cwd" => match current {
+                Some(name) => self
+                    .status
+                    .as_ref()
+                    .and_then(|s| s.cwd(name))
+                    .map(|dir| (display_dir(&dir), self.color_prompt)),
+                None => env::current_dir().ok().map(|dir| {
+                    (display_dir(&dir.display().to_string()), self.color_prompt)
+                }),
+            },
+            "status" => last.and_then(|l| l.exit_status).map(|code| match code {
+                0 => ("ok".to_string(), Color::Green),
+                code => (format!("exit {code}"), Color::Red),
+            }),
+            "duration" => last
+                .and_then(|l| l.duration)
+                .map(|d| (format_duration(d), self.color_prompt)),
+            "sessions" => {
+                let mut parts = Vec::new();
+                if running > 0 {
+                    parts.push(format!("{running} running"));
+                }
+                if disconnected > 0 {
+                    parts.push(format!("{disconnected} disconnected"));
+                }
+                Some((parts.join(", "), self.color_prompt))
+            }
+            "running" => Some((running.to_string(), self.color_prompt)),
+            "disconnected" => Some((disconnected.to_string(), self.color_prompt)),
+            _ => return None,
+        };
+        Some(value.unwrap_or_else(|| (String::new(), self.color_prompt)))
+    }
+
+    // An empty placeholder also swallows the literal text after it up to the next
+    // space, so `{mode}: ` disappears entirely when no session is selected.
+    fn render_template(&self, template: &str) -> String {
+        let current = self.mode.as_ref().and_then(|m| m.get_current());
+        let literal = Style::new().fg(self.color_prompt);
+        let mut out = String::new();
+        let mut text = String::new();
+        let mut skip = false;
+        let mut rest = template;
+        while !rest.is_empty() {
+            if let Some(body) = rest.strip_prefix('{')
+                && let Some(end) = body.find('}')
+                && let Some((value, color)) =
+                    self.placeholder(&body[..end], current.as_deref())
+            {
+                if !text.is_empty() {
+                    out.push_str(&literal.paint(std::mem::take(&mut text)).to_string());
+                }
+                skip = value.is_empty();
+                if !skip {
+                    out.push_str(&Style::new().fg(color).paint(value).to_string());
+                }
+                rest = &body[end + 1..];
+                continue;
+            }
+            let Some(ch) = rest.chars().next() else {
+                break;
+            };
+            rest = &rest[ch.len_utf8()..];
+            if skip {
+                skip = !ch.is_whitespace();
+                continue;
+            }
+            text.push(ch);
+        }
+        if !text.is_empty() {
+            out.push_str(&literal.paint(text).to_string());
+        }
+        out
+    }
+}
+
+fn display_dir(dir: &str) -> String {
+    match env::var("HOME") {
+        Ok(home) if !home.is_empty() && dir.starts_with(&home) => {
+            format!("{HOME_MARKER}{}", &dir[home.len()..])
+        }
+        _ => dir.to_string(),
+    }
+}
+
+fn format_duration(d: Duration) -> String {
+    let secs = d.as_secs();
+    match secs {
+        0 => format!("{}ms", d.as_millis()),
+        1..60 => format!("{:.1}s", d.as_secs_f64()),
+        60..3_600 => format!("{}m{:02}s", secs / 60, secs % 60),
+        _ => format!("{}h{:02}m", secs / 3_600, secs % 3_600 / 60),
+    }
 }
 
 impl Prompt for PshPrompt {
     fn render_prompt_left(&self) -> Cow<'_, str> {
         debug!("psh_prompt_render_left start");
-
-        let prefix = Style::new()
-            .fg(self.color_prompt)
-            .paint("psh> ")
-            .to_string();
-
-        let current = self.mode.as_ref().and_then(|m| m.get_current());
-
-        let suffix = match current {
-            Some(name) => {
-                let color = self.color_for_mode(&name);
-                Style::new()
-                    .fg(color)
-                    .paint(format!("{name}: "))
-                    .to_string()
-            }
-            None => String::new(),
-        };
-
-        let out = format!("{ANSI_RESET}{prefix}{suffix}");
+        let out = format!("{ANSI_RESET}{}", self.render_template(&self.template_left));
         debug!("psh_prompt_render_left ok");
         Cow::Owned(out)
     }
 
     fn render_prompt_right(&self) -> Cow<'_, str> {
-        Cow::Borrowed("")
+        Cow::Owned(
+            self.render_template(&self.template_right)
+                .trim_end()
+                .to_string(),
+        )
     }
 
-    fn render_prompt_indicator(&self, _mode: PromptEditMode) -> Cow<'_, str> {
-        Cow::Borrowed("")
+    fn render_prompt_indicator(&self, mode: PromptEditMode) -> Cow<'_, str> {
+        match mode {
+            PromptEditMode::Vi(PromptViMode::Insert) => {
+                Cow::Borrowed(self.vi_insert_indicator.as_str())
+            }
+            PromptEditMode::Vi(PromptViMode::Normal) => {
+                Cow::Borrowed(self.vi_normal_indicator.as_str())
+            }
+            _ => Cow::Borrowed(""),
+        }
     }
 
     fn render_prompt_multiline_indicator(&self) -> Cow<'_, str> {
@@ -13,7 +13,7 @@ use crate::{
     ui::PshPrompt,
 };
 
-fn color_for_entry(entry: &registry::Entry, settings: &ReplSettings) -> Color {
+pub fn color_for_entry(entry: &registry::Entry, settings: &ReplSettings) -> Color {
     match entry {
         registry::Entry::Builtin => settings.color_builtin,
         registry::Entry::Shell(ShellSpec::Local { .. }) => settings.color_local,
@@ -23,6 +23,7 @@ fn color_for_entry(entry: &registry::Entry, settings: &ReplSettings) -> Color {
 
 fn print_menu(
     entries: &[(String, registry::Entry, bool)],
+    router: &Router,
     settings: &ReplSettings,
     current: Option<&str>,
 ) -> Result<()> {
@@ -40,7 +41,12 @@ fn print_menu(
             " "
         };
         let run = if *running { "*" } else { " " };
-        let line = format!("{:>2}. {} {} {}\r\n", i + 1, marker, run, styled);
+        let lock = if router.is_locked(name) {
+            " [locked]"
+        } else {
+            ""
+        };
+        let line = format!("{:>2}. {} {} {}{}\r\n", i + 1, marker, run, styled, lock);
         out.write_all(line.as_bytes()).map_err(UiError::IoWrite)?;
     }
     out.flush().map_err(UiError::IoWrite)?;
@@ -60,11 +66,12 @@ pub async fn choose_prefix(
         return Ok(None);
     }
 
-    print_menu(&entries, settings, current)?;
+    print_menu(&entries, router, settings, current)?;
 
     let mut rl = Reedline::create();
     let mut menu_prompt = PshPrompt::new(settings);
     menu_prompt.set_registry(router.get_registry_clone());
+    menu_prompt.set_lock_state(router.lock_state());
 
     loop {
         let sig = rl.read_line(&menu_prompt);
@@ -97,7 +104,7 @@ pub async fn choose_prefix(
                 let mut out = stdout();
                 out.write_all(b"\r\n").map_err(UiError::IoWrite)?;
                 out.flush().map_err(UiError::IoWrite)?;
-                print_menu(&entries, settings, current)?;
+                print_menu(&entries, router, settings, current)?;
             }
             Ok(Signal::CtrlC) => {
                 info!("prefix_menu ctr_c_cancel");
@@ -0,0 +1,73 @@
+use std::io::{IsTerminal, stdin};
+
+use crossterm::{
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    terminal,
+};
+use tokio::task;
+use tracing::{debug, info, warn};
+
+use crate::{
+    error::{Result, UiError},
+    repl::secrets::SecretPrompter,
+    shell::Secret,
+    ui::{ui_flush, ui_print, ui_println},
+};
+
+pub struct TerminalSecretPrompter;
+
+impl SecretPrompter for TerminalSecretPrompter {
+    fn prompt(&self, name: &str, prompt: &str) -> Result<Option<Secret>> {
+        debug!(name = name, "terminal_secret_prompt start");
+        if !stdin().is_terminal() {
+            warn!(name = name, "terminal_secret_prompt no terminal");
+            return Ok(None);
+        }
+        if terminal::is_raw_mode_enabled().unwrap_or(false) {
+            warn!(name = name, "terminal_secret_prompt terminal busy");
+            return Ok(None);
+        }
+        ui_print(&format!("\r[{name}] {prompt} "))?;
+        ui_flush()?;
+        terminal::enable_raw_mode().map_err(|e| UiError::RawModeEnable(e.into()))?;
+        let typed = task::block_in_place(read_hidden);
+        terminal::disable_raw_mode().map_err(|e| UiError::RawModeDisable(e.into()))?;
+        ui_println("")?;
+        let secret = typed?;
+        match &secret {
+            Some(_) => info!(name = name, "terminal_secret_prompt ok"),
+            None => warn!(name = name, "terminal_secret_prompt canceled"),
+        }
+        Ok(secret)
+    }
+}
+
+fn read_hidden() -> Result<Option<Secret>> {
+    let mut typed = Secret::new(String::new());
+    loop {
+        let ev = event::read().map_err(UiError::IoRead)?;
+        let Event::Key(KeyEvent {
+            code,
+            modifiers,
+            kind: KeyEventKind::Press,
+            ..
+        }) = ev
+        else {
+            continue;
+        };
+        match code {
+            KeyCode::Enter => return Ok(Some(typed)),
+            KeyCode::Esc => return Ok(None),
+            KeyCode::Char('c' | 'd') if modifiers.contains(KeyModifiers::CONTROL) => {
+                return Ok(None);
+            }
+            KeyCode::Backspace => {
+                let mut value = typed.expose().to_string();
+                value.pop();
+                typed = Secret::new(value);
+            }
+            KeyCode::Char(c) => typed = Secret::new(format!("{}{c}", typed.expose())),
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,496 @@
+use std::{
+    collections::HashMap,
+    fmt::Write as _,
+    io::{Write, stdout},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+    time::Duration,
+};
+
+use crossterm::{
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    terminal,
+};
+use nu_ansi_term::Style;
+use tokio::{
+    sync::{Notify, broadcast::error::RecvError, mpsc},
+    task::JoinHandle,
+};
+use tracing::{debug, error, info, warn};
+
+use crate::{
+    PshError,
+    error::{BuiltinError, Result, UiError},
+    registry::{self, Registry},
+    repl::{PaneSet, SharedRouter, panes::ViewRequest, parser::Parsed},
+    runtime::ReplSettings,
+    shell::ShellEvent,
+    ui::prefix_menu::color_for_entry,
+};
+
+const INPUT_PROMPT: &str = "psh> ";
+const FRAME_INTERVAL: Duration = Duration::from_millis(33);
+const EVENT_POLL_INTERVAL: Duration = Duration::from_millis(50);
+const EVENT_CHANNEL_CAP: usize = 64;
+const MIN_PANE_SIZE: u16 = 3;
+const CTRL_C_LITERAL: u8 = 0x03;
+const ENTER_VIEW: &str = "\x1b[?1049h";
+const LEAVE_VIEW: &str = "\x1b[?1049l\x1b[?25h";
+const CLEAR: &str = "\x1b[0m\x1b[2J";
+const HOLD_HINT: &str = "\r\n\x1b[7m press any key to return to the panes \x1b[0m";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Rect {
+    x: u16,
+    y: u16,
+    w: u16,
+    h: u16,
+}
+
+impl Rect {
+    fn inner(&self) -> Option<Rect> {
+        (self.w >= MIN_PANE_SIZE && self.h >= MIN_PANE_SIZE).then(|| Rect {
+            x: self.x + 1,
+            y: self.y + 1,
+            w: self.w - 2,
+            h: self.h - 2,
+        })
+    }
+}
+
+fn layout(count: usize, cols: u16, rows: u16) -> Vec<Rect> {
+    if count == 0 {
+        return Vec::new();
+    }
+    let grid_cols = (1..=count).find(|c| c * c >= count).unwrap_or(1);
+    let grid_rows = count.div_ceil(grid_cols);
+    let mut rects = Vec::with_capacity(count);
+    for r in 0..grid_rows {
+        let in_row = (count - r * grid_cols).min(grid_cols);
+        let y0 = (usize::from(rows) * r / grid_rows) as u16;
+        let y1 = (usize::from(rows) * (r + 1) / grid_rows) as u16;
+        for c in 0..in_row {
+            let x0 = (usize::from(cols) * c / in_row) as u16;
+            let x1 = (usize::from(cols) * (c + 1) / in_row) as u16;
+            rects.push(Rect {
+                x: x0,
+                y: y0,
+                w: x1 - x0,
+                h: y1 - y0,
+            });
+        }
+    }
+    rects
+}
+
+fn menu_key_matches(settings: &ReplSettings, key: &KeyEvent) -> bool {
+    let (code, mods) = settings.menu_key;
+    let code = match code {
+        reedline::KeyCode::Char(c) => KeyCode::Char(c),
+        reedline::KeyCode::F(n) => KeyCode::F(n),
+        reedline::KeyCode::Tab => KeyCode::Tab,
+        reedline::KeyCode::Enter => KeyCode::Enter,
+        reedline::KeyCode::Esc => KeyCode::Esc,
+        reedline::KeyCode::Backspace => KeyCode::Backspace,
+        reedline::KeyCode::Delete => KeyCode::Delete,
+        reedline::KeyCode::Insert => KeyCode::Insert,
+        reedline::KeyCode::PageUp => KeyCode::PageUp,
+        reedline::KeyCode::PageDown => KeyCode::PageDown,
+        reedline::KeyCode::Home => KeyCode::Home,
+        reedline::KeyCode::End => KeyCode::End,
+        reedline::KeyCode::Up => KeyCode::Up,
+        reedline::KeyCode::Down => KeyCode::Down,
+        reedline::KeyCode::Left => KeyCode::Left,
+        reedline::KeyCode::Right => KeyCode::Right,
+        _ => return false,
+    };
+    key.code == code && key.modifiers == KeyModifiers::from_bits_truncate(mods.bits())
+}
+
+fn spawn_input_thread(
+    stop: Arc<AtomicBool>,
+) -> (mpsc::Receiver<Event>, thread::JoinHandle<()>) {
+    let (tx, rx) = mpsc::channel(EVENT_CHANNEL_CAP);
+    let handle = thread::spawn(move || {
+        info!("tui input started");
+        while !stop.load(Ordering::Relaxed) {
+            match event::poll(EVENT_POLL_INTERVAL) {
+                Ok(false) => {}
+                Ok(true) => match event::read() {
+                    Ok(ev) => {
+                        if tx.blocking_send(ev).is_err() {
+                            break;
+                        }
+                    }
+                    Err(e) => {
+                        error!(?e, "tui input read failed");
+                        break;
+                    }
+                },
+                Err(e) => {
+                    error!(?e, "tui input poll failed");
+                    break;
+                }
+            }
+        }
+        info!("tui input done");
+    });
+    (rx, handle)
+}
+
+struct PaneView<'a> {
+    router: &'a SharedRouter,
+    panes: PaneSet,
+    settings: &'a ReplSettings,
+    registry: Registry,
+    watchers: HashMap<String, JoinHandle<()>>,
+    sizes: HashMap<String, (u16, u16)>,
+    notify: Arc<Notify>,
+    input: String,
+    message: Option<String>,
+    holding: bool,
+    cols: u16,
+    rows: u16,
+}
+
+impl PaneView<'_> {
+    async fn sync_panes(&mut self) {
+        let names = self.panes.names();
+        self.watchers.retain(|name, handle| {
+            let keep = names.contains(name);
+            if !keep {
+                handle.abort();
+            }
+            keep
+        });
+        let rects = layout(names.len(), self.cols, self.rows.saturating_sub(1));
+        for (name, rect) in names.iter().zip(rects) {
+            let Some(shell) = self.router.lock().await.running_shell(name).await else {
+                continue;
+            };
+            if !self.watchers.contains_key(name) {
+                let mut rx = shell.subscribe();
+                let notify = self.notify.clone();
+                let handle = tokio::spawn(async move {
+                    loop {
+                        match rx.recv().await {
+                            Ok(ShellEvent::Raw(_)) => notify.notify_one(),
+                            Ok(ShellEvent::Exited(_)) => {
+                                notify.notify_one();
+                                break;
+                            }
+                            Ok(_) => {}
+                            Err(RecvError::Lagged(n)) => {
+                                warn!(skipped = n, "tui pane watcher lagged");
+                                notify.notify_one();
+                            }
+                            Err(RecvError::Closed) => {
+                                notify.notify_one();
+                                break;
+                            }
+                        }
+                    }
+                });
+                self.watchers.insert(name.clone(), handle);
+            }
+            if let Some(inner) = rect.inner()
+                && self.sizes.get(name) != Some(&(inner.w, inner.h))
+            {
+                match shell.resize(inner.w, inner.h).await {
+                    Ok(()) => {
+                        self.sizes.insert(name.clone(), (inner.w, inner.h));
+                    }
+                    Err(e) => warn!(name = %name, ?e, "tui pane resize failed"),
+                }
+            }
+        }
+    }
+
+    async fn restore_sizes(&mut self) {
+        let (cols, rows) = self.router.lock().await.term_size();
+        for name in self.sizes.keys() {
+            if let Some(shell) = self.router.lock().await.running_shell(name).await
+                && let Err(e) = shell.resize(cols, rows).await
+            {
+                warn!(name = %name, ?e, "tui pane restore size failed");
+            }
+        }
+        for (_, handle) in self.watchers.drain() {
+            handle.abort();
+        }
+    }
+
+    async fn draw(&mut self) -> Result<()> {
+        let pane_set = &self.panes;
+        let names = pane_set.names();
+        let focused = pane_set.focused();
+        let rects = layout(names.len(), self.cols, self.rows.saturating_sub(1));
+        let mut out = String::from("\x1b[?25l");
+        if names.is_empty() {
+            out.push_str(CLEAR);
+            let _ = write!(out, "\x1b[1;1Hno panes; `pane: add <name>` opens one");
+        }
+        for (name, rect) in names.iter().zip(rects) {
+            let color = self
+                .registry
+                .get_entry(name)
+                .map_or(self.settings.color_unknown, |e| {
+                    color_for_entry(&e, self.settings)
+                });
+            let style = match Some(name) == focused.as_ref() {
+                true => Style::new().fg(color).bold(),
+                false => Style::new().fg(color).dimmed(),
+            };
+            let Some(inner) = rect.inner() else {
+                continue;
+            };
+            let width = usize::from(inner.w);
+            let title: String = format!(" {name} ").chars().take(width).collect();
+            let fill = "─".repeat(width - title.chars().count());
+            let _ = write!(
+                out,
+                "\x1b[{};{}H{}",
+                rect.y + 1,
+                rect.x + 1,
+                style.paint(format!("┌{title}{fill}┐"))
+            );
+            let session = self.router.lock().await.running_shell(name).await;
+            let screen = session.as_ref().and_then(|s| s.screen());
+            let screen = screen.as_ref().and_then(|s| s.lock().ok());
+            for row in 0..inner.h {
+                let body = match &screen {
+                    Some(screen) => screen.render_row(usize::from(row), width),
+                    None if row == 0 => format!("{:<width$}", "[exited]"),
+                    None => " ".repeat(width),
+                };
+                let _ = write!(
+                    out,
+                    "\x1b[{};{}H{}{body}{}",
+                    inner.y + row + 1,
+                    rect.x + 1,
+                    style.paint("│"),
+                    style.paint("│")
+                );
+            }
+            let _ = write!(
+                out,
+                "\x1b[{};{}H{}",
+                rect.y + rect.h,
+                rect.x + 1,
+                style.paint(format!("└{}┘", "─".repeat(width)))
+            );
+        }
+
+        let target = focused.map(|n| format!("{n}: ")).unwrap_or_default();
+        let message = self
+            .message
+            .as_ref()
+            .map(|m| format!("  [{m}]"))
+            .unwrap_or_default();
+        let line = format!("{INPUT_PROMPT}{target}{}", self.input);
+        let _ = write!(
+            out,
+            "\x1b[{};1H\x1b[0m\x1b[2K{}{}{}",
+            self.rows,
+            Style::new()
+                .fg(self.settings.color_prompt)
+                .paint(line.as_str()),
+            message,
+            format_args!("\x1b[{};{}H\x1b[?25h", self.rows, line.chars().count() + 1)
+        );
+
+        let mut so = stdout();
+        so.write_all(out.as_bytes()).map_err(UiError::IoWrite)?;
+        so.flush().map_err(UiError::IoWrite)?;
+        Ok(())
+    }
+
+    async fn submit(&mut self) -> Result<bool> {
+        let line = std::mem::take(&mut self.input);
+        let focused = self.panes.focused();
+        debug!(len = line.len(), "tui submit start");
+        let mut router = self.router.lock().await;
+        if line.trim().is_empty() {
+            if let Some(name) = focused {
+                router.send_bytes(&name, b"\r".to_vec()).await?;
+            }
+            return Ok(false);
+        }
+        if let Some(name) = &focused {
+            router.set_current_mode(name);
+        }
+        let prints = !matches!(
+            router.parse_preview(line.trim()),
+            Parsed::Default { .. }
+                | Parsed::Entry {
+                    entry: registry::Entry::Shell(_),
+                    ..
+                }
+        );
+        let res = router.exec(line.trim()).await;
+        self.registry = router.get_registry_clone();
+        res?;
+        info!(prints, "tui submit ok");
+        Ok(prints)
+    }
+
+    async fn handle_key(&mut self, key: KeyEvent) -> Result<bool> {
+        if key.kind != KeyEventKind::Press {
+            return Ok(false);
+        }
+        if menu_key_matches(self.settings, &key) {
+            self.panes.focus_next();
+            return Ok(false);
+        }
+        if self.holding {
+            self.holding = false;
+            stdout()
+                .write_all(CLEAR.as_bytes())
+                .map_err(UiError::IoWrite)?;
+            return Ok(false);
+        }
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Char('d') if ctrl && self.input.is_empty() => return Ok(true),
+            KeyCode::Char('c') if ctrl => {
+                self.input.clear();
+                if let Some(name) = self.panes.focused() {
+                    self.router
+                        .lock()
+                        .await
+                        .send_bytes(&name, vec![CTRL_C_LITERAL])
+                        .await?;
+                }
+            }
+            KeyCode::Char(c) if !ctrl => self.input.push(c),
+            KeyCode::Backspace => {
+                self.input.pop();
+            }
+            KeyCode::Esc => self.input.clear(),
+            KeyCode::Enter => {
+                self.message = None;
+                let res = self.submit().await;
+                let mut so = stdout();
+                if let Ok(true) = res {
+                    self.holding = true;
+                    so.write_all(HOLD_HINT.as_bytes())
+                        .map_err(UiError::IoWrite)?;
+                    so.flush().map_err(UiError::IoWrite)?;
+                    return Ok(false);
+                }
+                so.write_all(CLEAR.as_bytes()).map_err(UiError::IoWrite)?;
+                res?;
+            }
+            _ => {}
+        }
+        Ok(false)
+    }
+}
+
+pub async fn run(router: &SharedRouter, settings: &ReplSettings) -> Result<()> {
+    debug!("tui_run start");
+    let (panes, registry, term_size) = {
+        let mut r = router.lock().await;
+        let panes = r.pane_state();
+        if panes.names().is_empty()
+            && let Some(name) = r.get_current_mode()
+            && let Err(e) = r.add_pane(&name).await
+        {
+            warn!(name = %name, ?e, "tui_run initial pane failed");
+        }
+        (panes, r.get_registry_clone(), r.term_size())
+    };
+
+    let (cols, rows) = match terminal::size().map_err(UiError::ResizeRead)? {
+        (0, _) | (_, 0) => term_size,
+        size => size,
+    };
+    terminal::enable_raw_mode().map_err(|e| UiError::RawModeEnable(e.into()))?;
+    panes.set_active(true);
+    let mut so = stdout();
+    so.write_all(format!("{ENTER_VIEW}{CLEAR}").as_bytes())
+        .map_err(UiError::IoWrite)?;
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let (mut events, input_thread) = spawn_input_thread(stop.clone());
+    let mut view = PaneView {
+        registry,
+        router,
+        panes,
+        settings,
+        watchers: HashMap::new(),
+        sizes: HashMap::new(),
+        notify: Arc::new(Notify::new()),
+        input: String::new(),
+        message: None,
+        holding: false,
+        cols,
+        rows,
+    };
+
+    let result: Result<()> = async {
+        loop {
+            if view.panes.take_request() == Some(ViewRequest::Close) {
+                info!("tui_run close requested");
+                return Ok(());
+            }
+            if !view.holding {
+                view.sync_panes().await;
+                view.draw().await?;
+            }
+            let notify = view.notify.clone();
+            tokio::select! {
+                ev = events.recv() => {
+                    match ev {
+                        Some(Event::Key(key)) => match view.handle_key(key).await {
+                            Ok(true) => return Ok(()),
+                            Ok(false) => {}
+                            Err(PshError::Builtin(BuiltinError::ExitRequested)) => {
+                                return Err(BuiltinError::ExitRequested.into());
+                            }
+                            Err(e) => {
+                                warn!(?e, "tui_run line failed");
+                                view.message = Some(e.to_string());
+                            }
+                        },
+                        Some(Event::Resize(c, r)) => {
+                            view.cols = c;
+                            view.rows = r;
+                            view.router.lock().await.set_term_size(c, r).await;
+                            view.holding = false;
+                            stdout()
+                                .write_all(CLEAR.as_bytes())
+                                .map_err(UiError::IoWrite)?;
+                        }
+                        Some(_) => {}
+                        None => return Ok(()),
+                    }
+                }
+                _ = notify.notified() => {
+                    tokio::time::sleep(FRAME_INTERVAL).await;
+                }
+            }
+        }
+    }
+    .await;
+
+    stop.store(true, Ordering::Relaxed);
+    drop(events);
+    if input_thread.join().is_err() {
+        warn!("tui_run input thread panicked");
+    }
+    view.panes.set_active(false);
+    view.restore_sizes().await;
+    let mut so = stdout();
+    let _ = so.write_all(LEAVE_VIEW.as_bytes());
+    let _ = so.flush();
+    terminal::disable_raw_mode().map_err(|e| UiError::RawModeDisable(e.into()))?;
+    match &result {
+        Ok(()) => info!("tui_run ok"),
+        Err(e) => warn!(?e, "tui_run ended with error"),
+    }
+    result
+}
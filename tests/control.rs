@@ -0,0 +1,365 @@
+use std::{path::PathBuf, sync::Arc, time::Duration};
+
+use psh::{
+    PshError,
+    builtins::BuiltinSet,
+    control::ControlServer,
+    error::ControlError,
+    registry::Registry,
+    repl::{Policy, Router, ShareMode, SharedRouter, policy::Confirmer},
+};
+use serde_json::{Value, json};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines},
+    net::{
+        UnixStream,
+        unix::{OwnedReadHalf, OwnedWriteHalf},
+    },
+    sync::Mutex,
+    time::timeout,
+};
+
+const WAIT: Duration = Duration::from_secs(10);
+
+fn socket_path(test: &str) -> PathBuf {
+    let dir =
+        std::env::temp_dir().join(format!("psh-control-{}-{test}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir.join("control.sock")
+}
+
+fn router() -> SharedRouter {
+    let builtins = BuiltinSet::with_defaults();
+    let registry = Registry::with_builtins(&builtins);
+    Arc::new(Mutex::new(Router::new(registry, builtins, 80, 24)))
+}
+
+struct AlwaysConfirm;
+
+impl Confirmer for AlwaysConfirm {
+    fn confirm(&self, _reason: &str, _expected: &str) -> psh::error::Result<bool> {
+        Ok(true)
+    }
+}
+
+struct Client {
+    lines: Lines<BufReader<OwnedReadHalf>>,
+    write: OwnedWriteHalf,
+    next_id: u64,
+}
+
+impl Client {
+    async fn connect(server: &ControlServer) -> Self {
+        let stream = UnixStream::connect(server.path()).await.expect("connect");
+        let (read, write) = stream.into_split();
+        Self {
+            lines: BufReader::new(read).lines(),
+            write,
+            next_id: 0,
+        }
+    }
+
+    async fn send_raw(&mut self, line: &str) {
+        self.write
+            .write_all(format!("{line}\n").as_bytes())
+            .await
+            .expect("write");
+    }
+
+    async fn recv_notification(&mut self, method: &str) -> Value {
+        loop {
+            let msg = self.recv().await;
+            if msg["method"] == method {
+                return msg;
+            }
+        }
+    }
+
+    async fn recv(&mut self) -> Value {
+        let line = timeout(WAIT, self.lines.next_line())
+            .await
+            .expect("reply in time")
+            .expect("read")
+            .expect("open connection");
+        serde_json::from_str(&line).expect("json reply")
+    }
+
+    async fn call(&mut self, method: &str, params: Value) -> Value {
+        self.next_id += 1;
+        let id = self.next_id;
+        let request =
+            json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+        self.send_raw(&request.to_string()).await;
+        loop {
+            let msg = self.recv().await;
+            if msg["id"] == json!(id) {
+                return msg;
+            }
+        }
+    }
+}
+
+fn names(list: &Value) -> Vec<String> {
+    list["result"]
+        .as_array()
+        .expect("list result")
+        .iter()
+        .map(|e| e["name"].as_str().expect("name").to_string())
+        .collect()
+}
+
+#[tokio::test]
+async fn lists_builtins_and_rejects_bad_requests() {
+    let server = ControlServer::start(&socket_path("list"), router()).expect("start");
+    let mut client = Client::connect(&server).await;
+
+    let list = client.call("sessions.list", Value::Null).await;
+    let local = list["result"]
+        .as_array()
+        .expect("list result")
+        .iter()
+        .find(|e| e["name"] == "local")
+        .expect("local builtin listed")
+        .clone();
+    assert_eq!(local["kind"], "builtin");
+    assert_eq!(local["running"], false);
+
+    let unknown = client.call("sessions.frobnicate", Value::Null).await;
+    assert_eq!(unknown["error"]["code"], -32601);
+
+    let invalid = client.call("session.send", json!({ "name": "x" })).await;
+    assert_eq!(invalid["error"]["code"], -32602);
+
+    client.send_raw("{not json").await;
+    let parse = client.recv().await;
+    assert_eq!(parse["error"]["code"], -32700);
+    assert_eq!(parse["id"], Value::Null);
+
+    let builtin = client
+        .call("registry.remove", json!({ "name": "local" }))
+        .await;
+    assert_eq!(builtin["error"]["code"], -32000);
+}
+
+#[tokio::test]
+async fn registers_sends_and_streams_output() {
+    let server = ControlServer::start(&socket_path("stream"), router()).expect("start");
+    let mut client = Client::connect(&server).await;
+
+    let spec = json!({ "type": "local", "program": "/bin/sh" });
+    let added = client
+        .call("registry.add", json!({ "name": "sh", "spec": spec }))
+        .await;
+    assert_eq!(added["result"], true);
+
+    let again = client
+        .call("registry.add", json!({ "name": "sh", "spec": spec }))
+        .await;
+    assert_eq!(again["error"]["code"], -32000);
+
+    let list = client.call("sessions.list", Value::Null).await;
+    assert!(names(&list).contains(&"sh".to_string()));
+
+    let subscribed = client
+        .call("session.subscribe", json!({ "name": "sh" }))
+        .await;
+    assert_eq!(subscribed["result"], true);
+
+    let sent = client
+        .call(
+            "session.send",
+            json!({ "name": "sh", "line": "echo control-$((40 + 2))" }),
+        )
+        .await;
+    assert_eq!(sent["result"], true);
+
+    let mut output = String::new();
+    while !output.contains("control-42") {
+        let msg = client.recv().await;
+        if msg["method"] == "session.output" {
+            assert_eq!(msg["params"]["name"], "sh");
+            output.push_str(msg["params"]["data"].as_str().expect("data"));
+        }
+    }
+
+    let removed = client
+        .call("registry.remove", json!({ "name": "sh" }))
+        .await;
+    assert_eq!(removed["result"], true);
+    let list = client.call("sessions.list", Value::Null).await;
+    assert!(!names(&list).contains(&"sh".to_string()));
+}
+
+#[tokio::test]
+async fn refuses_a_socket_that_is_in_use() {
+    let path = socket_path("in-use");
+    let _server = ControlServer::start(&path, router()).expect("start");
+    match ControlServer::start(&path, router()) {
+        Err(PshError::Control(ControlError::InUse { .. })) => {}
+        Err(e) => panic!("unexpected error: {e}"),
+        Ok(_) => panic!("second server bound an in-use socket"),
+    }
+}
+
+#[tokio::test]
+async fn joins_shared_sessions_with_viewer_permissions() {
+    let shared = router();
+    let server =
+        ControlServer::start(&socket_path("join"), shared.clone()).expect("start");
+    let mut host = Client::connect(&server).await;
+    let spec = json!({ "type": "local", "program": "/bin/sh" });
+    let added = host
+        .call("registry.add", json!({ "name": "sh", "spec": spec }))
+        .await;
+    assert_eq!(added["result"], true);
+
+    let mut reader = Client::connect(&server).await;
+    let refused = reader.call("session.join", json!({ "name": "sh" })).await;
+    assert_eq!(refused["error"]["code"], -32000);
+
+    shared
+        .lock()
+        .await
+        .share_session("sh", ShareMode::Read)
+        .expect("share");
+    let too_much = reader
+        .call("session.join", json!({ "name": "sh", "mode": "write" }))
+        .await;
+    assert_eq!(too_much["error"]["code"], -32000);
+    let joined = reader.call("session.join", json!({ "name": "sh" })).await;
+    assert_eq!(joined["result"]["mode"], "read");
+    let read_id = joined["result"]["viewer"].clone();
+    let typed = reader
+        .call("session.input", json!({ "viewer": read_id, "data": "x" }))
+        .await;
+    assert_eq!(typed["error"]["code"], -32000);
+    let sent = reader
+        .call("session.send", json!({ "name": "sh", "line": "x" }))
+        .await;
+    assert_eq!(sent["error"]["code"], -32000);
+    let spec = json!({ "type": "local", "program": "/bin/true" });
+    let added = reader
+        .call("registry.add", json!({ "name": "other", "spec": spec }))
+        .await;
+    assert_eq!(added["error"]["code"], -32000);
+    let removed = reader
+        .call("registry.remove", json!({ "name": "sh" }))
+        .await;
+    assert_eq!(removed["error"]["code"], -32000);
+    let followed = reader
+        .call("session.subscribe", json!({ "name": "sh" }))
+        .await;
+    assert_eq!(followed["result"], true);
+
+    shared
+        .lock()
+        .await
+        .share_session("sh", ShareMode::Write)
+        .expect("share");
+    let mut writer = Client::connect(&server).await;
+    let joined = writer
+        .call("session.join", json!({ "name": "sh", "mode": "write" }))
+        .await;
+    let write_id = joined["result"]["viewer"].clone();
+    let forged = writer
+        .call("session.input", json!({ "viewer": read_id, "data": "x" }))
+        .await;
+    assert_eq!(forged["error"]["code"], -32000);
+    assert_eq!(shared.lock().await.list_viewers("sh").len(), 2);
+
+    let typed = writer
+        .call(
+            "session.input",
+            json!({ "viewer": write_id, "data": "echo shared-$((6 * 7))\n" }),
+        )
+        .await;
+    assert_eq!(typed["result"], true);
+    let mut output = String::new();
+    while !output.contains("shared-42") {
+        let msg = reader.recv_notification("session.output").await;
+        output.push_str(msg["params"]["data"].as_str().expect("data"));
+    }
+
+    assert_eq!(shared.lock().await.unshare_session("sh"), 2);
+    let left = reader.recv_notification("session.left").await;
+    assert_eq!(left["params"]["viewer"], read_id);
+    assert!(shared.lock().await.list_viewers("sh").is_empty());
+
+    let removed = host.call("registry.remove", json!({ "name": "sh" })).await;
+    assert_eq!(removed["result"], true);
+}
+
+#[tokio::test]
+async fn declines_guarded_lines_without_prompting() {
+    let shared = router();
+    {
+        let mut r = shared.lock().await;
+        r.set_policy(Policy::from_config(None).expect("default policy"));
+        r.set_confirmer(Arc::new(AlwaysConfirm));
+    }
+    let server =
+        ControlServer::start(&socket_path("guard"), shared.clone()).expect("start");
+    let mut client = Client::connect(&server).await;
+    let spec = json!({ "type": "local", "program": "/bin/sh", "tags": ["prod"] });
+    let added = client
+        .call("registry.add", json!({ "name": "db", "spec": spec }))
+        .await;
+    assert_eq!(added["result"], true);
+
+    let declined = client
+        .call(
+            "session.send",
+            json!({ "name": "db", "line": "rm -rf /nonexistent" }),
+        )
+        .await;
+    assert_eq!(declined["error"]["code"], -32000);
+    let message = declined["error"]["message"].as_str().expect("message");
+    assert!(message.contains("confirmation declined"), "{message}");
+
+    let sent = client
+        .call("session.send", json!({ "name": "db", "line": "true" }))
+        .await;
+    assert_eq!(sent["result"], true);
+    assert!(
+        shared
+            .lock()
+            .await
+            .exec("db: rm -rf /nonexistent")
+            .await
+            .is_ok(),
+        "the host confirmer is restored after control requests"
+    );
+
+    shared
+        .lock()
+        .await
+        .share_session("db", ShareMode::Write)
+        .expect("share");
+    let mut viewer = Client::connect(&server).await;
+    let joined = viewer
+        .call("session.join", json!({ "name": "db", "mode": "write" }))
+        .await;
+    let id = joined["result"]["viewer"].clone();
+    let declined = viewer
+        .call(
+            "session.input",
+            json!({ "viewer": id, "data": "rm -rf /nonexistent\r" }),
+        )
+        .await;
+    let message = declined["error"]["message"].as_str().expect("message");
+    assert!(message.contains("confirmation declined"), "{message}");
+    let typed = viewer
+        .call("session.input", json!({ "viewer": id, "data": "tru" }))
+        .await;
+    assert_eq!(typed["result"], true);
+    let sent = viewer
+        .call("session.input", json!({ "viewer": id, "data": "e\r" }))
+        .await;
+    assert_eq!(sent["result"], true);
+    shared.lock().await.unshare_session("db");
+
+    let removed = client
+        .call("registry.remove", json!({ "name": "db" }))
+        .await;
+    assert_eq!(removed["result"], true);
+}
@@ -0,0 +1,138 @@
+use std::{collections::HashMap, time::Duration};
+
+use psh::{
+    Psh,
+    runtime::config::{
+        AuditSection, PolicyRuleSection, PolicySection, PshConfig, ShellsSection,
+    },
+    shell::{ShellEvent, ShellSpec},
+};
+use tokio::time::timeout;
+
+const WAIT: Duration = Duration::from_secs(10);
+
+fn sh() -> ShellSpec {
+    ShellSpec::Local {
+        program: "/bin/sh".to_string(),
+        tags: Vec::new(),
+        readonly: false,
+        on_connect: None,
+    }
+}
+
+#[tokio::test]
+async fn embeds_routing_without_the_repl() {
+    let config = PshConfig {
+        shells: Some(ShellsSection {
+            default_shell: Some("sh".to_string()),
+            catalog: Some(HashMap::from([("sh".to_string(), sh())])),
+        }),
+        audit: Some(AuditSection {
+            enabled: Some(false),
+            ..AuditSection::default()
+        }),
+        ..PshConfig::default()
+    };
+    let psh = Psh::builder()
+        .config(config)
+        .without_logging()
+        .build()
+        .await
+        .expect("build");
+
+    let sh_info = psh
+        .sessions()
+        .await
+        .into_iter()
+        .find(|s| s.name == "sh")
+        .expect("configured shell listed");
+    assert!(!sh_info.running);
+
+    let mut events = psh.subscribe("sh").await.expect("subscribe");
+    psh.exec("echo embed-$((20 + 1))").await.expect("exec");
+    let mut output = String::new();
+    while !output.contains("embed-21") {
+        match timeout(WAIT, events.recv()).await.expect("output in time") {
+            Ok(ShellEvent::Output(data)) => output.push_str(&data),
+            Ok(_) => {}
+            Err(e) => panic!("event stream failed: {e}"),
+        }
+    }
+
+    psh.spawn("other", sh()).await.expect("spawn");
+    let running: Vec<String> = psh
+        .sessions()
+        .await
+        .into_iter()
+        .filter(|s| s.running)
+        .map(|s| s.name)
+        .collect();
+    assert!(running.contains(&"sh".to_string()));
+    assert!(running.contains(&"other".to_string()));
+    assert!(psh.spawn("other", sh()).await.is_err());
+
+    psh.shutdown().await;
+}
+
+#[tokio::test]
+async fn refuses_to_start_with_an_invalid_policy() {
+    let config = PshConfig {
+        policy: Some(PolicySection {
+            max_fanout: None,
+            rules: Some(vec![PolicyRuleSection {
+                name: "broken".to_string(),
+                tags: vec!["prod".to_string()],
+                patterns: vec!["rm (".to_string()],
+            }]),
+        }),
+        ..PshConfig::default()
+    };
+
+    let err = Psh::builder()
+        .config(config)
+        .without_logging()
+        .start_shells(false)
+        .build()
+        .await
+        .err()
+        .expect("invalid policy rejected");
+
+    assert!(err.to_string().contains("broken"), "{err}");
+}
+
+#[tokio::test]
+async fn declines_guarded_lines_without_a_confirmer() {
+    let db = ShellSpec::Local {
+        program: "/bin/sh".to_string(),
+        tags: vec!["prod".to_string()],
+        readonly: false,
+        on_connect: None,
+    };
+    let config = PshConfig {
+        shells: Some(ShellsSection {
+            default_shell: None,
+            catalog: Some(HashMap::from([("db".to_string(), db)])),
+        }),
+        audit: Some(AuditSection {
+            enabled: Some(false),
+            ..AuditSection::default()
+        }),
+        ..PshConfig::default()
+    };
+    let psh = Psh::builder()
+        .config(config)
+        .without_logging()
+        .start_shells(false)
+        .build()
+        .await
+        .expect("build");
+
+    let err = psh
+        .exec("db: rm -rf /nonexistent")
+        .await
+        .expect_err("declined");
+    assert!(err.to_string().contains("confirmation declined"), "{err}");
+    psh.exec("db: true").await.expect("unguarded line");
+
+    psh.shutdown().await;
+}
@@ -0,0 +1,161 @@
+use std::{fs, path::PathBuf};
+
+use psh::{
+    harness::Harness,
+    registry::Entry,
+    repl::Policy,
+    shell::{ShellSpec, mock::MockScript},
+};
+
+fn script(test: &str, body: &str) -> PathBuf {
+    let path =
+        std::env::temp_dir().join(format!("psh-expect-{}-{test}", std::process::id()));
+    fs::write(&path, body).expect("write script");
+    path
+}
+
+fn login() -> MockScript {
+    MockScript::new()
+        .expect("connect")
+        .respond("login: ")
+        .expect("admin")
+        .respond("Password: ")
+        .expect("hunter2")
+        .respond("Welcome\n$ ")
+}
+
+#[tokio::test]
+async fn runs_send_expect_and_branches() {
+    let mut h = Harness::new().shell("router", login());
+    let path = script(
+        "branches",
+        "# log in\n\
+         send connect\n\
+         expect /login: / 2\n\
+         send admin\n\
+         if expect /Password:/ 2\n\
+         \x20 send hunter2\n\
+         else\n\
+         \x20 fail no password prompt\n\
+         end\n\
+         if expect /Denied/ 0.2\n\
+         \x20 fail denied\n\
+         else\n\
+         \x20 send show version\n\
+         end\n",
+    );
+
+    let out = h
+        .exec(&format!("expect: run router {}", path.display()))
+        .await
+        .expect("expect run");
+    fs::remove_file(&path).ok();
+
+    assert!(out.contains("started on router"), "{out}");
+    assert!(h.wait_for_output("router", "Welcome").await);
+    let inputs = h
+        .eventually(|h| (h.inputs("router").len() == 4).then(|| h.inputs("router")))
+        .await;
+    assert_eq!(
+        inputs,
+        Some(
+            ["connect", "admin", "hunter2", "show version"]
+                .map(String::from)
+                .to_vec()
+        )
+    );
+}
+
+#[tokio::test]
+async fn fails_on_timeouts_and_parse_errors() {
+    let mut h = Harness::new().shell("router", login());
+    let timeout = script("timeout", "send connect\nexpect /never/ 0.2\nsend late\n");
+    let broken = script("broken", "if expect /x/\nsend y\n");
+
+    h.exec(&format!("expect: run router {}", timeout.display()))
+        .await
+        .expect("started");
+    let err = h
+        .exec(&format!("expect: run router {}", timeout.display()))
+        .await
+        .expect_err("busy");
+    assert!(err.to_string().contains("already running"), "{err}");
+    h.exec("router: held").await.expect("held");
+    assert!(
+        h.eventually(|h| (!h.script_running("router")).then_some(()))
+            .await
+            .is_some()
+    );
+    assert_eq!(h.inputs("router"), vec!["connect"]);
+
+    let err = h
+        .exec(&format!("expect: check {}", broken.display()))
+        .await
+        .expect_err("parse");
+    assert!(err.to_string().contains("line 1"), "{err}");
+
+    fs::remove_file(&timeout).ok();
+    fs::remove_file(&broken).ok();
+}
+
+#[tokio::test]
+async fn runs_on_connect_scripts_when_sessions_start() {
+    let path = script(
+        "on-connect",
+        "send connect\nexpect /login:/ 2\nsend admin\nexpect /Password:/ 2\nsend hunter2\nexpect /\\$ / 2\n",
+    );
+    let mut h = Harness::new().shell("router", login());
+    h.router().register_entry(
+        "router".to_string(),
+        Entry::Shell(ShellSpec::Local {
+            program: "mock".to_string(),
+            tags: Vec::new(),
+            readonly: false,
+            on_connect: Some(path.display().to_string()),
+        }),
+    );
+
+    h.exec("router: show version").await.expect("send");
+    fs::remove_file(&path).ok();
+
+    let inputs = h
+        .eventually(|h| (h.inputs("router").len() == 4).then(|| h.inputs("router")))
+        .await;
+    assert_eq!(
+        inputs,
+        Some(
+            ["connect", "admin", "hunter2", "show version"]
+                .map(String::from)
+                .to_vec()
+        )
+    );
+}
+
+#[tokio::test]
+async fn declines_guarded_script_lines() {
+    let path = script("guarded", "send rm -rf /srv\nsend after\n");
+    let mut h = Harness::new().shell("db", MockScript::new()).confirm(true);
+    h.router().register_entry(
+        "db".to_string(),
+        Entry::Shell(ShellSpec::Local {
+            program: "mock".to_string(),
+            tags: vec!["prod".to_string()],
+            readonly: false,
+            on_connect: None,
+        }),
+    );
+    h.router()
+        .set_policy(Policy::from_config(None).expect("default policy"));
+
+    h.exec(&format!("expect: run db {}", path.display()))
+        .await
+        .expect("started");
+    fs::remove_file(&path).ok();
+
+    assert!(
+        h.eventually(|h| (!h.script_running("db")).then_some(()))
+            .await
+            .is_some()
+    );
+    assert!(h.inputs("db").is_empty());
+}
@@ -0,0 +1,275 @@
+use std::{fs, time::Duration};
+
+use psh::{
+    harness::Harness,
+    registry::Entry,
+    repl::Policy,
+    runtime::AuditLog,
+    shell::{ShellSpec, mock::MockScript, spec::RemoteBackend},
+};
+
+fn uptime(reply: &str) -> MockScript {
+    MockScript::new().expect("uptime").respond(reply).status(0)
+}
+
+#[tokio::test]
+async fn routes_prefixed_and_default_lines() {
+    let mut h = Harness::new()
+        .shell("web", uptime("web up 3 days\n"))
+        .shell("db", MockScript::new().expect("false").status(1))
+        .default_shell("web");
+
+    h.exec("uptime").await.expect("default line");
+    h.exec("db: false").await.expect("prefixed line");
+
+    assert!(h.wait_for_output("web", "web up 3 days").await);
+    assert_eq!(h.eventually(|h| h.last_status("db")).await, Some(1));
+    assert_eq!(h.eventually(|h| h.last_status("web")).await, Some(0));
+    assert_eq!(h.inputs("web"), vec!["uptime"]);
+    assert_eq!(h.inputs("db"), vec!["false"]);
+}
+
+#[tokio::test]
+async fn audits_exit_statuses_reported_by_the_prompt_hook() {
+    let path =
+        std::env::temp_dir().join(format!("psh-routing-{}-audit", std::process::id()));
+    let _ = fs::remove_file(&path);
+    let mut h = Harness::new().shell("db", MockScript::new().expect("false").status(1));
+    let audit = AuditLog::open(&path, &[]).expect("audit");
+    h.router().set_audit_log(Some(audit));
+
+    h.exec("db: false").await.expect("send");
+
+    let audited = h
+        .eventually(|_| {
+            let log = fs::read_to_string(&path).unwrap_or_default();
+            (log.lines().count() == 2).then_some(log)
+        })
+        .await
+        .expect("status record");
+    fs::remove_file(&path).ok();
+    let records: Vec<&str> = audited.lines().collect();
+    assert!(records[0].contains(r#""exit_status":null"#), "{audited}");
+    assert!(records[1].contains(r#""line":"false""#), "{audited}");
+    assert!(records[1].contains(r#""exit_status":1"#), "{audited}");
+}
+
+#[tokio::test]
+async fn aliases_fan_out_to_every_target() {
+    let mut h = Harness::new()
+        .shell("web", uptime("web ok\n"))
+        .shell("db", uptime("db ok\n"));
+
+    h.exec(r#"alias: set both "web: uptime" "db: uptime""#)
+        .await
+        .expect("alias set");
+    h.exec("both").await.expect("alias run");
+
+    assert!(h.wait_for_output("web", "web ok").await);
+    assert!(h.wait_for_output("db", "db ok").await);
+}
+
+#[tokio::test]
+async fn aliases_may_wrap_the_command_they_name() {
+    let mut h = Harness::new().shell("web", MockScript::new());
+
+    h.exec(r#"alias: set ls "ls --color""#)
+        .await
+        .expect("alias set");
+    h.exec(r#"alias: set ll "ls -l""#).await.expect("alias set");
+    h.router().set_current_mode("web");
+    h.exec("ls").await.expect("self alias");
+    h.exec("ll").await.expect("nested alias");
+    let inputs = h
+        .eventually(|h| (h.inputs("web").len() == 2).then(|| h.inputs("web")))
+        .await;
+    assert_eq!(
+        inputs,
+        Some(["ls --color", "ls --color -l"].map(String::from).to_vec())
+    );
+
+    h.exec(r#"alias: set a "b""#).await.expect("alias set");
+    h.exec(r#"alias: set b "a""#).await.expect("alias set");
+    let err = h.exec("a").await.expect_err("cycle");
+    assert!(err.to_string().contains("too deep"), "{err}");
+}
+
+#[tokio::test]
+async fn substitutes_captured_output_into_another_session() {
+    let mut h = Harness::new()
+        .shell(
+            "web",
+            MockScript::new().expect("hostname").respond("web-01\n"),
+        )
+        .shell("db", MockScript::new().expect("ping").respond("pong\n"));
+
+    h.exec("db: ping -c1 $(web: hostname)")
+        .await
+        .expect("substituted line");
+
+    assert!(h.wait_for_output("db", "pong").await);
+    assert_eq!(h.inputs("db"), vec!["ping -c1 web-01"]);
+    assert_eq!(h.inputs("web").len(), 1);
+    assert!(h.inputs("web")[0].contains("hostname"));
+}
+
+#[tokio::test]
+async fn builtins_report_and_change_registry_state() {
+    let mut h = Harness::new().shell("web", MockScript::new().echo(true));
+
+    h.exec("local: add tools /bin/true --tags ops")
+        .await
+        .expect("local add");
+    assert!(h.registry().get_shell_spec("tools").is_some());
+
+    h.exec("web: hello").await.expect("send");
+    assert!(h.wait_for_output("web", "hello").await);
+    let sessions = h.exec("admin: sessions").await.expect("sessions");
+    assert!(sessions.contains("  web"), "{sessions}");
+    assert!(sessions.contains("  tools"), "{sessions}");
+
+    h.exec("admin: lock web").await.expect("lock");
+    assert!(h.exec("web: blocked").await.is_err());
+    assert_eq!(h.inputs("web"), vec!["hello"]);
+
+    h.exec("local: remove tools").await.expect("local remove");
+    assert!(h.registry().get_shell_spec("tools").is_none());
+    assert!(h.wait_for_exit("tools").await);
+}
+
+#[tokio::test]
+async fn remote_add_rejects_conflicting_ports() {
+    let mut h = Harness::new();
+
+    let err = h
+        .exec("remote: add edge ssh edge 2222 --port 22")
+        .await
+        .expect_err("conflicting ports");
+    assert!(
+        err.to_string().contains("conflicts with `--port 22`"),
+        "{err}"
+    );
+    assert!(h.registry().get_shell_spec("edge").is_none());
+}
+
+#[tokio::test]
+async fn disconnects_drop_the_session_until_the_next_line() {
+    let mut h =
+        Harness::new().shell("flaky", MockScript::new().expect("exit").disconnect());
+
+    h.exec("flaky: exit").await.expect("send");
+    assert!(h.wait_for_exit("flaky").await);
+
+    h.exec("flaky: again").await.expect("respawn");
+    let respawned = h
+        .eventually(|h| (!h.inputs("flaky").is_empty()).then(|| h.inputs("flaky")))
+        .await;
+    assert_eq!(respawned, Some(vec!["again".to_string()]));
+}
+
+#[tokio::test]
+async fn delayed_output_arrives_after_exec_returns() {
+    let script = MockScript::new()
+        .expect("slow")
+        .delay(Duration::from_millis(200))
+        .respond("done\n");
+    let mut h = Harness::new().shell("job", script);
+
+    h.exec("job: slow").await.expect("send");
+    assert!(!h.output("job").contains("done"));
+    assert!(h.wait_for_output("job", "done").await);
+}
+
+fn ssh(host: &str, extra_args: &[&str], via: Option<&str>) -> Entry {
+    Entry::Shell(ShellSpec::Remote {
+        host: host.to_string(),
+        backend: RemoteBackend::Ssh {
+            port: 22,
+            extra_args: extra_args.iter().map(|a| a.to_string()).collect(),
+            via: via.map(str::to_string),
+        },
+        tags: Vec::new(),
+        readonly: false,
+        on_connect: None,
+    })
+}
+
+#[tokio::test]
+async fn rejects_bastions_whose_extra_args_cannot_reach_the_jump() {
+    let mut h = Harness::new();
+    h.router()
+        .register_entry("bastion".to_string(), ssh("bastion", &["-i", "key"], None));
+    h.router()
+        .register_entry("web".to_string(), ssh("web", &[], Some("bastion")));
+
+    let err = h.exec("web: uptime").await.expect_err("bastion extra args");
+    let chain =
+        std::iter::successors(Some(&err as &dyn std::error::Error), |e| e.source())
+            .map(|e| e.to_string())
+            .collect::<Vec<_>>()
+            .join(": ");
+    assert!(chain.contains("via bastion bastion"), "{chain}");
+    assert!(chain.contains("extra_args"), "{chain}");
+    assert!(h.running().await.is_empty());
+}
+
+#[tokio::test]
+async fn installs_a_guarded_prompt_hook_on_ssh_remotes_after_login() {
+    let mut h = Harness::new().remote("web", MockScript::new().banner("Welcome\n$ "));
+    h.router().register_entry(
+        "web".to_string(),
+        Entry::Shell(ShellSpec::Remote {
+            host: "web".to_string(),
+            backend: RemoteBackend::Ssh {
+                port: 22,
+                extra_args: Vec::new(),
+                via: None,
+            },
+            tags: Vec::new(),
+            readonly: false,
+            on_connect: None,
+        }),
+    );
+
+    h.exec("web: uptime").await.expect("send");
+
+    let inputs = h
+        .eventually(|h| (h.inputs("web").len() == 2).then(|| h.inputs("web")))
+        .await
+        .expect("hook and command sent");
+    assert!(
+        inputs[0].starts_with(r#" [ -n "$BASH_VERSION$ZSH_VERSION" ] && eval '"#),
+        "{inputs:?}"
+    );
+    assert!(inputs[0].contains("__psh_hook"), "{inputs:?}");
+    assert_eq!(inputs[1], "uptime");
+}
+
+#[tokio::test]
+async fn pane_view_declines_guarded_commands_without_prompting() {
+    let mut h = Harness::new().shell("db", MockScript::new()).confirm(true);
+    h.router().register_entry(
+        "db".to_string(),
+        Entry::Shell(ShellSpec::Local {
+            program: "mock".to_string(),
+            tags: vec!["prod".to_string()],
+            readonly: false,
+            on_connect: None,
+        }),
+    );
+
+    h.router()
+        .set_policy(Policy::from_config(None).expect("default policy"));
+
+    h.exec("db: rm -rf /srv")
+        .await
+        .expect("confirmed outside panes");
+    h.router().pane_state().set_active(true);
+    let err = h.exec("db: rm -rf /srv").await.expect_err("declined");
+
+    assert!(err.to_string().contains("leave the pane view"), "{err}");
+    let inputs = h
+        .eventually(|h| (!h.inputs("db").is_empty()).then(|| h.inputs("db")))
+        .await;
+    assert_eq!(inputs, Some(vec!["rm -rf /srv".to_string()]));
+}
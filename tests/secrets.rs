@@ -0,0 +1,222 @@
+use std::{
+    fs,
+    time::{Duration, Instant},
+};
+
+use psh::{
+    harness::Harness,
+    registry::Entry,
+    repl::{Policy, ShareMode},
+    runtime::AuditLog,
+    shell::{ShellSpec, mock::MockScript},
+};
+
+fn login(password: &str) -> MockScript {
+    MockScript::new()
+        .banner("router login ok\nPassword: ")
+        .expect(password)
+        .respond("Welcome\nrouter> ")
+        .expect("hunter")
+        .respond("Permission denied\nPassword: ")
+}
+
+#[tokio::test]
+async fn answers_connect_prompts_from_the_secrets_source() {
+    let mut h = Harness::new()
+        .remote("router", login("s3cret"))
+        .secret("router", "s3cret");
+
+    let out = h.exec("router: show version").await.expect("send");
+
+    assert!(!out.contains("s3cret"), "{out}");
+    let inputs = h
+        .eventually(|h| (h.inputs("router").len() == 2).then(|| h.inputs("router")))
+        .await;
+    assert_eq!(
+        inputs,
+        Some(["s3cret", "show version"].map(String::from).to_vec())
+    );
+}
+
+#[tokio::test]
+async fn stops_watching_the_login_once_the_shell_prompt_shows() {
+    let mut h = Harness::new()
+        .remote("router", login("s3cret"))
+        .secret("router", "s3cret");
+
+    let started = Instant::now();
+    h.exec("router: show version").await.expect("send");
+
+    assert!(
+        started.elapsed() < Duration::from_millis(900),
+        "{:?}",
+        started.elapsed()
+    );
+}
+
+#[tokio::test]
+async fn asks_locally_when_the_stored_password_is_rejected() {
+    let mut h = Harness::new()
+        .remote("router", login("s3cret"))
+        .secret("router", "hunter1")
+        .typed_secret("s3cret");
+
+    h.exec("router: show version").await.expect("send");
+
+    let inputs = h
+        .eventually(|h| (h.inputs("router").len() == 3).then(|| h.inputs("router")))
+        .await;
+    assert_eq!(
+        inputs,
+        Some(
+            ["hunter1", "s3cret", "show version"]
+                .map(String::from)
+                .to_vec()
+        )
+    );
+}
+
+#[tokio::test]
+async fn fails_the_connect_without_a_password() {
+    let mut h = Harness::new().remote("router", login("s3cret"));
+
+    let err = h.exec("router: show version").await.expect_err("no secret");
+
+    assert!(err.to_string().contains("no password for router"), "{err}");
+    assert!(h.inputs("router").is_empty());
+}
+
+#[tokio::test]
+async fn sends_secrets_on_request() {
+    let mut h = Harness::new()
+        .shell("web", MockScript::new())
+        .typed_secret("sudo-pass");
+
+    let out = h.exec("secret: ask web").await.expect("ask");
+
+    assert!(out.contains("password sent to web"), "{out}");
+    assert!(!out.contains("sudo-pass"), "{out}");
+    let inputs = h
+        .eventually(|h| (!h.inputs("web").is_empty()).then(|| h.inputs("web")))
+        .await;
+    assert_eq!(inputs, Some(vec!["sudo-pass".to_string()]));
+}
+
+fn sudo() -> MockScript {
+    MockScript::new()
+        .expect("sudo true")
+        .respond("[sudo] password for ops: ")
+        .expect("pa55")
+        .respond("\n$ ")
+}
+
+#[tokio::test]
+async fn answers_later_prompts_without_auditing_the_password() {
+    let path =
+        std::env::temp_dir().join(format!("psh-secrets-{}-audit", std::process::id()));
+    let _ = fs::remove_file(&path);
+    let mut h = Harness::new().shell("web", sudo());
+    let audit = AuditLog::open(&path, &[]).expect("audit");
+    h.router().set_audit_log(Some(audit));
+
+    h.exec("web: sudo true").await.expect("send");
+    assert!(
+        h.eventually(|h| h.prompting("web").then_some(()))
+            .await
+            .is_some()
+    );
+    h.exec("web: pa55").await.expect("answer");
+    assert!(h.wait_for_output("web", "$ ").await);
+    h.exec("web: ls").await.expect("send");
+
+    let inputs = h
+        .eventually(|h| (h.inputs("web").len() == 3).then(|| h.inputs("web")))
+        .await;
+    assert_eq!(
+        inputs,
+        Some(["sudo true", "pa55", "ls"].map(String::from).to_vec())
+    );
+    let audited = fs::read_to_string(&path).expect("audit log");
+    fs::remove_file(&path).ok();
+    assert!(audited.contains("sudo true"), "{audited}");
+    assert!(audited.contains("\"ls\""), "{audited}");
+    assert!(!audited.contains("pa55"), "{audited}");
+}
+
+#[tokio::test]
+async fn guards_commands_typed_at_a_password_prompt() {
+    let mut h = Harness::new().shell("db", sudo());
+    h.router().register_entry(
+        "db".to_string(),
+        Entry::Shell(ShellSpec::Local {
+            program: "mock".to_string(),
+            tags: vec!["prod".to_string()],
+            readonly: false,
+            on_connect: None,
+        }),
+    );
+    h.router()
+        .set_policy(Policy::from_config(None).expect("default policy"));
+
+    h.exec("db: sudo true").await.expect("send");
+    assert!(
+        h.eventually(|h| h.prompting("db").then_some(()))
+            .await
+            .is_some()
+    );
+    let err = h.exec("db: rm -rf /srv").await.expect_err("guarded");
+
+    assert!(err.to_string().contains("confirmation declined"), "{err}");
+    assert_eq!(h.inputs("db"), vec!["sudo true"]);
+}
+
+#[tokio::test]
+async fn keeps_viewer_answers_out_of_recordings() {
+    let path = std::env::temp_dir()
+        .join(format!("psh-secrets-{}-viewer.cast", std::process::id()));
+    let mut h = Harness::new().shell("web", sudo());
+    h.exec("web: sudo true").await.expect("send");
+    h.router()
+        .start_recording("web", Some(path.clone()))
+        .await
+        .expect("record");
+    h.router()
+        .share_session("web", ShareMode::Write)
+        .expect("share");
+    let joined = h
+        .router()
+        .join_session("web", ShareMode::Write)
+        .await
+        .expect("join");
+    assert!(
+        h.eventually(|h| h.prompting("web").then_some(()))
+            .await
+            .is_some()
+    );
+
+    for key in ["p", "a", "5", "5", "\r"] {
+        h.router()
+            .viewer_input(joined.id, key.as_bytes().to_vec())
+            .await
+            .expect("type");
+    }
+    assert!(h.wait_for_output("web", "$ ").await);
+    h.router()
+        .viewer_input(joined.id, b"ls\r".to_vec())
+        .await
+        .expect("type");
+    let inputs = h
+        .eventually(|h| (h.inputs("web").len() == 3).then(|| h.inputs("web")))
+        .await;
+    h.router().stop_recording("web").await.expect("stop");
+
+    assert_eq!(
+        inputs,
+        Some(["sudo true", "pa55", "ls"].map(String::from).to_vec())
+    );
+    let cast = fs::read_to_string(&path).expect("cast");
+    fs::remove_file(&path).ok();
+    assert!(cast.contains("ls"), "{cast}");
+    assert!(!cast.contains("pa55"), "{cast}");
+    assert!(!cast.contains("\"p\""), "{cast}");
+}